@@ -0,0 +1,58 @@
+//! Fuzzes the processor with mutated serialized inputs.
+//!
+//! Any input must either be processed into the same block every time, or be rejected with a typed
+//! error. A panic, e.g. from an index out of bounds or an unwrap, is a crash.
+
+#![no_main]
+
+use bincode::Options;
+use libfuzzer_sys::fuzz_target;
+use reth_primitives::B256;
+use revm::InMemoryDB;
+use sp1_reth_primitives::db::InMemoryDBHelper;
+use sp1_reth_primitives::processor::{state_root, EvmProcessor};
+use sp1_reth_primitives::SP1RethInput;
+
+/// The maximum number of bytes allocated while deserializing an input, so that mutated length
+/// prefixes do not exhaust the memory of the fuzzer.
+const MAX_INPUT_BYTES: u64 = 64 * 1024 * 1024;
+
+/// The outcome of processing an input: the hash of the block, or the phase and error it was
+/// rejected with.
+#[derive(Debug, PartialEq, Eq)]
+enum Outcome {
+    Block(B256),
+    Rejected(&'static str, String),
+}
+
+fn process(mut input: SP1RethInput) -> Outcome {
+    let db = match InMemoryDB::initialize(&mut input) {
+        Ok(db) => db,
+        Err(err) => return Outcome::Rejected("initialize database", err.to_string()),
+    };
+    let mut executor = EvmProcessor::new(input, db).with_checkpoint_state_root(state_root);
+    if let Err(err) = executor.initialize() {
+        return Outcome::Rejected("initialize", err.to_string());
+    }
+    if let Err(err) = executor.execute() {
+        return Outcome::Rejected("execute", err.to_string());
+    }
+    if let Err(err) = executor.finalize() {
+        return Outcome::Rejected("finalize", err.to_string());
+    }
+    Outcome::Block(executor.header.unwrap().hash_slow())
+}
+
+fuzz_target!(|data: &[u8]| {
+    // Cache files of the script are accepted as seeds: the metadata after the input is ignored.
+    let Ok(input) = bincode::options()
+        .with_fixint_encoding()
+        .allow_trailing_bytes()
+        .with_limit(MAX_INPUT_BYTES)
+        .deserialize::<SP1RethInput>(data)
+    else {
+        return;
+    };
+    let outcome = process(input.clone());
+    assert_eq!(outcome, process(input), "processing is not deterministic");
+});
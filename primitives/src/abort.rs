@@ -0,0 +1,85 @@
+//! Records committed by the program when it aborts the execution of a block.
+
+use crate::mpt::keccak;
+use crate::SP1RethInput;
+use reth_primitives::B256;
+use serde::{Deserialize, Serialize};
+
+/// The phase of the program in which an abort happened.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AbortPhase {
+    /// Building the database from the witness and validating the header.
+    Initialize,
+    /// Executing the transactions of the block.
+    Execute,
+    /// Computing the post-state of the block.
+    Finalize,
+}
+
+/// A record of an abort of the program.
+///
+/// The program panics with this record, so that no proof exists for a block it failed to execute.
+/// It is only committed together with an [InvalidityRecord], when the failure proves the block
+/// invalid.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AbortRecord {
+    /// The hash of the parent of the block whose execution aborted.
+    pub parent_hash: B256,
+
+    /// The number of the block whose execution aborted.
+    pub block_number: u64,
+
+    /// The phase in which the program aborted.
+    pub phase: AbortPhase,
+
+    /// The code of the error, unique within its phase.
+    pub code: u32,
+
+    /// The Keccak-256 hash of the human readable error message.
+    pub detail_hash: B256,
+}
+
+impl AbortRecord {
+    /// Creates a record of an abort on the block of the input, hashing the given error message.
+    pub fn new(input: &SP1RethInput, phase: AbortPhase, code: u32, detail: &str) -> Self {
+        Self {
+            parent_hash: input.parent_header.hash_slow(),
+            block_number: input.parent_header.number + 1,
+            phase,
+            code,
+            detail_hash: keccak(detail.as_bytes()).into(),
+        }
+    }
+}
+
+/// A record of the consensus rule violated by a block, committed after the abort record when the
+/// program proves a block invalid instead of aborting.
+///
+/// Unlike an abort, which may be caused by an incomplete witness, a record is only committed for
+/// violations of the block itself, so that fraud-proof systems can rely on it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InvalidityRecord {
+    /// The hash of the parent of the invalid block.
+    pub parent_hash: B256,
+
+    /// The number of the invalid block.
+    pub block_number: u64,
+
+    /// The hash of the claimed header of the invalid block.
+    pub block_hash: B256,
+
+    /// The root of the transactions of the invalid block.
+    pub transactions_root: B256,
+
+    /// The phase in which the violation was detected.
+    pub phase: AbortPhase,
+
+    /// The code of the error, as in abort records.
+    pub code: u32,
+
+    /// The index of the offending transaction, if a transaction violates the rules.
+    pub transaction_index: Option<u32>,
+
+    /// The Keccak-256 hash of the human readable error message.
+    pub detail_hash: B256,
+}
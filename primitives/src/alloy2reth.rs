@@ -12,13 +12,36 @@ use reth_primitives::Signature as RethSignature;
 use reth_primitives::TransactionSignedNoHash as RethTransaction;
 use reth_primitives::Withdrawal as RethWithdrawal;
 use reth_primitives::U256;
-use reth_primitives::{Transaction, TransactionSignedNoHash, TxEip1559, TxEip2930, TxLegacy};
+use reth_primitives::{
+    Transaction, TransactionSignedNoHash, TxEip1559, TxEip2930, TxEip4844, TxLegacy, B256,
+};
+use thiserror::Error as ThisError;
 
 /// A trait to convert from Alloy types to Reth types.
 pub trait IntoReth<T> {
     fn into_reth(self) -> T;
 }
 
+/// A trait to convert from Alloy types to Reth types that Reth cannot always represent.
+pub trait TryIntoReth<T> {
+    type Error;
+
+    fn try_into_reth(self) -> Result<T, Self::Error>;
+}
+
+/// Represents the reasons an Alloy transaction cannot be converted to a Reth transaction.
+#[derive(Debug, PartialEq, Eq, ThisError)]
+pub enum ConversionError {
+    /// Triggered by an EIP-7702 set-code transaction. Neither the transaction types of reth nor
+    /// the execution environment of revm can represent authorization lists yet.
+    #[error("EIP-7702 set-code transaction {0} is not supported")]
+    SetCodeTransaction(B256),
+
+    /// Triggered by a transaction of a type unknown to reth.
+    #[error("transaction {hash} has the unknown type {tx_type}")]
+    UnknownTxType { hash: B256, tx_type: u64 },
+}
+
 impl IntoReth<RethWithdrawal> for AlloyWithdrawal {
     fn into_reth(self) -> RethWithdrawal {
         RethWithdrawal {
@@ -30,8 +53,15 @@ impl IntoReth<RethWithdrawal> for AlloyWithdrawal {
     }
 }
 
-impl IntoReth<RethTransaction> for AlloyTransaction {
-    fn into_reth(self) -> RethTransaction {
+/// The transaction type of EIP-7702 set-code transactions.
+///
+/// Reference: https://eips.ethereum.org/EIPS/eip-7702
+pub const EIP7702_TX_TYPE: u64 = 4;
+
+impl TryIntoReth<RethTransaction> for AlloyTransaction {
+    type Error = ConversionError;
+
+    fn try_into_reth(self) -> Result<RethTransaction, ConversionError> {
         let tx_type: u64 = self
             .transaction_type
             .unwrap_or(U64::ZERO)
@@ -93,12 +123,48 @@ impl IntoReth<RethTransaction> for AlloyTransaction {
                         .collect(),
                 ),
             }),
-            _ => panic!("invalid tx type: {}", tx_type),
+            3 => Transaction::Eip4844(TxEip4844 {
+                chain_id: self.chain_id.unwrap().try_into().unwrap(),
+                nonce: self.nonce.try_into().unwrap(),
+                max_fee_per_gas: self.max_fee_per_gas.unwrap().try_into().unwrap(),
+                max_priority_fee_per_gas: self
+                    .max_priority_fee_per_gas
+                    .unwrap()
+                    .try_into()
+                    .unwrap(),
+                gas_limit: self.gas.try_into().unwrap(),
+                to: match self.to {
+                    None => reth_primitives::TransactionKind::Create,
+                    Some(to) => reth_primitives::TransactionKind::Call(to),
+                },
+                value: self.value.into(),
+                input: self.input,
+                access_list: reth_primitives::AccessList(
+                    self.access_list
+                        .unwrap()
+                        .into_iter()
+                        .map(|item| item.into_reth())
+                        .collect(),
+                ),
+                blob_versioned_hashes: self.blob_versioned_hashes,
+                max_fee_per_blob_gas: self.max_fee_per_blob_gas.unwrap().try_into().unwrap(),
+            }),
+            EIP7702_TX_TYPE => return Err(ConversionError::SetCodeTransaction(self.hash)),
+            _ => {
+                return Err(ConversionError::UnknownTxType {
+                    hash: self.hash,
+                    tx_type,
+                })
+            }
         };
-        TransactionSignedNoHash {
-            signature: self.signature.unwrap().into_reth(),
+        Ok(TransactionSignedNoHash {
+            // System transactions of some chains come without a signature.
+            signature: self
+                .signature
+                .map(|signature| signature.into_reth())
+                .unwrap_or_default(),
             transaction: inner_tx,
-        }
+        })
     }
 }
 
@@ -147,7 +213,7 @@ impl IntoReth<RethHeader> for AlloyHeader {
             extra_data: self.extra_data.0.into(),
             mix_hash: self.mix_hash.unwrap(),
             nonce: u64::from_be_bytes(self.nonce.unwrap().0),
-            base_fee_per_gas: Some(self.base_fee_per_gas.unwrap().try_into().unwrap()),
+            base_fee_per_gas: self.base_fee_per_gas.map(|x| x.try_into().unwrap()),
             blob_gas_used: self.blob_gas_used.map(|x| x.try_into().unwrap()),
             excess_blob_gas: self.excess_blob_gas.map(|x| x.try_into().unwrap()),
             parent_beacon_block_root: self.parent_beacon_block_root,
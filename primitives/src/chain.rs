@@ -0,0 +1,721 @@
+//! The hardfork schedules of the chains supported by SP1 Reth.
+
+use alloy_primitives::address;
+#[cfg(feature = "optimism")]
+use alloy_primitives::b256;
+#[cfg(feature = "optimism")]
+use reth_primitives::B256;
+use reth_primitives::{Address, BaseFeeParams};
+use revm::primitives::{fake_exponential, SpecId, GAS_PER_BLOB};
+use serde::{Deserialize, Serialize};
+
+/// The condition under which a hardfork activates.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ForkCondition {
+    /// The hardfork activates at the given block number.
+    Block(u64),
+    /// The hardfork activates at the given block timestamp.
+    Timestamp(u64),
+}
+
+impl ForkCondition {
+    /// Returns whether the condition is met by a block with the given number and timestamp.
+    pub fn is_active_at(&self, block_number: u64, timestamp: u64) -> bool {
+        match self {
+            ForkCondition::Block(activation) => block_number >= *activation,
+            ForkCondition::Timestamp(activation) => timestamp >= *activation,
+        }
+    }
+}
+
+/// The hardforks of the Ethereum protocol, in activation order.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum Hardfork {
+    Frontier,
+    Homestead,
+    Dao,
+    Tangerine,
+    SpuriousDragon,
+    Byzantium,
+    Constantinople,
+    Petersburg,
+    Istanbul,
+    MuirGlacier,
+    Berlin,
+    London,
+    ArrowGlacier,
+    GrayGlacier,
+    Paris,
+    Shanghai,
+    Cancun,
+    #[cfg(feature = "optimism")]
+    Bedrock,
+    #[cfg(feature = "optimism")]
+    Regolith,
+    #[cfg(feature = "optimism")]
+    Canyon,
+    #[cfg(feature = "optimism")]
+    Ecotone,
+}
+
+impl Hardfork {
+    /// Returns the [SpecId] used by revm to execute blocks under the rules of this hardfork.
+    pub fn spec_id(&self) -> SpecId {
+        match self {
+            Hardfork::Frontier => SpecId::FRONTIER,
+            Hardfork::Homestead => SpecId::HOMESTEAD,
+            Hardfork::Dao => SpecId::DAO_FORK,
+            Hardfork::Tangerine => SpecId::TANGERINE,
+            Hardfork::SpuriousDragon => SpecId::SPURIOUS_DRAGON,
+            Hardfork::Byzantium => SpecId::BYZANTIUM,
+            Hardfork::Constantinople => SpecId::CONSTANTINOPLE,
+            Hardfork::Petersburg => SpecId::PETERSBURG,
+            Hardfork::Istanbul => SpecId::ISTANBUL,
+            Hardfork::MuirGlacier => SpecId::MUIR_GLACIER,
+            Hardfork::Berlin => SpecId::BERLIN,
+            Hardfork::London => SpecId::LONDON,
+            Hardfork::ArrowGlacier => SpecId::ARROW_GLACIER,
+            Hardfork::GrayGlacier => SpecId::GRAY_GLACIER,
+            Hardfork::Paris => SpecId::MERGE,
+            Hardfork::Shanghai => SpecId::SHANGHAI,
+            Hardfork::Cancun => SpecId::CANCUN,
+            #[cfg(feature = "optimism")]
+            Hardfork::Bedrock => SpecId::BEDROCK,
+            #[cfg(feature = "optimism")]
+            Hardfork::Regolith => SpecId::REGOLITH,
+            #[cfg(feature = "optimism")]
+            Hardfork::Canyon => SpecId::CANYON,
+            #[cfg(feature = "optimism")]
+            Hardfork::Ecotone => SpecId::ECOTONE,
+        }
+    }
+}
+
+/// The transaction types of Ethereum: legacy, EIP-2930, EIP-1559 and EIP-4844 transactions.
+pub const ETHEREUM_TX_TYPES: [u8; 4] = [0, 1, 2, 3];
+
+/// The transaction types of OP Stack chains, which support deposits but no blob transactions.
+#[cfg(feature = "optimism")]
+pub const OPTIMISM_TX_TYPES: [u8; 4] = [0, 1, 2, 0x7e];
+
+/// Returns the hardfork introducing a typed transaction, or [None] for legacy transactions and
+/// unknown types.
+pub fn tx_type_hardfork(tx_type: u8) -> Option<Hardfork> {
+    match tx_type {
+        1 => Some(Hardfork::Berlin),
+        2 => Some(Hardfork::London),
+        3 => Some(Hardfork::Cancun),
+        #[cfg(feature = "optimism")]
+        0x7e => Some(Hardfork::Bedrock),
+        _ => None,
+    }
+}
+
+/// The address of the beacon roots contract.
+///
+/// Reference: https://eips.ethereum.org/EIPS/eip-4788
+pub const BEACON_ROOTS_ADDRESS: Address = address!("000F3df6D732807Ef1319fB7B8bB8522d0Beac02");
+
+/// The address used as the caller of system contract calls.
+///
+/// Reference: https://eips.ethereum.org/EIPS/eip-4788
+pub const SYSTEM_ADDRESS: Address = address!("fffffffffffffffffffffffffffffffffffffffe");
+
+/// The address of the create2deployer contract, which OP Stack chains deploy in the first block of
+/// Canyon.
+///
+/// Reference: https://github.com/ethereum-optimism/op-geth/blob/optimism/consensus/misc/create2deployer.go
+#[cfg(feature = "optimism")]
+pub const CREATE2_DEPLOYER_ADDRESS: Address = address!("13b0D85CcB8bf860b6b79AF3029fCA081AE9beF2");
+
+/// The hash of the code of the create2deployer contract.
+#[cfg(feature = "optimism")]
+pub const CREATE2_DEPLOYER_CODE_HASH: B256 =
+    b256!("b0550b5b431e30d38000efb7107aaa0ade03d48a7198a140edda9d27134468b2");
+
+/// The system contracts called by the protocol while executing a block.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SystemContracts {
+    /// The EIP-4788 contract storing the parent beacon block root, if the chain has one.
+    pub beacon_roots: Option<Address>,
+
+    /// The contract processing withdrawals through a system call, if the chain does not credit
+    /// withdrawals as plain balance increments.
+    pub withdrawals: Option<Address>,
+}
+
+impl SystemContracts {
+    /// Returns the system contracts of Ethereum.
+    pub fn ethereum() -> Self {
+        Self {
+            beacon_roots: Some(BEACON_ROOTS_ADDRESS),
+            withdrawals: None,
+        }
+    }
+}
+
+/// The parameters of the blob gas market of a chain.
+///
+/// Reference: https://eips.ethereum.org/EIPS/eip-4844#parameters
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlobParams {
+    /// The maximum number of blobs of a block.
+    pub max_blobs_per_block: u64,
+
+    /// The number of blobs per block the excess blob gas is measured against.
+    pub target_blobs_per_block: u64,
+
+    /// The update fraction of the blob gas price, which bounds how fast it changes.
+    pub update_fraction: u64,
+
+    /// The blob gas price of a block without excess blob gas, in wei.
+    pub min_blob_gas_price: u64,
+}
+
+impl BlobParams {
+    /// Returns the blob parameters of Ethereum.
+    pub fn ethereum() -> Self {
+        Self {
+            max_blobs_per_block: 6,
+            target_blobs_per_block: 3,
+            update_fraction: 3_338_477,
+            min_blob_gas_price: 1,
+        }
+    }
+
+    /// Returns the maximum blob gas of a block.
+    pub fn max_blob_gas_per_block(&self) -> u64 {
+        self.max_blobs_per_block * GAS_PER_BLOB
+    }
+
+    /// Returns the excess blob gas of a block from the blob gas fields of its parent.
+    pub fn excess_blob_gas(&self, parent_excess_blob_gas: u64, parent_blob_gas_used: u64) -> u64 {
+        (parent_excess_blob_gas + parent_blob_gas_used)
+            .saturating_sub(self.target_blobs_per_block * GAS_PER_BLOB)
+    }
+
+    /// Returns the blob gas price of a block with the given excess blob gas.
+    pub fn blob_gas_price(&self, excess_blob_gas: u64) -> u128 {
+        fake_exponential(
+            self.min_blob_gas_price,
+            excess_blob_gas,
+            self.update_fraction,
+        )
+    }
+}
+
+/// The consensus engine sealing the blocks of a chain, which determines the header fields and
+/// block rewards the protocol adds on top of transaction execution.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Consensus {
+    /// Proof of work until Paris, and the beacon chain afterwards.
+    #[default]
+    Ethereum,
+    /// The Bor engine of Polygon PoS, whose validators seal blocks in their extra data.
+    #[cfg(feature = "polygon")]
+    Bor,
+}
+
+/// The configuration of the chain a block is executed on.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChainConfig {
+    /// The EIP-155 chain id of the chain.
+    pub chain_id: u64,
+
+    /// The consensus engine of the chain.
+    pub consensus: Consensus,
+
+    /// The parameters used to compute the base fee of a block from its parent, with their
+    /// activation conditions, in activation order.
+    pub base_fee_params: Vec<(ForkCondition, BaseFeeParams)>,
+
+    /// The system contracts of the chain.
+    pub system_contracts: SystemContracts,
+
+    /// The accounts credited with the base fee of every transaction, with their activation
+    /// conditions, in activation order. The base fee is burnt while none of them is active.
+    pub fee_collector: Vec<(ForkCondition, Address)>,
+
+    /// The transaction types accepted by the chain.
+    pub tx_types: Vec<u8>,
+
+    /// The parameters of the blob gas market, which apply from Cancun.
+    pub blob_params: BlobParams,
+
+    /// The first hardfork whose blocks can be executed. Earlier blocks follow rules that are not
+    /// implemented, such as another consensus engine.
+    pub supported_from: Hardfork,
+
+    /// The activation of the first hardfork of the chain whose rules are not implemented, if any.
+    /// Its blocks would otherwise be executed under the rules of the last implemented hardfork.
+    pub supported_until: Option<ForkCondition>,
+
+    /// The hardforks of the chain and their activation conditions, in activation order.
+    pub hardforks: Vec<(Hardfork, ForkCondition)>,
+}
+
+impl ChainConfig {
+    /// Returns the configuration of Ethereum mainnet.
+    ///
+    /// Reference: https://github.com/ethereum/execution-specs/tree/master/network-upgrades/mainnet-upgrades
+    pub fn mainnet() -> Self {
+        Self {
+            chain_id: 1,
+            consensus: Consensus::Ethereum,
+            base_fee_params: vec![(ForkCondition::Block(0), BaseFeeParams::ethereum())],
+            system_contracts: SystemContracts::ethereum(),
+            fee_collector: Vec::new(),
+            tx_types: ETHEREUM_TX_TYPES.to_vec(),
+            blob_params: BlobParams::ethereum(),
+            supported_from: Hardfork::Frontier,
+            // Prague.
+            supported_until: Some(ForkCondition::Timestamp(1_746_612_311)),
+            hardforks: vec![
+                (Hardfork::Frontier, ForkCondition::Block(0)),
+                (Hardfork::Homestead, ForkCondition::Block(1_150_000)),
+                (Hardfork::Dao, ForkCondition::Block(1_920_000)),
+                (Hardfork::Tangerine, ForkCondition::Block(2_463_000)),
+                (Hardfork::SpuriousDragon, ForkCondition::Block(2_675_000)),
+                (Hardfork::Byzantium, ForkCondition::Block(4_370_000)),
+                (Hardfork::Constantinople, ForkCondition::Block(7_280_000)),
+                (Hardfork::Petersburg, ForkCondition::Block(7_280_000)),
+                (Hardfork::Istanbul, ForkCondition::Block(9_069_000)),
+                (Hardfork::MuirGlacier, ForkCondition::Block(9_200_000)),
+                (Hardfork::Berlin, ForkCondition::Block(12_244_000)),
+                (Hardfork::London, ForkCondition::Block(12_965_000)),
+                (Hardfork::ArrowGlacier, ForkCondition::Block(13_773_000)),
+                (Hardfork::GrayGlacier, ForkCondition::Block(15_050_000)),
+                (Hardfork::Paris, ForkCondition::Block(15_537_394)),
+                (Hardfork::Shanghai, ForkCondition::Timestamp(1_681_338_455)),
+                (Hardfork::Cancun, ForkCondition::Timestamp(1_710_338_135)),
+            ],
+        }
+    }
+
+    /// Returns the configuration of the Sepolia testnet.
+    ///
+    /// Reference: https://github.com/eth-clients/sepolia
+    pub fn sepolia() -> Self {
+        Self {
+            chain_id: 11_155_111,
+            consensus: Consensus::Ethereum,
+            base_fee_params: vec![(ForkCondition::Block(0), BaseFeeParams::ethereum())],
+            system_contracts: SystemContracts::ethereum(),
+            fee_collector: Vec::new(),
+            tx_types: ETHEREUM_TX_TYPES.to_vec(),
+            blob_params: BlobParams::ethereum(),
+            supported_from: Hardfork::Frontier,
+            // Prague.
+            supported_until: Some(ForkCondition::Timestamp(1_741_159_776)),
+            hardforks: vec![
+                (Hardfork::Frontier, ForkCondition::Block(0)),
+                (Hardfork::Homestead, ForkCondition::Block(0)),
+                (Hardfork::Tangerine, ForkCondition::Block(0)),
+                (Hardfork::SpuriousDragon, ForkCondition::Block(0)),
+                (Hardfork::Byzantium, ForkCondition::Block(0)),
+                (Hardfork::Constantinople, ForkCondition::Block(0)),
+                (Hardfork::Petersburg, ForkCondition::Block(0)),
+                (Hardfork::Istanbul, ForkCondition::Block(0)),
+                (Hardfork::MuirGlacier, ForkCondition::Block(0)),
+                (Hardfork::Berlin, ForkCondition::Block(0)),
+                (Hardfork::London, ForkCondition::Block(0)),
+                (Hardfork::Paris, ForkCondition::Block(1_735_371)),
+                (Hardfork::Shanghai, ForkCondition::Timestamp(1_677_557_088)),
+                (Hardfork::Cancun, ForkCondition::Timestamp(1_706_655_072)),
+            ],
+        }
+    }
+
+    /// Returns the configuration of the Holesky testnet.
+    ///
+    /// Reference: https://github.com/eth-clients/holesky
+    pub fn holesky() -> Self {
+        Self {
+            chain_id: 17_000,
+            consensus: Consensus::Ethereum,
+            base_fee_params: vec![(ForkCondition::Block(0), BaseFeeParams::ethereum())],
+            system_contracts: SystemContracts::ethereum(),
+            fee_collector: Vec::new(),
+            tx_types: ETHEREUM_TX_TYPES.to_vec(),
+            blob_params: BlobParams::ethereum(),
+            supported_from: Hardfork::Frontier,
+            // Prague.
+            supported_until: Some(ForkCondition::Timestamp(1_740_434_112)),
+            hardforks: vec![
+                (Hardfork::Frontier, ForkCondition::Block(0)),
+                (Hardfork::Homestead, ForkCondition::Block(0)),
+                (Hardfork::Tangerine, ForkCondition::Block(0)),
+                (Hardfork::SpuriousDragon, ForkCondition::Block(0)),
+                (Hardfork::Byzantium, ForkCondition::Block(0)),
+                (Hardfork::Constantinople, ForkCondition::Block(0)),
+                (Hardfork::Petersburg, ForkCondition::Block(0)),
+                (Hardfork::Istanbul, ForkCondition::Block(0)),
+                (Hardfork::MuirGlacier, ForkCondition::Block(0)),
+                (Hardfork::Berlin, ForkCondition::Block(0)),
+                (Hardfork::London, ForkCondition::Block(0)),
+                (Hardfork::Paris, ForkCondition::Block(0)),
+                (Hardfork::Shanghai, ForkCondition::Timestamp(1_696_000_704)),
+                (Hardfork::Cancun, ForkCondition::Timestamp(1_707_305_664)),
+            ],
+        }
+    }
+
+    /// Returns the configuration of Gnosis Chain.
+    ///
+    /// Gnosis credits the base fee to a fee collector instead of burning it, and processes
+    /// withdrawals through a system call to its deposit contract.
+    ///
+    /// Reference: https://github.com/gnosischain/specs/tree/master/execution
+    pub fn gnosis() -> Self {
+        Self {
+            chain_id: 100,
+            consensus: Consensus::Ethereum,
+            // Reference: https://github.com/NethermindEth/nethermind/blob/master/src/Nethermind/Chains/gnosis.json
+            base_fee_params: vec![(
+                ForkCondition::Block(0),
+                BaseFeeParams {
+                    max_change_denominator: 8,
+                    elasticity_multiplier: 2,
+                },
+            )],
+            system_contracts: SystemContracts {
+                beacon_roots: Some(BEACON_ROOTS_ADDRESS),
+                withdrawals: Some(address!("0B98057eA310F4d31F2a452B414647007d1645d9")),
+            },
+            fee_collector: vec![(
+                ForkCondition::Block(0),
+                address!("6BBe78ee9e474842Dbd4AB4987b3CeFE88426A92"),
+            )],
+            tx_types: ETHEREUM_TX_TYPES.to_vec(),
+            // Gnosis targets one blob per block and prices blob gas from 1 gwei, as set by the
+            // eip4844 parameters of its chain spec.
+            blob_params: BlobParams {
+                max_blobs_per_block: 2,
+                target_blobs_per_block: 1,
+                update_fraction: 1_112_826,
+                min_blob_gas_price: 1_000_000_000,
+            },
+            // The blocks before the merge were sealed by AuRa, whose block rewards are minted by
+            // a contract.
+            supported_from: Hardfork::Paris,
+            // Prague.
+            supported_until: Some(ForkCondition::Timestamp(1_746_021_820)),
+            hardforks: vec![
+                (Hardfork::Frontier, ForkCondition::Block(0)),
+                (Hardfork::Homestead, ForkCondition::Block(0)),
+                (Hardfork::Tangerine, ForkCondition::Block(0)),
+                (Hardfork::SpuriousDragon, ForkCondition::Block(0)),
+                (Hardfork::Byzantium, ForkCondition::Block(0)),
+                (Hardfork::Constantinople, ForkCondition::Block(1_604_400)),
+                (Hardfork::Petersburg, ForkCondition::Block(2_508_800)),
+                (Hardfork::Istanbul, ForkCondition::Block(7_298_030)),
+                (Hardfork::Berlin, ForkCondition::Block(16_101_500)),
+                (Hardfork::London, ForkCondition::Block(19_040_000)),
+                (Hardfork::Paris, ForkCondition::Block(25_349_536)),
+                (Hardfork::Shanghai, ForkCondition::Timestamp(1_690_889_660)),
+                (Hardfork::Cancun, ForkCondition::Timestamp(1_710_181_820)),
+            ],
+        }
+    }
+
+    /// Returns the configuration of Polygon PoS.
+    ///
+    /// Polygon sends the base fee to a burn contract, and its Bor engine seals blocks without
+    /// block rewards. The state syncs and span commits Bor applies at the end of some sprints are
+    /// not executed, so only blocks without them can be proven.
+    ///
+    /// Reference: https://github.com/maticnetwork/bor/blob/master/params/config.go
+    #[cfg(feature = "polygon")]
+    pub fn polygon() -> Self {
+        const LONDON_BLOCK: u64 = 23_850_000;
+        const DELHI_BLOCK: u64 = 38_189_056;
+        const SHANGHAI_BLOCK: u64 = 50_523_000;
+        Self {
+            chain_id: 137,
+            consensus: Consensus::Bor,
+            // Delhi raised the max change denominator from 8 to 16. Bor picks the denominator from
+            // the number of the parent, so the change applies from the block after Delhi.
+            base_fee_params: vec![
+                (
+                    ForkCondition::Block(0),
+                    BaseFeeParams {
+                        max_change_denominator: 8,
+                        elasticity_multiplier: 2,
+                    },
+                ),
+                (
+                    ForkCondition::Block(DELHI_BLOCK + 1),
+                    BaseFeeParams {
+                        max_change_denominator: 16,
+                        elasticity_multiplier: 2,
+                    },
+                ),
+            ],
+            system_contracts: SystemContracts {
+                beacon_roots: None,
+                withdrawals: None,
+            },
+            // The burn contract, which was replaced together with the Shanghai hardfork of Bor.
+            fee_collector: vec![
+                (
+                    ForkCondition::Block(LONDON_BLOCK),
+                    address!("70bcA57F4579f58670aB2d18Ef16e02C17553C38"),
+                ),
+                (
+                    ForkCondition::Block(SHANGHAI_BLOCK),
+                    address!("7A8ed27F4C30512326878652d20fC85727401854"),
+                ),
+            ],
+            tx_types: vec![0, 1, 2],
+            blob_params: BlobParams::ethereum(),
+            supported_from: Hardfork::Frontier,
+            // Ahmedabad, which raised the maximum code size.
+            supported_until: Some(ForkCondition::Block(62_278_656)),
+            hardforks: vec![
+                (Hardfork::Frontier, ForkCondition::Block(0)),
+                (Hardfork::Homestead, ForkCondition::Block(0)),
+                (Hardfork::Tangerine, ForkCondition::Block(0)),
+                (Hardfork::SpuriousDragon, ForkCondition::Block(0)),
+                (Hardfork::Byzantium, ForkCondition::Block(0)),
+                (Hardfork::Constantinople, ForkCondition::Block(0)),
+                (Hardfork::Petersburg, ForkCondition::Block(0)),
+                (Hardfork::Istanbul, ForkCondition::Block(3_395_000)),
+                (Hardfork::MuirGlacier, ForkCondition::Block(3_395_000)),
+                (Hardfork::Berlin, ForkCondition::Block(14_750_000)),
+                (Hardfork::London, ForkCondition::Block(LONDON_BLOCK)),
+                (Hardfork::Shanghai, ForkCondition::Block(SHANGHAI_BLOCK)),
+                (Hardfork::Cancun, ForkCondition::Block(54_876_000)),
+            ],
+        }
+    }
+
+    /// Returns the configuration of OP Mainnet.
+    ///
+    /// Reference: https://github.com/ethereum-optimism/superchain-registry
+    #[cfg(feature = "optimism")]
+    pub fn optimism_mainnet() -> Self {
+        const BEDROCK_BLOCK: u64 = 105_235_063;
+        const CANYON_TIMESTAMP: u64 = 1_704_992_401;
+        const ECOTONE_TIMESTAMP: u64 = 1_710_374_401;
+        Self {
+            chain_id: 10,
+            consensus: Consensus::Ethereum,
+            // Canyon raised the max change denominator from 50 to 250.
+            base_fee_params: vec![
+                (
+                    ForkCondition::Block(0),
+                    BaseFeeParams {
+                        max_change_denominator: 50,
+                        elasticity_multiplier: 6,
+                    },
+                ),
+                (
+                    ForkCondition::Timestamp(CANYON_TIMESTAMP),
+                    BaseFeeParams {
+                        max_change_denominator: 250,
+                        elasticity_multiplier: 6,
+                    },
+                ),
+            ],
+            system_contracts: SystemContracts::ethereum(),
+            fee_collector: Vec::new(),
+            tx_types: OPTIMISM_TX_TYPES.to_vec(),
+            blob_params: BlobParams::ethereum(),
+            // The blocks before Bedrock were produced by the legacy sequencer and cannot be
+            // re-executed.
+            supported_from: Hardfork::Bedrock,
+            // Fjord.
+            supported_until: Some(ForkCondition::Timestamp(1_720_627_201)),
+            hardforks: vec![
+                (Hardfork::Frontier, ForkCondition::Block(0)),
+                (Hardfork::Homestead, ForkCondition::Block(0)),
+                (Hardfork::Tangerine, ForkCondition::Block(0)),
+                (Hardfork::SpuriousDragon, ForkCondition::Block(0)),
+                (Hardfork::Byzantium, ForkCondition::Block(0)),
+                (Hardfork::Constantinople, ForkCondition::Block(0)),
+                (Hardfork::Petersburg, ForkCondition::Block(0)),
+                (Hardfork::Istanbul, ForkCondition::Block(0)),
+                (Hardfork::MuirGlacier, ForkCondition::Block(0)),
+                (Hardfork::Berlin, ForkCondition::Block(3_950_000)),
+                (Hardfork::London, ForkCondition::Block(BEDROCK_BLOCK)),
+                (Hardfork::ArrowGlacier, ForkCondition::Block(BEDROCK_BLOCK)),
+                (Hardfork::GrayGlacier, ForkCondition::Block(BEDROCK_BLOCK)),
+                (Hardfork::Paris, ForkCondition::Block(BEDROCK_BLOCK)),
+                (Hardfork::Bedrock, ForkCondition::Block(BEDROCK_BLOCK)),
+                (Hardfork::Regolith, ForkCondition::Timestamp(0)),
+                (
+                    Hardfork::Shanghai,
+                    ForkCondition::Timestamp(CANYON_TIMESTAMP),
+                ),
+                (Hardfork::Canyon, ForkCondition::Timestamp(CANYON_TIMESTAMP)),
+                (
+                    Hardfork::Cancun,
+                    ForkCondition::Timestamp(ECOTONE_TIMESTAMP),
+                ),
+                (
+                    Hardfork::Ecotone,
+                    ForkCondition::Timestamp(ECOTONE_TIMESTAMP),
+                ),
+            ],
+        }
+    }
+
+    /// Returns whether the hardfork is active for a block with the given number and timestamp.
+    pub fn is_active(&self, hardfork: Hardfork, block_number: u64, timestamp: u64) -> bool {
+        self.hardforks.iter().any(|(fork, condition)| {
+            *fork == hardfork && condition.is_active_at(block_number, timestamp)
+        })
+    }
+
+    /// Returns whether a block with the given timestamp, child of the block with the given number
+    /// and timestamp, is the first block of the hardfork: the hardfork is active for the block but
+    /// not for its parent, wherever the activation falls between them.
+    pub fn is_activation_block(
+        &self,
+        hardfork: Hardfork,
+        parent_number: u64,
+        parent_timestamp: u64,
+        timestamp: u64,
+    ) -> bool {
+        self.is_active(hardfork, parent_number.saturating_add(1), timestamp)
+            && !self.is_active(hardfork, parent_number, parent_timestamp)
+    }
+
+    /// Returns the base fee parameters in effect for a block with the given number and timestamp.
+    pub fn base_fee_params_at(&self, block_number: u64, timestamp: u64) -> BaseFeeParams {
+        self.base_fee_params
+            .iter()
+            .rev()
+            .find(|(condition, _)| condition.is_active_at(block_number, timestamp))
+            .map(|(_, params)| *params)
+            .expect("no base fee parameters active at the genesis of the chain")
+    }
+
+    /// Returns the account credited with the base fee of a block with the given number and
+    /// timestamp, or [None] if the base fee is burnt.
+    pub fn fee_collector_at(&self, block_number: u64, timestamp: u64) -> Option<Address> {
+        self.fee_collector
+            .iter()
+            .rev()
+            .find(|(condition, _)| condition.is_active_at(block_number, timestamp))
+            .map(|(_, address)| *address)
+    }
+
+    /// Returns whether blocks carry the header fields introduced by the hardfork: the withdrawals
+    /// root of Shanghai and the blob gas fields of Cancun. Bor activates the execution changes of
+    /// these hardforks without their header fields.
+    pub fn has_header_fields(&self, hardfork: Hardfork, block_number: u64, timestamp: u64) -> bool {
+        self.consensus == Consensus::Ethereum && self.is_active(hardfork, block_number, timestamp)
+    }
+
+    /// Returns whether the chain accepts the transaction type in a block with the given number and
+    /// timestamp.
+    pub fn is_tx_type_active(&self, tx_type: u8, block_number: u64, timestamp: u64) -> bool {
+        self.tx_types.contains(&tx_type)
+            && tx_type_hardfork(tx_type).map_or(true, |hardfork| {
+                self.is_active(hardfork, block_number, timestamp)
+            })
+    }
+
+    /// Returns the [SpecId] of the latest hardfork active for a block with the given number and
+    /// timestamp. Blocks from [ChainConfig::supported_until] on must be rejected beforehand.
+    pub fn spec_id(&self, block_number: u64, timestamp: u64) -> SpecId {
+        self.hardforks
+            .iter()
+            .filter(|(_, condition)| condition.is_active_at(block_number, timestamp))
+            .map(|(fork, _)| fork.spec_id())
+            .max()
+            .expect("no hardfork active at the genesis of the chain")
+    }
+}
+
+impl Default for ChainConfig {
+    /// Defaults to the configuration of Ethereum mainnet.
+    fn default() -> Self {
+        Self::mainnet()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_schedules() {
+        // Every chain has base fee parameters from its genesis.
+        let mainnet = ChainConfig::mainnet();
+        assert_eq!(mainnet.base_fee_params_at(0, 0), BaseFeeParams::ethereum());
+        assert_eq!(mainnet.fee_collector_at(19_000_000, 0), None);
+
+        // The blob gas market of Ethereum matches the constants of revm, while Gnosis prices
+        // blob gas from 1 gwei.
+        let ethereum = mainnet.blob_params;
+        assert_eq!(
+            ethereum.max_blob_gas_per_block(),
+            revm::primitives::MAX_BLOB_GAS_PER_BLOCK
+        );
+        for (excess, used) in [(0, 0), (0, 6 * GAS_PER_BLOB), (10_000_000, GAS_PER_BLOB)] {
+            assert_eq!(
+                ethereum.excess_blob_gas(excess, used),
+                revm::primitives::calc_excess_blob_gas(excess, used)
+            );
+            assert_eq!(
+                ethereum.blob_gas_price(excess),
+                revm::primitives::calc_blob_gasprice(excess)
+            );
+        }
+        let gnosis = ChainConfig::gnosis().blob_params;
+        assert_eq!(gnosis.max_blob_gas_per_block(), 2 * GAS_PER_BLOB);
+        assert_eq!(gnosis.excess_blob_gas(0, 2 * GAS_PER_BLOB), GAS_PER_BLOB);
+        assert_eq!(gnosis.blob_gas_price(0), 1_000_000_000);
+        assert!(gnosis.blob_gas_price(GAS_PER_BLOB) > 1_000_000_000);
+    }
+
+    #[test]
+    #[cfg(feature = "optimism")]
+    fn test_optimism_schedules() {
+        // Canyon raised the max change denominator.
+        let optimism = ChainConfig::optimism_mainnet();
+        let canyon = 1_704_992_401;
+        let denominator = |timestamp| {
+            optimism
+                .base_fee_params_at(110_000_000, timestamp)
+                .max_change_denominator
+        };
+        assert_eq!(denominator(canyon - 1), 50);
+        assert_eq!(denominator(canyon), 250);
+
+        // The create2deployer is only deployed in the first block of Canyon, even if no block
+        // lands on the activation timestamp.
+        let number = 110_000_000;
+        let is_first = |parent_timestamp, timestamp| {
+            optimism.is_activation_block(Hardfork::Canyon, number, parent_timestamp, timestamp)
+        };
+        assert!(is_first(canyon - 2, canyon));
+        assert!(is_first(canyon - 1, canyon + 1));
+        assert!(!is_first(canyon - 4, canyon - 2));
+        assert!(!is_first(canyon, canyon + 2));
+    }
+
+    #[test]
+    #[cfg(feature = "polygon")]
+    fn test_polygon_schedules() {
+        // Bor raised the max change denominator from the child of the Delhi block.
+        let polygon = ChainConfig::polygon();
+        let denominator = |number| polygon.base_fee_params_at(number, 0).max_change_denominator;
+        assert_eq!(denominator(23_850_000), 8);
+        assert_eq!(denominator(38_189_056), 8);
+        assert_eq!(denominator(38_189_057), 16);
+
+        // The base fee is sent to the burn contract in effect at the height of the block.
+        assert_eq!(polygon.fee_collector_at(23_849_999, 0), None);
+        assert_eq!(
+            polygon.fee_collector_at(50_522_999, 0),
+            Some(address!("70bcA57F4579f58670aB2d18Ef16e02C17553C38"))
+        );
+        assert_eq!(
+            polygon.fee_collector_at(50_523_000, 0),
+            Some(address!("7A8ed27F4C30512326878652d20fC85727401854"))
+        );
+    }
+}
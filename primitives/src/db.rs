@@ -21,15 +21,76 @@ use crate::mpt::StateAccount;
 use crate::mpt::KECCAK_EMPTY;
 use crate::SP1RethInput;
 
-use anyhow::{anyhow, Result};
+use anyhow::Result;
 use hashbrown::hash_map::Entry;
 use reth_primitives::revm_primitives::{AccountInfo, Bytecode};
 use reth_primitives::Bytes;
-use reth_primitives::{Address, B256, U256};
+use reth_primitives::{Address, Header, B256, U256};
 use revm::db::AccountState;
 use revm::db::DbAccount;
 use revm::db::InMemoryDB;
-use revm::primitives::HashMap;
+use revm::primitives::{Account, HashMap};
+use revm::{Database, DatabaseCommit};
+use std::borrow::Borrow;
+use thiserror::Error as ThisError;
+
+/// Represents the errors that can occur while reading from the witness database.
+#[derive(Debug, ThisError)]
+pub enum DbError {
+    /// Triggered when an account is not part of the witness.
+    #[error("account {address} is not part of the witness")]
+    AccountNotFound { address: Address },
+
+    /// Triggered when a storage slot is neither proven present nor proven absent by the witness.
+    #[error("storage slot {index} of account {address} is not part of the witness")]
+    StorageSlotNotFound { address: Address, index: U256 },
+
+    /// Triggered when the storage trie of an account does not match the storage root of the
+    /// account in the state trie.
+    #[error("storage trie of account {address} has root {actual}, expected {expected}")]
+    StorageRootMismatch {
+        address: Address,
+        expected: B256,
+        actual: B256,
+    },
+
+    /// Triggered when the bytecode of an account is not part of the witness.
+    #[error("bytecode {code_hash} of account {address} is not part of the witness")]
+    MissingBytecode { address: Address, code_hash: B256 },
+
+    /// Triggered when an ancestor header does not extend the chain of the parent header.
+    #[error("invalid ancestor header {number}: {reason}")]
+    InvalidAncestor { number: u64, reason: &'static str },
+
+    /// Triggered when the state trie of the witness does not match the state root of the parent
+    /// header, which is required to prove a block invalid.
+    #[error("state trie has root {actual}, expected the parent state root {expected}")]
+    StateRootMismatch { expected: B256, actual: B256 },
+
+    /// Triggered when bytecode looked up by its hash is not part of the witness.
+    #[error("bytecode {code_hash} is not part of the witness")]
+    CodeNotFound { code_hash: B256 },
+
+    /// Triggered when the hash of a recent block is not part of the ancestor headers.
+    #[error("hash of block {number} is not part of the witness")]
+    BlockHashNotFound { number: U256 },
+}
+
+impl DbError {
+    /// Returns the code of the error used in abort records.
+    pub fn code(&self) -> u32 {
+        match self {
+            DbError::AccountNotFound { .. } => 1,
+            DbError::StorageSlotNotFound { .. } => 2,
+            DbError::StorageRootMismatch { .. } => 3,
+            DbError::MissingBytecode { .. } => 4,
+            DbError::InvalidAncestor { .. } => 5,
+            DbError::StateRootMismatch { .. } => 6,
+            DbError::CodeNotFound { .. } => 7,
+            DbError::BlockHashNotFound { .. } => 8,
+        }
+    }
+}
 
 /// A helper trait to extend [InMemoryDB] with additional functionality.
 pub trait InMemoryDBHelper {
@@ -53,6 +114,16 @@ pub trait InMemoryDBHelper {
 
 impl InMemoryDBHelper for InMemoryDB {
     fn initialize(input: &mut SP1RethInput) -> Result<Self> {
+        // A block is only proven invalid against the state of its parent, while the state trie of
+        // a valid block is bound to its parent through the resulting state root.
+        if input.prove_invalid && input.parent_state_trie.hash() != input.parent_header.state_root {
+            return Err(DbError::StateRootMismatch {
+                expected: input.parent_header.state_root,
+                actual: input.parent_state_trie.hash(),
+            }
+            .into());
+        }
+
         // For each contract's byte code, hash it and store it in a map.
         let contracts: HashMap<B256, Bytes> = input
             .contracts
@@ -69,25 +140,37 @@ impl InMemoryDBHelper for InMemoryDB {
                 .unwrap_or_default();
 
             if storage_trie.hash() != state_account.storage_root {
-                panic!(
-                    "Storage trie root does not match for account {:?}: expected {}, got {}",
-                    address,
-                    state_account.storage_root,
-                    storage_trie.hash()
-                );
+                return Err(DbError::StorageRootMismatch {
+                    address: *address,
+                    expected: state_account.storage_root,
+                    actual: storage_trie.hash(),
+                }
+                .into());
             }
 
             let bytecode = if state_account.code_hash.0 == KECCAK_EMPTY.0 {
                 Bytecode::new()
             } else {
-                let bytes = contracts.get(&state_account.code_hash).unwrap().clone();
-                Bytecode::new_raw(bytes)
+                let Some(bytes) = contracts.get(&state_account.code_hash) else {
+                    return Err(DbError::MissingBytecode {
+                        address: *address,
+                        code_hash: state_account.code_hash,
+                    }
+                    .into());
+                };
+                Bytecode::new_raw(bytes.clone())
             };
 
+            // Slots missing from the trie are proven absent by the exclusion proofs of the witness,
+            // so they are stored as explicit zeros. An incomplete proof fails the lookup instead.
             let mut storage = HashMap::with_capacity(slots.len());
             for slot in slots {
                 let value: U256 = storage_trie
-                    .get_rlp(&keccak(slot.to_be_bytes::<32>()))?
+                    .get_rlp(&keccak(slot.to_be_bytes::<32>()))
+                    .map_err(|_| DbError::StorageSlotNotFound {
+                        address: *address,
+                        index: *slot,
+                    })?
                     .unwrap_or_default();
                 storage.insert(*slot, value);
             }
@@ -106,31 +189,7 @@ impl InMemoryDBHelper for InMemoryDB {
         }
 
         // Insert ancestor headers into the database.
-        let mut block_hashes = HashMap::with_capacity(input.ancestor_headers.len() + 1);
-        block_hashes.insert(
-            U256::from(input.parent_header.number),
-            input.parent_header.hash_slow(),
-        );
-        let mut prev = &input.parent_header;
-        for current in &input.ancestor_headers {
-            let current_hash = current.hash_slow();
-            if prev.parent_hash != current_hash {
-                panic!(
-                    "Invalid chain: {} is not the parent of {}",
-                    current.number, prev.number
-                );
-            }
-            if input.parent_header.number < current.number
-                || input.parent_header.number - current.number >= 256
-            {
-                panic!(
-                    "Invalid chain: {} is not one of the {} most recent blocks",
-                    current.number, 256,
-                );
-            }
-            block_hashes.insert(U256::from(current.number), current_hash);
-            prev = current;
-        }
+        let block_hashes = ancestor_block_hashes(&input.parent_header, &input.ancestor_headers)?;
 
         // Return the DB.
         Ok(InMemoryDB {
@@ -143,7 +202,7 @@ impl InMemoryDBHelper for InMemoryDB {
     fn get_account_info(&self, address: Address) -> Result<Option<AccountInfo>> {
         match self.accounts.get(&address) {
             Some(db_account) => Ok(db_account.info()),
-            None => Err(anyhow!("Account not found.")),
+            None => Err(DbError::AccountNotFound { address }.into()),
         }
     }
 
@@ -152,12 +211,11 @@ impl InMemoryDBHelper for InMemoryDB {
             Some(account) => match account.storage.get(&index) {
                 Some(value) => Ok(*value),
                 None => match account.account_state {
-                    AccountState::NotExisting => unreachable!(),
-                    AccountState::StorageCleared => Ok(U256::ZERO),
-                    _ => Err(anyhow!("Storage slot not found.")),
+                    AccountState::NotExisting | AccountState::StorageCleared => Ok(U256::ZERO),
+                    _ => Err(DbError::StorageSlotNotFound { address, index }.into()),
                 },
             },
-            None => Err(anyhow!("Account not found.")),
+            None => Err(DbError::AccountNotFound { address }.into()),
         }
     }
 
@@ -178,3 +236,184 @@ impl InMemoryDBHelper for InMemoryDB {
         };
     }
 }
+
+/// The witness database executed by the program, which fails on every read the witness does not
+/// cover.
+///
+/// An [InMemoryDB] reads missing accounts as empty and missing storage slots as zero. Such reads
+/// go unnoticed unless they change the state root, and could make a valid block fail a consensus
+/// check, so they are reported as [DbError]s instead.
+pub struct WitnessDb(pub InMemoryDB);
+
+impl Database for WitnessDb {
+    type Error = DbError;
+
+    fn basic(&mut self, address: Address) -> std::result::Result<Option<AccountInfo>, DbError> {
+        match self.0.accounts.get(&address) {
+            Some(account) => Ok(account.info()),
+            None => Err(DbError::AccountNotFound { address }),
+        }
+    }
+
+    fn code_by_hash(&mut self, code_hash: B256) -> std::result::Result<Bytecode, DbError> {
+        self.0
+            .contracts
+            .get(&code_hash)
+            .cloned()
+            .ok_or(DbError::CodeNotFound { code_hash })
+    }
+
+    fn storage(&mut self, address: Address, index: U256) -> std::result::Result<U256, DbError> {
+        let Some(account) = self.0.accounts.get(&address) else {
+            return Err(DbError::AccountNotFound { address });
+        };
+        match account.storage.get(&index) {
+            Some(value) => Ok(*value),
+            // The storage of deleted and created accounts is known to be empty.
+            None => match account.account_state {
+                AccountState::NotExisting | AccountState::StorageCleared => Ok(U256::ZERO),
+                _ => Err(DbError::StorageSlotNotFound { address, index }),
+            },
+        }
+    }
+
+    fn block_hash(&mut self, number: U256) -> std::result::Result<B256, DbError> {
+        self.0
+            .block_hashes
+            .get(&number)
+            .copied()
+            .ok_or(DbError::BlockHashNotFound { number })
+    }
+}
+
+impl DatabaseCommit for WitnessDb {
+    fn commit(&mut self, changes: HashMap<Address, Account>) {
+        self.0.commit(changes)
+    }
+}
+
+impl Borrow<InMemoryDB> for WitnessDb {
+    fn borrow(&self) -> &InMemoryDB {
+        &self.0
+    }
+}
+
+/// Returns the hashes of the parent header and its ancestors by block number, after checking that
+/// every ancestor is the parent of the previous header and one of the 256 most recent blocks.
+fn ancestor_block_hashes(
+    parent_header: &Header,
+    ancestor_headers: &[Header],
+) -> Result<HashMap<U256, B256>, DbError> {
+    let mut block_hashes = HashMap::with_capacity(ancestor_headers.len() + 1);
+    block_hashes.insert(U256::from(parent_header.number), parent_header.hash_slow());
+    let mut prev = parent_header;
+    for current in ancestor_headers {
+        let current_hash = current.hash_slow();
+        if prev.parent_hash != current_hash {
+            return Err(DbError::InvalidAncestor {
+                number: current.number,
+                reason: "not the parent of the previous header",
+            });
+        }
+        if parent_header.number < current.number || parent_header.number - current.number >= 256 {
+            return Err(DbError::InvalidAncestor {
+                number: current.number,
+                reason: "not one of the 256 most recent blocks",
+            });
+        }
+        block_hashes.insert(U256::from(current.number), current_hash);
+        prev = current;
+    }
+    Ok(block_hashes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mpt::MptNode;
+    use crate::processor::tests::empty_block_input;
+
+    fn chain(len: u64) -> Vec<Header> {
+        let mut headers: Vec<Header> = Vec::new();
+        for number in 0..len {
+            let parent_hash = headers.last().map(Header::hash_slow).unwrap_or_default();
+            headers.push(Header {
+                number,
+                parent_hash,
+                ..Default::default()
+            });
+        }
+        headers.reverse();
+        headers
+    }
+
+    #[test]
+    fn test_ancestor_block_hashes() {
+        let headers = chain(4);
+        let block_hashes = ancestor_block_hashes(&headers[0], &headers[1..]).unwrap();
+        assert_eq!(block_hashes.len(), 4);
+        assert_eq!(block_hashes[&U256::from(1)], headers[2].hash_slow());
+
+        // A gap in the chain is rejected.
+        let err = ancestor_block_hashes(&headers[0], &headers[2..]).unwrap_err();
+        assert!(matches!(err, DbError::InvalidAncestor { number: 1, .. }));
+
+        // So is an ancestor beyond the 256 most recent blocks.
+        let headers = chain(258);
+        let err = ancestor_block_hashes(&headers[0], &headers[1..]).unwrap_err();
+        assert!(matches!(err, DbError::InvalidAncestor { number: 1, .. }));
+    }
+
+    #[test]
+    fn test_witness_db() {
+        let address = Address::repeat_byte(1);
+        let mut db = InMemoryDB::default();
+        db.insert_account_info(address, AccountInfo::default());
+        db.insert_account_storage(address, U256::from(1), U256::from(2))
+            .unwrap();
+        let mut db = WitnessDb(db);
+
+        // Accounts and storage slots of the witness are read as usual.
+        assert_eq!(db.basic(address).unwrap(), Some(AccountInfo::default()));
+        assert_eq!(db.storage(address, U256::from(1)).unwrap(), U256::from(2));
+
+        // Reads the witness does not cover fail instead of resolving to empty values.
+        let missing = Address::repeat_byte(2);
+        assert!(matches!(
+            db.basic(missing),
+            Err(DbError::AccountNotFound { .. })
+        ));
+        assert!(matches!(
+            db.storage(address, U256::from(3)),
+            Err(DbError::StorageSlotNotFound { .. })
+        ));
+        assert!(matches!(
+            db.block_hash(U256::from(1)),
+            Err(DbError::BlockHashNotFound { .. })
+        ));
+
+        // Accounts proven absent have no storage.
+        db.0.accounts.insert(missing, DbAccount::new_not_existing());
+        assert_eq!(db.basic(missing).unwrap(), None);
+        assert_eq!(db.storage(missing, U256::from(3)).unwrap(), U256::ZERO);
+    }
+
+    #[test]
+    fn test_prove_invalid_state_root() {
+        let mut input = SP1RethInput {
+            prove_invalid: true,
+            ..empty_block_input(19_000_000, 1_710_000_000)
+        };
+        assert!(InMemoryDB::initialize(&mut input).is_ok());
+
+        // The state trie must be the state of the parent to prove the block invalid.
+        input.parent_state_trie = MptNode::default();
+        let err = InMemoryDB::initialize(&mut input).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<DbError>(),
+            Some(DbError::StateRootMismatch { .. })
+        ));
+        input.prove_invalid = false;
+        assert!(InMemoryDB::initialize(&mut input).is_ok());
+    }
+}
@@ -0,0 +1,149 @@
+//! A structured diff of the state changes of a block, for indexers and auditors that need to know
+//! what a proof asserted beyond the state root.
+
+use crate::mpt::keccak;
+use crate::mpt::StateAccount;
+use crate::processor::ProcessorError;
+use crate::SP1RethInput;
+use reth_primitives::{Address, B256, U256};
+use revm::db::AccountState;
+use revm::db::InMemoryDB;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// How an account changed in the block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AccountChange {
+    /// The account did not exist before the block.
+    Created,
+
+    /// The account existed before the block and was deleted.
+    Deleted,
+
+    /// The account existed before and after the block.
+    Updated,
+}
+
+/// The changes of a single account in the block.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AccountDiff {
+    /// How the account changed.
+    pub change: AccountChange,
+
+    /// The state of the account before the block, or [None] if it did not exist.
+    pub before: Option<StateAccount>,
+
+    /// The nonce of the account after the block.
+    pub nonce: u64,
+
+    /// The balance of the account after the block.
+    pub balance: U256,
+
+    /// The code hash of the account after the block.
+    pub code_hash: B256,
+
+    /// Whether the storage of the account was cleared before applying the storage changes.
+    pub storage_cleared: bool,
+
+    /// The storage slots whose value changed, mapped to their values before and after the block.
+    pub storage: BTreeMap<U256, (U256, U256)>,
+}
+
+impl AccountDiff {
+    /// Returns the balance after the block minus the balance before the block, and whether the
+    /// balance decreased.
+    pub fn balance_delta(&self) -> (U256, bool) {
+        let before = self
+            .before
+            .as_ref()
+            .map_or(U256::ZERO, |before| before.balance);
+        if self.balance >= before {
+            (self.balance - before, false)
+        } else {
+            (before - self.balance, true)
+        }
+    }
+
+    /// Returns whether the fields and storage of an updated account are identical to its state
+    /// before the block.
+    fn is_unchanged(&self) -> bool {
+        let Some(before) = &self.before else {
+            return false;
+        };
+        self.storage.is_empty()
+            && self.nonce == before.nonce
+            && self.balance == before.balance
+            && self.code_hash == before.code_hash
+    }
+}
+
+/// The accounts and storage slots changed by a block.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlockStateDiff {
+    /// The changed accounts, sorted by address.
+    pub accounts: BTreeMap<Address, AccountDiff>,
+}
+
+impl BlockStateDiff {
+    /// Computes the diff between the parent tries of the input and the state of the database.
+    ///
+    /// Accounts touched without any observable change are omitted, as are accounts created and
+    /// deleted within the block.
+    pub fn compute(input: &SP1RethInput, db: &InMemoryDB) -> Result<Self, ProcessorError> {
+        let mut accounts = BTreeMap::new();
+        for (address, account) in &db.accounts {
+            if account.account_state == AccountState::None {
+                continue;
+            }
+            let before = input
+                .parent_state_trie
+                .get_rlp::<StateAccount>(&keccak(address))
+                .map_err(|_| ProcessorError::MissingWitness { address: *address })?;
+
+            let change = match (&account.account_state, &before) {
+                (AccountState::NotExisting, None) => continue,
+                (AccountState::NotExisting, Some(_)) => AccountChange::Deleted,
+                (_, None) => AccountChange::Created,
+                (_, Some(_)) => AccountChange::Updated,
+            };
+
+            // The parent storage of an account whose storage was cleared is only needed for the
+            // values before the block, which are zero if it has no witness.
+            let storage_cleared = account.account_state == AccountState::StorageCleared;
+            let mut storage = BTreeMap::new();
+            if change != AccountChange::Deleted {
+                let storage_trie = match input.parent_storage.get(address) {
+                    Some((storage_trie, _)) => Some(storage_trie),
+                    None if storage_cleared => None,
+                    None => return Err(ProcessorError::MissingWitness { address: *address }),
+                };
+                for (index, value) in &account.storage {
+                    let previous = match storage_trie {
+                        Some(storage_trie) => storage_trie
+                            .get_rlp::<U256>(&keccak(index.to_be_bytes::<32>()))?
+                            .unwrap_or_default(),
+                        None => U256::ZERO,
+                    };
+                    if previous != *value {
+                        storage.insert(*index, (previous, *value));
+                    }
+                }
+            }
+
+            let diff = AccountDiff {
+                change,
+                nonce: account.info.nonce,
+                balance: account.info.balance,
+                code_hash: account.info.code_hash,
+                storage_cleared,
+                storage,
+                before,
+            };
+            if !diff.storage_cleared && diff.is_unchanged() {
+                continue;
+            }
+            accounts.insert(*address, diff);
+        }
+        Ok(Self { accounts })
+    }
+}
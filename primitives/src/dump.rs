@@ -0,0 +1,147 @@
+//! Dumps of tries as Graphviz DOT graphs or structured JSON, for diagnosing witnesses whose state
+//! or storage roots do not match.
+
+use crate::mpt::{MptNode, MptNodeData, MptNodeReference};
+use reth_primitives::{Bytes, B256};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+/// A node of a trie and its descendants, in a form that serializes to readable JSON.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum NodeDump {
+    /// An empty trie.
+    Null,
+
+    /// A branch, with its children by nibble.
+    Branch {
+        hash: B256,
+        embedded: bool,
+        children: BTreeMap<u8, NodeDump>,
+    },
+
+    /// A leaf, with the remaining nibbles of its key as a hex string.
+    Leaf {
+        hash: B256,
+        embedded: bool,
+        path: String,
+        value: Bytes,
+    },
+
+    /// An extension, with its shared nibbles as a hex string.
+    Extension {
+        hash: B256,
+        embedded: bool,
+        path: String,
+        child: Box<NodeDump>,
+    },
+
+    /// A subtrie the witness only contains the hash of.
+    Digest { hash: B256 },
+}
+
+impl NodeDump {
+    /// Dumps the trie rooted at the given node.
+    ///
+    /// Nodes whose encoding is shorter than a hash are embedded in their parent rather than
+    /// referenced by their hash, which is reported as `embedded`.
+    pub fn new(node: &MptNode) -> Self {
+        let embedded = matches!(node.reference(), MptNodeReference::Bytes(_));
+        match node.as_data() {
+            MptNodeData::Null => NodeDump::Null,
+            MptNodeData::Branch(children) => NodeDump::Branch {
+                hash: node.hash(),
+                embedded,
+                children: children
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(nib, child)| Some((nib as u8, NodeDump::new(child.as_ref()?))))
+                    .collect(),
+            },
+            MptNodeData::Leaf(_, value) => NodeDump::Leaf {
+                hash: node.hash(),
+                embedded,
+                path: hex_nibs(&node.nibs()),
+                value: value.clone().into(),
+            },
+            MptNodeData::Extension(_, child) => NodeDump::Extension {
+                hash: node.hash(),
+                embedded,
+                path: hex_nibs(&node.nibs()),
+                child: Box::new(NodeDump::new(child)),
+            },
+            MptNodeData::Digest(digest) => NodeDump::Digest { hash: *digest },
+        }
+    }
+}
+
+/// Renders the trie rooted at the given node as a Graphviz DOT graph.
+///
+/// Every node is labelled with its type and the first bytes of its hash, leaves also with the rest
+/// of their key, edges with the nibbles they consume, and unresolved subtries are drawn dashed.
+pub fn to_dot(node: &MptNode) -> String {
+    let mut dot = String::from("digraph trie {\n  node [shape=box, fontname=monospace];\n");
+    let mut next_id = 0;
+    write_dot(node, &mut next_id, &mut dot);
+    dot.push_str("}\n");
+    dot
+}
+
+/// Writes the node and its descendants, and returns the id of the node.
+fn write_dot(node: &MptNode, next_id: &mut usize, dot: &mut String) -> usize {
+    let id = *next_id;
+    *next_id += 1;
+    let short_hash = format!("{}..", hex::encode(&node.hash()[..4]));
+    match node.as_data() {
+        MptNodeData::Null => {
+            let _ = writeln!(dot, "  n{} [label=\"null\"];", id);
+        }
+        MptNodeData::Branch(children) => {
+            let _ = writeln!(dot, "  n{} [label=\"branch\\n{}\"];", id, short_hash);
+            for (nib, child) in children.iter().enumerate() {
+                if let Some(child) = child {
+                    let child_id = write_dot(child, next_id, dot);
+                    let _ = writeln!(dot, "  n{} -> n{} [label=\"{:x}\"];", id, child_id, nib);
+                }
+            }
+        }
+        MptNodeData::Leaf(_, value) => {
+            let _ = writeln!(
+                dot,
+                "  n{} [label=\"leaf {}\\n{}\\n{} bytes\"];",
+                id,
+                hex_nibs(&node.nibs()),
+                short_hash,
+                value.len()
+            );
+        }
+        MptNodeData::Extension(_, child) => {
+            let _ = writeln!(dot, "  n{} [label=\"extension\\n{}\"];", id, short_hash);
+            let child_id = write_dot(child, next_id, dot);
+            let _ = writeln!(
+                dot,
+                "  n{} -> n{} [label=\"{}\"];",
+                id,
+                child_id,
+                hex_nibs(&node.nibs())
+            );
+        }
+        MptNodeData::Digest(_) => {
+            let _ = writeln!(
+                dot,
+                "  n{} [label=\"digest\\n{}\", style=dashed];",
+                id, short_hash
+            );
+        }
+    }
+    id
+}
+
+/// Formats nibbles as a hex string with one digit per nibble.
+fn hex_nibs(nibs: &[u8]) -> String {
+    nibs.iter().fold(String::new(), |mut output, nib| {
+        let _ = write!(output, "{:x}", nib);
+        output
+    })
+}
@@ -0,0 +1,78 @@
+//! Address filters used to simulate the execution of a block without some of its transactions.
+//!
+//! Filters are only meant for simulations: a block executed with a filter generally does not
+//! match the canonical block, so the filter and the transactions it skipped are committed alongside
+//! the result.
+
+use reth_primitives::Address;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+/// How the addresses of an [ExecutionFilter] are interpreted.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FilterMode {
+    /// Transactions from or to any of the addresses are skipped.
+    Blocklist,
+    /// Transactions are skipped unless both their sender and recipient are in the addresses.
+    Allowlist,
+}
+
+/// The reason a transaction was skipped by an [ExecutionFilter].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SkipReason {
+    /// The sender of the transaction is blocked.
+    BlockedSender,
+    /// The recipient of the transaction is blocked.
+    BlockedRecipient,
+    /// The sender of the transaction is not allowed.
+    SenderNotAllowed,
+    /// The recipient of the transaction is not allowed.
+    RecipientNotAllowed,
+}
+
+/// A transaction that was skipped during execution.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SkippedTransaction {
+    /// The index of the transaction in the block.
+    pub index: u32,
+
+    /// The reason the transaction was skipped.
+    pub reason: SkipReason,
+}
+
+/// A filter deciding which transactions of a block are executed.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExecutionFilter {
+    /// How the addresses are interpreted.
+    pub mode: FilterMode,
+
+    /// The addresses the filter applies to.
+    pub addresses: BTreeSet<Address>,
+}
+
+impl ExecutionFilter {
+    /// Returns the reason a transaction from `from` to `to` should be skipped, if any. Contract
+    /// creations have no recipient and are only filtered by their sender.
+    pub fn skip_reason(&self, from: Address, to: Option<Address>) -> Option<SkipReason> {
+        match self.mode {
+            FilterMode::Blocklist => {
+                if self.addresses.contains(&from) {
+                    Some(SkipReason::BlockedSender)
+                } else if to.is_some_and(|to| self.addresses.contains(&to)) {
+                    Some(SkipReason::BlockedRecipient)
+                } else {
+                    None
+                }
+            }
+            FilterMode::Allowlist => {
+                if !self.addresses.contains(&from) {
+                    Some(SkipReason::SenderNotAllowed)
+                } else if to.is_some_and(|to| !self.addresses.contains(&to)) {
+                    Some(SkipReason::RecipientNotAllowed)
+                } else {
+                    None
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,27 @@
+//! Execution hooks, which let type-1 rollups customize how their blocks are executed while
+//! reusing the witness, trie and validation machinery of SP1 Reth.
+//!
+//! Unlike a [crate::profile::ChainProfile], hooks are code rather than configuration: a rollup
+//! embedding SP1 Reth sets them on the [crate::processor::EvmProcessor] of its own program.
+
+use reth_primitives::{Header, TransactionSignedNoHash};
+use revm::precompile::PrecompileWithAddress;
+
+/// Customizations of block execution. Every hook defaults to the behavior of Ethereum.
+pub trait ExecutionHooks: Send + Sync {
+    /// Overrides the fields of the header derived from the input, before the header is validated.
+    fn header(&self, _header: &mut Header) {}
+
+    /// Returns whether the transaction at the given index is a system transaction of the rollup,
+    /// such as an anchor transaction. System transactions are executed without being validated
+    /// against the sender account and the base fee.
+    fn is_system_transaction(&self, _index: usize, _tx: &TransactionSignedNoHash) -> bool {
+        false
+    }
+
+    /// Returns the precompiles of the rollup, which are added to the precompiles of Ethereum
+    /// active at the block. A precompile at the address of an Ethereum precompile replaces it.
+    fn precompiles(&self) -> Vec<PrecompileWithAddress> {
+        Vec::new()
+    }
+}
@@ -16,21 +16,46 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+pub mod abort;
 pub mod alloy2reth;
+pub mod chain;
 pub mod db;
+pub mod diff;
+pub mod dump;
+pub mod filter;
+pub mod hooks;
 pub mod mpt;
+pub mod oracle;
+pub mod overrides;
+pub mod pow;
 pub mod processor;
+pub mod profile;
+pub mod public_values;
+pub mod receipts;
+pub mod senders;
+pub mod state;
+pub mod store;
+pub mod validation;
+pub mod withdrawals;
 
+use crate::chain::ChainConfig;
+use crate::filter::ExecutionFilter;
 use crate::mpt::MptNode;
 use crate::mpt::StorageEntry;
+use crate::overrides::BlockOverrides;
+use crate::withdrawals::Withdrawals;
 
-use reth_primitives::{Address, Bytes, Header, TransactionSignedNoHash, Withdrawal, B256};
+use reth_primitives::{Address, Bytes, Header, TransactionSignedNoHash, B256, B512, U256};
 use revm::primitives::HashMap;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 
 /// Necessary information to prove the execution of Ethereum blocks inside SP1.
 #[derive(Clone, Serialize, Deserialize)]
 pub struct SP1RethInput {
+    /// The configuration of the chain the block belongs to.
+    pub chain_config: ChainConfig,
+
     /// The Keccak 256-bit hash of the parent block's header, in its entirety.
     pub parent_header: Header,
 
@@ -52,10 +77,35 @@ pub struct SP1RethInput {
     /// computation has been carried out on this block.
     pub mix_hash: B256,
 
+    /// A scalar value corresponding to the difficulty level of this block. This is zero after the
+    /// merge.
+    pub difficulty: U256,
+
+    /// A 64-bit value which, combined with the mix-hash, proves that a sufficient amount of
+    /// computation has been carried out on this block. This is zero after the merge.
+    pub nonce: u64,
+
+    /// The headers of the ommers included in this block. This is empty after the merge, and blocks
+    /// with ommers are rejected as unsupported.
+    pub ommers: Vec<Header>,
+
+    /// The hash of the parent beacon block's root, if the block is post-Cancun.
+    pub parent_beacon_block_root: Option<B256>,
+
+    /// The base fee per gas of the block, if the block is post-London. It must follow from the gas
+    /// usage and base fee of the parent.
+    pub base_fee_per_gas: Option<u64>,
+
+    /// The excess blob gas of the block, if the block is post-Cancun. It must follow from the blob
+    /// gas usage and excess blob gas of the parent.
+    pub excess_blob_gas: Option<u64>,
+
     /// The state trie of the parent block.
+    #[serde(with = "store::trie")]
     pub parent_state_trie: MptNode,
 
-    /// The storage of the parent block.
+    /// The storage of the parent block, whose tries share a store of their distinct nodes.
+    #[serde(with = "store::storage")]
     pub parent_storage: HashMap<Address, StorageEntry>,
 
     /// The relevant contracts for the block.
@@ -67,6 +117,79 @@ pub struct SP1RethInput {
     /// A list of transactions to process.
     pub transactions: Vec<TransactionSignedNoHash>,
 
+    /// The public keys of the senders of signed transactions, keyed by their index in the block.
+    /// A valid hint spares recovering the sender from the signature, and an invalid one is
+    /// ignored.
+    pub sender_hints: BTreeMap<u32, B512>,
+
     /// A list of withdrawals to process.
-    pub withdrawals: Vec<Withdrawal>,
+    pub withdrawals: Withdrawals,
+
+    /// Whether the guest should commit the compact receipts of the block.
+    pub commit_receipts: bool,
+
+    /// Whether the guest should commit the number, hash and state root of the block in the ABI of
+    /// block-hash oracle contracts.
+    pub commit_block_oracle: bool,
+
+    /// Whether the guest should commit the consensus rule violated by an invalid block instead of
+    /// aborting, so that the invalidity of the block can be proven.
+    pub prove_invalid: bool,
+
+    /// An optional filter to simulate the block without some of its transactions. The filter and
+    /// the skipped transactions are committed, since the resulting block is no longer canonical.
+    pub execution_filter: Option<ExecutionFilter>,
+
+    /// Optional overrides of the block environment to simulate a variant of the block. The
+    /// overrides are committed, since the resulting block is no longer canonical.
+    pub block_overrides: Option<BlockOverrides>,
+
+    /// An optional number of transactions to execute, in which case only that prefix of the
+    /// block is executed and the state root after it is committed. Withdrawals and block rewards
+    /// are not applied to a prefix.
+    pub transaction_limit: Option<u32>,
+
+    /// An optional index of the first transaction to execute, in which case the parent tries hold
+    /// the state after the preceding transactions of the block. The state root of the parent
+    /// tries is committed before the state root after the executed range, so that proofs of
+    /// consecutive ranges can be chained. The gas limit of the block is only checked against the
+    /// gas used by the range.
+    pub transaction_start: Option<u32>,
+
+    /// An optional number of transactions, in which case the state root after every such number of
+    /// transactions is committed, so that dispute protocols can reference intermediate state roots
+    /// of the block.
+    pub state_root_interval: Option<u32>,
+
+    /// The hash the finalized header is expected to have, in which case the program aborts if it
+    /// does not, rather than proving the execution of a different block.
+    pub expected_block_hash: Option<B256>,
+
+    /// The gas the block is expected to use, in which case the gas used by the executed
+    /// transactions must match it exactly.
+    pub expected_gas_used: Option<u64>,
+
+    /// The blob gas the block is expected to use, in which case the blob gas of the executed
+    /// transactions must match it exactly.
+    pub expected_blob_gas_used: Option<u64>,
+
+    /// The transactions root of the block, in which case the transactions of the input are
+    /// checked against it before being executed.
+    pub expected_transactions_root: Option<B256>,
+
+    /// The header claimed for the block, which is required to prove the block invalid. Its fields
+    /// known before execution must match the input, and its hash is committed with the violated
+    /// consensus rule.
+    pub claimed_header: Option<Header>,
+}
+
+impl SP1RethInput {
+    /// Returns whether the block itself is executed, rather than a simulated variant of it or a
+    /// transaction range starting from an intermediate state. Only violations found while
+    /// executing the block itself prove it invalid.
+    pub fn proves_block(&self) -> bool {
+        self.execution_filter.is_none()
+            && self.block_overrides.is_none()
+            && self.transaction_start.is_none()
+    }
 }
@@ -16,26 +16,28 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use alloc::boxed::Box;
-use alloy_primitives::{b256, B256};
+use alloc::sync::Arc;
+use alloy_primitives::{b256, Bytes, B256};
 use alloy_rlp::Encodable;
 use core::{
-    cell::RefCell,
     cmp,
     fmt::{Debug, Write},
     iter, mem,
 };
 use hashbrown::HashMap;
+use std::sync::OnceLock;
 
 use rlp::{Decodable, DecoderError, Prototype, Rlp};
 use serde::{Deserialize, Serialize};
 use thiserror::Error as ThisError;
+use tiny_keccak::{Hasher, Keccak};
 
 use alloy_primitives::{TxNumber, U256};
 use alloy_rlp_derive::{RlpDecodable, RlpEncodable, RlpMaxEncodedLen};
 use alloy_rpc_types::EIP1186AccountProofResponse;
 use anyhow::Context;
 use anyhow::Result;
+use reth_primitives::trie::AccountProof;
 use reth_primitives::Address;
 
 pub type StorageEntry = (MptNode, Vec<U256>);
@@ -116,17 +118,21 @@ pub const KECCAK_EMPTY: B256 =
 
 /// Computes the Keccak-256 hash of the provided data.
 ///
-/// This function is a thin wrapper around the Keccak256 hashing algorithm
-/// and is optimized for performance.
+/// The hash is computed by `tiny-keccak`, which the program patches to use the keccak precompile
+/// of SP1, so that hashing the nodes of the tries costs a syscall per permutation instead of a
+/// permutation in software. The precompile permutes a single state, so siblings are hashed one
+/// after the other rather than batched.
 ///
 /// # TODO
 /// - Consider switching the return type to `B256` for consistency with other parts of the
 ///   codebase.
 #[inline]
 pub fn keccak(data: impl AsRef<[u8]>) -> [u8; 32] {
-    // TODO: Remove this benchmarking code once performance testing is complete.
-    // std::hint::black_box(sha2::Sha256::digest(&data));
-    *alloy_primitives::utils::keccak256(data)
+    let mut hasher = Keccak::v256();
+    hasher.update(data.as_ref());
+    let mut hash = [0u8; 32];
+    hasher.finalize(&mut hash);
+    hash
 }
 
 /// Represents the root node of a sparse Merkle Patricia Trie.
@@ -136,14 +142,42 @@ pub fn keccak(data: impl AsRef<[u8]>) -> [u8; 32] {
 /// optimizing storage. However, operations targeting a truncated part will fail and
 /// return an error. Another distinction of this implementation is that branches cannot
 /// store values, aligning with the construction of MPTs in Ethereum.
-#[derive(Clone, Debug, Default, PartialEq, Eq, Ord, PartialOrd, Serialize, Deserialize)]
+///
+/// Children are shared through reference counting and copied on modification, so cloning a
+/// trie is cheap and mutating the clone leaves the original untouched. This allows the same
+/// prestate to be reused, e.g. across multiple blocks in an in-memory pipeline.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
 pub struct MptNode {
     /// The type and data of the node.
     data: MptNodeData,
     /// Cache for a previously computed reference of this node. This is skipped during
     /// serialization.
+    ///
+    /// Modifications only clear the cache of the nodes along their path, so hashing a modified
+    /// trie only re-encodes those nodes.
     #[serde(skip)]
-    cached_reference: RefCell<Option<MptNodeReference>>,
+    cached_reference: OnceLock<MptNodeReference>,
+}
+
+/// Nodes are compared by their data only, regardless of whether their reference is cached.
+impl PartialEq for MptNode {
+    fn eq(&self, other: &Self) -> bool {
+        self.data == other.data
+    }
+}
+
+impl Eq for MptNode {}
+
+impl PartialOrd for MptNode {
+    fn partial_cmp(&self, other: &Self) -> Option<cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for MptNode {
+    fn cmp(&self, other: &Self) -> cmp::Ordering {
+        self.data.cmp(&other.data)
+    }
 }
 
 /// Represents custom error types for the sparse Merkle Patricia Trie (MPT).
@@ -168,6 +202,9 @@ pub enum Error {
     /// errors.
     #[error("RLP error")]
     LegacyRlp(#[from] DecoderError),
+    /// Triggered when the nodes of a proof do not hash to the root it is verified against.
+    #[error("proof does not match the root {0:#}")]
+    ProofRootMismatch(B256),
 }
 
 /// Represents the various types of data that can be stored within a node in the sparse
@@ -181,13 +218,13 @@ pub enum MptNodeData {
     /// Represents an empty trie node.
     #[default]
     Null,
-    /// A node that can have up to 16 children. Each child is an optional boxed [MptNode].
-    Branch([Option<Box<MptNode>>; 16]),
+    /// A node that can have up to 16 children. Each child is an optional shared [MptNode].
+    Branch([Option<Arc<MptNode>>; 16]),
     /// A leaf node that contains a key and a value, both represented as byte vectors.
     Leaf(Vec<u8>, Vec<u8>),
     /// A node that has exactly one child and is used to represent a shared prefix of
     /// several keys.
-    Extension(Vec<u8>, Box<MptNode>),
+    Extension(Vec<u8>, Arc<MptNode>),
     /// Represents a sub-trie by its hash, allowing for efficient storage of large
     /// sub-tries without storing their entire content.
     Digest(B256),
@@ -218,7 +255,7 @@ impl From<MptNodeData> for MptNode {
     fn from(value: MptNodeData) -> Self {
         Self {
             data: value,
-            cached_reference: RefCell::new(None),
+            cached_reference: OnceLock::new(),
         }
     }
 }
@@ -234,6 +271,25 @@ impl Encodable for MptNode {
     /// method handles different node data types and encodes them accordingly.
     #[inline]
     fn encode(&self, out: &mut dyn alloy_rlp::BufMut) {
+        self.encode_with_payload_length(self.payload_length(), out);
+    }
+
+    /// Returns the length of the encoded node in bytes.
+    ///
+    /// This method calculates the length of the RLP-encoded node. It's useful for
+    /// determining the size requirements for storage or transmission.
+    #[inline]
+    fn length(&self) -> usize {
+        let payload_length = self.payload_length();
+        payload_length + alloy_rlp::length_of_length(payload_length)
+    }
+}
+
+impl MptNode {
+    /// Encodes the node into the `out` buffer, given the length of its payload as returned by
+    /// [MptNode::payload_length], so that the references of the children are only walked once
+    /// when the caller needs the length too.
+    fn encode_with_payload_length(&self, payload_length: usize, out: &mut dyn alloy_rlp::BufMut) {
         match &self.data {
             MptNodeData::Null => {
                 out.put_u8(alloy_rlp::EMPTY_STRING_CODE);
@@ -241,7 +297,7 @@ impl Encodable for MptNode {
             MptNodeData::Branch(nodes) => {
                 alloy_rlp::Header {
                     list: true,
-                    payload_length: self.payload_length(),
+                    payload_length,
                 }
                 .encode(out);
                 nodes.iter().for_each(|child| match child {
@@ -254,7 +310,7 @@ impl Encodable for MptNode {
             MptNodeData::Leaf(prefix, value) => {
                 alloy_rlp::Header {
                     list: true,
-                    payload_length: self.payload_length(),
+                    payload_length,
                 }
                 .encode(out);
                 prefix.as_slice().encode(out);
@@ -263,7 +319,7 @@ impl Encodable for MptNode {
             MptNodeData::Extension(prefix, node) => {
                 alloy_rlp::Header {
                     list: true,
-                    payload_length: self.payload_length(),
+                    payload_length,
                 }
                 .encode(out);
                 prefix.as_slice().encode(out);
@@ -274,16 +330,6 @@ impl Encodable for MptNode {
             }
         }
     }
-
-    /// Returns the length of the encoded node in bytes.
-    ///
-    /// This method calculates the length of the RLP-encoded node. It's useful for
-    /// determining the size requirements for storage or transmission.
-    #[inline]
-    fn length(&self) -> usize {
-        let payload_length = self.payload_length();
-        payload_length + alloy_rlp::length_of_length(payload_length)
-    }
 }
 
 /// Provides decoding functionalities for the [MptNode] type.
@@ -306,10 +352,10 @@ impl Decodable for MptNode {
             Prototype::Null | Prototype::Data(0) => Ok(MptNodeData::Null.into()),
             Prototype::List(2) => {
                 let path: Vec<u8> = rlp.val_at(0)?;
-                let prefix = path[0];
+                let prefix = *path.first().ok_or(DecoderError::Custom("empty path"))?;
                 if (prefix & (2 << 4)) == 0 {
                     let node: MptNode = Decodable::decode(&rlp.at(1)?)?;
-                    Ok(MptNodeData::Extension(path, Box::new(node)).into())
+                    Ok(MptNodeData::Extension(path, Arc::new(node)).into())
                 } else {
                     Ok(MptNodeData::Leaf(path, rlp.val_at(1)?).into())
                 }
@@ -321,7 +367,7 @@ impl Decodable for MptNode {
                         Prototype::Null | Prototype::Data(0) => {
                             node_list.push(None);
                         }
-                        _ => node_list.push(Some(Box::new(Decodable::decode(&node_rlp)?))),
+                        _ => node_list.push(Some(Arc::new(Decodable::decode(&node_rlp)?))),
                     }
                 }
                 let value: Vec<u8> = rlp.val_at(16)?;
@@ -381,8 +427,7 @@ impl MptNode {
     #[inline]
     pub fn reference(&self) -> MptNodeReference {
         self.cached_reference
-            .borrow_mut()
-            .get_or_insert_with(|| self.calc_reference())
+            .get_or_init(|| self.calc_reference())
             .clone()
     }
 
@@ -393,11 +438,7 @@ impl MptNode {
     pub fn hash(&self) -> B256 {
         match self.data {
             MptNodeData::Null => EMPTY_ROOT,
-            _ => match self
-                .cached_reference
-                .borrow_mut()
-                .get_or_insert_with(|| self.calc_reference())
-            {
+            _ => match self.cached_reference.get_or_init(|| self.calc_reference()) {
                 MptNodeReference::Digest(digest) => *digest,
                 MptNodeReference::Bytes(bytes) => keccak(bytes).into(),
             },
@@ -406,11 +447,7 @@ impl MptNode {
 
     /// Encodes the [MptNodeReference] of this node into the `out` buffer.
     fn reference_encode(&self, out: &mut dyn alloy_rlp::BufMut) {
-        match self
-            .cached_reference
-            .borrow_mut()
-            .get_or_insert_with(|| self.calc_reference())
-        {
+        match self.cached_reference.get_or_init(|| self.calc_reference()) {
             // if the reference is an RLP-encoded byte slice, copy it directly
             MptNodeReference::Bytes(bytes) => out.put_slice(bytes),
             // if the reference is a digest, RLP-encode it with its fixed known length
@@ -423,11 +460,7 @@ impl MptNode {
 
     /// Returns the length of the encoded [MptNodeReference] of this node.
     fn reference_length(&self) -> usize {
-        match self
-            .cached_reference
-            .borrow_mut()
-            .get_or_insert_with(|| self.calc_reference())
-        {
+        match self.cached_reference.get_or_init(|| self.calc_reference()) {
             MptNodeReference::Bytes(bytes) => bytes.len(),
             MptNodeReference::Digest(_) => 1 + 32,
         }
@@ -438,7 +471,13 @@ impl MptNode {
             MptNodeData::Null => MptNodeReference::Bytes(vec![alloy_rlp::EMPTY_STRING_CODE]),
             MptNodeData::Digest(digest) => MptNodeReference::Digest(*digest),
             _ => {
-                let encoded = alloy_rlp::encode(self);
+                // The node is encoded exactly once per mutation: the references of its children
+                // are cached, so unchanged subtrees are neither encoded nor hashed again.
+                let payload_length = self.payload_length();
+                let mut encoded = Vec::with_capacity(
+                    payload_length + alloy_rlp::length_of_length(payload_length),
+                );
+                self.encode_with_payload_length(payload_length, &mut encoded);
                 if encoded.len() < 32 {
                     MptNodeReference::Bytes(encoded)
                 } else {
@@ -499,6 +538,80 @@ impl MptNode {
         }
     }
 
+    /// Verifies a Merkle proof, i.e. the RLP-encoded nodes on the path of a key as returned by
+    /// `eth_getProof`, against the root of a trie.
+    ///
+    /// Returns the value of the key, or [None] if the proof shows that the key is not in the
+    /// trie. A proof missing a node on the path of the key fails with
+    /// [Error::NodeNotResolved], and nodes that are not on the path are ignored.
+    pub fn verify_proof(
+        root: B256,
+        key: &[u8],
+        proof: &[impl AsRef<[u8]>],
+    ) -> Result<Option<Vec<u8>>, Error> {
+        let nodes = proof
+            .iter()
+            .map(|bytes| {
+                let node = MptNode::decode(bytes)?;
+                Ok((MptNodeReference::Digest(keccak(bytes).into()), node))
+            })
+            .collect::<Result<HashMap<_, _>, Error>>()?;
+        let trie = resolve_nodes(&node_from_digest(root), &nodes);
+        // resolving re-encodes the nodes, which differs from a non-canonical encoding
+        if trie.hash() != root {
+            return Err(Error::ProofRootMismatch(root));
+        }
+        Ok(trie.get(key)?.map(<[u8]>::to_vec))
+    }
+
+    /// Returns the Merkle proof of a key, i.e. the RLP-encoded nodes on its path in the format
+    /// of `eth_getProof`, which [MptNode::verify_proof] accepts.
+    ///
+    /// The proof starts with the root and contains every node on the path that is referenced by
+    /// its hash; nodes shorter than a hash are embedded in their parent. If the key is not in the
+    /// trie, this is an exclusion proof ending with the node where its path diverges. Proving a
+    /// key whose path reaches an unresolved node fails with [Error::NodeNotResolved].
+    pub fn prove(&self, key: &[u8]) -> Result<Vec<Bytes>, Error> {
+        let mut proof = Vec::new();
+        let nibs = to_nibs(key);
+        let mut nibs = nibs.as_slice();
+        let mut node = self;
+        loop {
+            match node.as_data() {
+                MptNodeData::Null => return Ok(proof),
+                MptNodeData::Digest(digest) => return Err(Error::NodeNotResolved(*digest)),
+                _ => {
+                    let is_root = core::ptr::eq(node, self);
+                    if is_root || matches!(node.reference(), MptNodeReference::Digest(_)) {
+                        proof.push(alloy_rlp::encode(node).into());
+                    }
+                }
+            }
+            node = match node.as_data() {
+                MptNodeData::Branch(children) => {
+                    let Some((nib, tail)) = nibs.split_first() else {
+                        return Ok(proof);
+                    };
+                    match &children[*nib as usize] {
+                        Some(child) => {
+                            nibs = tail;
+                            child
+                        }
+                        None => return Ok(proof),
+                    }
+                }
+                MptNodeData::Extension(prefix, child) => match strip_encoded_path(prefix, nibs) {
+                    Some(tail) => {
+                        nibs = tail;
+                        child
+                    }
+                    None => return Ok(proof),
+                },
+                _ => return Ok(proof),
+            };
+        }
+    }
+
     fn get_internal(&self, key_nibs: &[u8]) -> Result<Option<&[u8]>, Error> {
         match &self.data {
             MptNodeData::Null => Ok(None),
@@ -513,14 +626,14 @@ impl MptNode {
                 }
             }
             MptNodeData::Leaf(prefix, value) => {
-                if prefix_nibs(prefix) == key_nibs {
+                if strip_encoded_path(prefix, key_nibs).is_some_and(<[u8]>::is_empty) {
                     Ok(Some(value))
                 } else {
                     Ok(None)
                 }
             }
             MptNodeData::Extension(prefix, node) => {
-                if let Some(tail) = key_nibs.strip_prefix(prefix_nibs(prefix).as_slice()) {
+                if let Some(tail) = strip_encoded_path(prefix, key_nibs) {
                     node.get_internal(tail)
                 } else {
                     Ok(None)
@@ -536,7 +649,25 @@ impl MptNode {
     /// present, it returns `true`. Otherwise, it returns `false`.
     #[inline]
     pub fn delete(&mut self, key: &[u8]) -> Result<bool, Error> {
-        self.delete_internal(&to_nibs(key))
+        self.delete_nibs(&to_nibs(key))
+    }
+
+    /// Removes a key from the trie like [MptNode::delete], using `nibs` as scratch space for the
+    /// nibbles of the key instead of allocating them.
+    pub fn delete_with(&mut self, key: &[u8], nibs: &mut Vec<u8>) -> Result<bool, Error> {
+        to_nibs_into(key, nibs);
+        self.delete_nibs(nibs)
+    }
+
+    /// Removes the key with the given nibbles, leaving the trie untouched if it is absent.
+    ///
+    /// Nodes shared with other tries are copied on the way down, so the key is looked up first:
+    /// the zero slots read during a block are deleted without copying the nodes of their paths.
+    fn delete_nibs(&mut self, key_nibs: &[u8]) -> Result<bool, Error> {
+        if self.get_internal(key_nibs)?.is_none() {
+            return Ok(false);
+        }
+        self.delete_internal(key_nibs)
     }
 
     fn delete_internal(&mut self, key_nibs: &[u8]) -> Result<bool, Error> {
@@ -547,7 +678,7 @@ impl MptNode {
                     let child = &mut children[*i as usize];
                     match child {
                         Some(node) => {
-                            if !node.delete_internal(tail)? {
+                            if !Arc::make_mut(node).delete_internal(tail)? {
                                 return Ok(false);
                             }
                             // if the node is now empty, remove it
@@ -567,7 +698,7 @@ impl MptNode {
                 // if there is only exactly one node left, we need to convert the branch
                 if remaining.next().is_none() {
                     let mut orphan = node.take().unwrap();
-                    match &mut orphan.data {
+                    match &mut Arc::make_mut(&mut orphan).data {
                         // if the orphan is a leaf, prepend the corresponding nib to it
                         MptNodeData::Leaf(prefix, orphan_value) => {
                             let new_nibs: Vec<_> =
@@ -598,7 +729,7 @@ impl MptNode {
                 }
             }
             MptNodeData::Leaf(prefix, _) => {
-                if prefix_nibs(prefix) != key_nibs {
+                if !strip_encoded_path(prefix, key_nibs).is_some_and(<[u8]>::is_empty) {
                     return Ok(false);
                 }
                 self.data = MptNodeData::Null;
@@ -606,7 +737,7 @@ impl MptNode {
             MptNodeData::Extension(prefix, child) => {
                 let mut self_nibs = prefix_nibs(prefix);
                 if let Some(tail) = key_nibs.strip_prefix(self_nibs.as_slice()) {
-                    if !child.delete_internal(tail)? {
+                    if !Arc::make_mut(child).delete_internal(tail)? {
                         return Ok(false);
                     }
                 } else {
@@ -615,7 +746,7 @@ impl MptNode {
 
                 // an extension can only point to a branch or a digest; since it's sub trie was
                 // modified, we need to make sure that this property still holds
-                match &mut child.data {
+                match &mut Arc::make_mut(child).data {
                     // if the child is empty, remove the extension
                     MptNodeData::Null => {
                         self.data = MptNodeData::Null;
@@ -655,7 +786,7 @@ impl MptNode {
         if value.is_empty() {
             panic!("value must not be empty");
         }
-        self.insert_internal(&to_nibs(key), value)
+        self.insert_nibs(&to_nibs(key), value)
     }
 
     /// Inserts an RLP-encoded value into the trie.
@@ -663,7 +794,32 @@ impl MptNode {
     /// This method inserts a value that's been encoded using RLP into the trie.
     #[inline]
     pub fn insert_rlp(&mut self, key: &[u8], value: impl Encodable) -> Result<bool, Error> {
-        self.insert_internal(&to_nibs(key), value.to_rlp())
+        self.insert_nibs(&to_nibs(key), value.to_rlp())
+    }
+
+    /// Inserts an RLP-encoded value into the trie like [MptNode::insert_rlp], using `nibs` as
+    /// scratch space for the nibbles of the key instead of allocating them.
+    #[inline]
+    pub fn insert_rlp_with(
+        &mut self,
+        key: &[u8],
+        value: impl Encodable,
+        nibs: &mut Vec<u8>,
+    ) -> Result<bool, Error> {
+        to_nibs_into(key, nibs);
+        self.insert_nibs(nibs, value.to_rlp())
+    }
+
+    /// Inserts the value for the key with the given nibbles, leaving the trie untouched if the
+    /// key already has that value.
+    ///
+    /// Like [MptNode::delete_nibs], this spares copying the shared nodes of the paths of the
+    /// slots and accounts that are written back unchanged.
+    fn insert_nibs(&mut self, key_nibs: &[u8], value: Vec<u8>) -> Result<bool, Error> {
+        if self.get_internal(key_nibs)? == Some(value.as_slice()) {
+            return Ok(false);
+        }
+        self.insert_internal(key_nibs, value)
     }
 
     fn insert_internal(&mut self, key_nibs: &[u8], value: Vec<u8>) -> Result<bool, Error> {
@@ -676,13 +832,13 @@ impl MptNode {
                     let child = &mut children[*i as usize];
                     match child {
                         Some(node) => {
-                            if !node.insert_internal(tail, value)? {
+                            if !Arc::make_mut(node).insert_internal(tail, value)? {
                                 return Ok(false);
                             }
                         }
                         // if the corresponding child is empty, insert a new leaf
                         None => {
-                            *child = Some(Box::new(
+                            *child = Some(Arc::new(
                                 MptNodeData::Leaf(to_encoded_path(tail, true), value).into(),
                             ));
                         }
@@ -705,16 +861,16 @@ impl MptNode {
                 } else {
                     let split_point = common_len + 1;
                     // otherwise, create a branch with two children
-                    let mut children: [Option<Box<MptNode>>; 16] = Default::default();
+                    let mut children: [Option<Arc<MptNode>>; 16] = Default::default();
 
-                    children[self_nibs[common_len] as usize] = Some(Box::new(
+                    children[self_nibs[common_len] as usize] = Some(Arc::new(
                         MptNodeData::Leaf(
                             to_encoded_path(&self_nibs[split_point..], true),
                             mem::take(old_value),
                         )
                         .into(),
                     ));
-                    children[key_nibs[common_len] as usize] = Some(Box::new(
+                    children[key_nibs[common_len] as usize] = Some(Arc::new(
                         MptNodeData::Leaf(to_encoded_path(&key_nibs[split_point..], true), value)
                             .into(),
                     ));
@@ -724,7 +880,7 @@ impl MptNode {
                         // create parent extension for new branch
                         self.data = MptNodeData::Extension(
                             to_encoded_path(&self_nibs[..common_len], false),
-                            Box::new(branch.into()),
+                            Arc::new(branch.into()),
                         );
                     } else {
                         self.data = branch;
@@ -736,7 +892,9 @@ impl MptNode {
                 let common_len = lcp(&self_nibs, key_nibs);
                 if common_len == self_nibs.len() {
                     // traverse down for update
-                    if !existing_child.insert_internal(&key_nibs[common_len..], value)? {
+                    if !Arc::make_mut(existing_child)
+                        .insert_internal(&key_nibs[common_len..], value)?
+                    {
                         return Ok(false);
                     }
                 } else if common_len == key_nibs.len() {
@@ -744,10 +902,10 @@ impl MptNode {
                 } else {
                     let split_point = common_len + 1;
                     // otherwise, create a branch with two children
-                    let mut children: [Option<Box<MptNode>>; 16] = Default::default();
+                    let mut children: [Option<Arc<MptNode>>; 16] = Default::default();
 
                     children[self_nibs[common_len] as usize] = if split_point < self_nibs.len() {
-                        Some(Box::new(
+                        Some(Arc::new(
                             MptNodeData::Extension(
                                 to_encoded_path(&self_nibs[split_point..], false),
                                 mem::take(existing_child),
@@ -757,7 +915,7 @@ impl MptNode {
                     } else {
                         Some(mem::take(existing_child))
                     };
-                    children[key_nibs[common_len] as usize] = Some(Box::new(
+                    children[key_nibs[common_len] as usize] = Some(Arc::new(
                         MptNodeData::Leaf(to_encoded_path(&key_nibs[split_point..], true), value)
                             .into(),
                     ));
@@ -767,7 +925,7 @@ impl MptNode {
                         // Create parent extension for new branch
                         self.data = MptNodeData::Extension(
                             to_encoded_path(&self_nibs[..common_len], false),
-                            Box::new(branch.into()),
+                            Arc::new(branch.into()),
                         );
                     } else {
                         self.data = branch;
@@ -782,7 +940,7 @@ impl MptNode {
     }
 
     fn invalidate_ref_cache(&mut self) {
-        self.cached_reference.borrow_mut().take();
+        self.cached_reference.take();
     }
 
     /// Returns the number of traversable nodes in the trie.
@@ -801,6 +959,17 @@ impl MptNode {
         }
     }
 
+    /// Returns an iterator over the key/value pairs of the trie, in ascending key order.
+    ///
+    /// The keys are returned as nibbles, see [to_nibs]. Unresolved subtries cannot be walked,
+    /// so the iterator yields [Error::NodeNotResolved] for every digest and carries on with the
+    /// rest of the trie.
+    pub fn iter(&self) -> MptIter<'_> {
+        MptIter {
+            stack: vec![(self, Vec::new())],
+        }
+    }
+
     /// Formats the trie as a string list, where each line corresponds to a trie leaf.
     ///
     /// This method is primarily used for debugging purposes, providing a visual
@@ -863,17 +1032,61 @@ impl MptNode {
     }
 }
 
+/// An iterator over the key/value pairs of a trie, returned by [MptNode::iter].
+pub struct MptIter<'a> {
+    /// The nodes left to visit, with the nibbles of the path leading to them.
+    stack: Vec<(&'a MptNode, Vec<u8>)>,
+}
+
+impl<'a> Iterator for MptIter<'a> {
+    type Item = Result<(Vec<u8>, &'a [u8]), Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some((node, mut path)) = self.stack.pop() {
+            match node.as_data() {
+                MptNodeData::Null => {}
+                MptNodeData::Branch(children) => {
+                    // push in reverse, so that the children are visited in ascending order
+                    for (i, child) in children.iter().enumerate().rev() {
+                        if let Some(child) = child {
+                            let mut child_path = path.clone();
+                            child_path.push(i as u8);
+                            self.stack.push((child, child_path));
+                        }
+                    }
+                }
+                MptNodeData::Leaf(prefix, value) => {
+                    path.extend(prefix_nibs(prefix));
+                    return Some(Ok((path, value)));
+                }
+                MptNodeData::Extension(prefix, child) => {
+                    path.extend(prefix_nibs(prefix));
+                    self.stack.push((child, path));
+                }
+                MptNodeData::Digest(digest) => return Some(Err(Error::NodeNotResolved(*digest))),
+            }
+        }
+        None
+    }
+}
+
 /// Converts a byte slice into a vector of nibbles.
 ///
 /// A nibble is 4 bits or half of an 8-bit byte. This function takes each byte from the
 /// input slice, splits it into two nibbles, and appends them to the resulting vector.
 pub fn to_nibs(slice: &[u8]) -> Vec<u8> {
     let mut result = Vec::with_capacity(2 * slice.len());
+    to_nibs_into(slice, &mut result);
+    result
+}
+
+/// Writes the nibbles of a slice of bytes into `out`, replacing its contents.
+fn to_nibs_into(slice: &[u8], out: &mut Vec<u8>) {
+    out.clear();
     for byte in slice {
-        result.push(byte >> 4);
-        result.push(byte & 0xf);
+        out.push(byte >> 4);
+        out.push(byte & 0xf);
     }
-    result
 }
 
 /// Encodes a slice of nibbles into a vector of bytes, with an additional prefix to
@@ -907,6 +1120,30 @@ fn lcp(a: &[u8], b: &[u8]) -> usize {
     cmp::min(a.len(), b.len())
 }
 
+/// Strips the path of a leaf or extension from the start of the nibbles, and returns the remaining
+/// nibbles, or [None] if they do not start with the path.
+///
+/// The path is compared in its encoded form, so that lookups do not allocate the nibbles of every
+/// node they pass, like [prefix_nibs] does.
+fn strip_encoded_path<'a>(prefix: &[u8], mut nibs: &'a [u8]) -> Option<&'a [u8]> {
+    let (extension, tail) = prefix.split_first()?;
+    // the first bit of the first nibble denotes the parity
+    if extension & (1 << 4) != 0 {
+        let (nib, rest) = nibs.split_first()?;
+        if *nib != extension & 0xf {
+            return None;
+        }
+        nibs = rest;
+    }
+    if nibs.len() < 2 * tail.len() {
+        return None;
+    }
+    let (path, rest) = nibs.split_at(2 * tail.len());
+    iter::zip(tail, path.chunks_exact(2))
+        .all(|(byte, pair)| *byte == ((pair[0] << 4) | pair[1]))
+        .then_some(rest)
+}
+
 fn prefix_nibs(prefix: &[u8]) -> Vec<u8> {
     let (extension, tail) = prefix.split_first().unwrap();
     // the first bit of the first nibble denotes the parity
@@ -953,7 +1190,7 @@ pub fn mpt_from_proof(proof_nodes: &[MptNode]) -> Result<MptNode> {
                 if let Some(child) = children.iter_mut().flatten().find(
                     |child| matches!(child.as_data(), MptNodeData::Digest(d) if d == child_ref),
                 ) {
-                    *child = Box::new(replacement);
+                    *child = Arc::new(replacement);
                 } else {
                     panic!("node {} does not reference the successor", i);
                 }
@@ -963,7 +1200,7 @@ pub fn mpt_from_proof(proof_nodes: &[MptNode]) -> Result<MptNode> {
                 if !matches!(child.as_data(), MptNodeData::Digest(d) if d == child_ref) {
                     panic!("node {} does not reference the successor", i);
                 }
-                MptNodeData::Extension(prefix, Box::new(replacement)).into()
+                MptNodeData::Extension(prefix, Arc::new(replacement)).into()
             }
             MptNodeData::Null | MptNodeData::Leaf(_, _) | MptNodeData::Digest(_) => {
                 panic!("node {} has no children to replace", i);
@@ -979,9 +1216,11 @@ pub fn mpt_from_proof(proof_nodes: &[MptNode]) -> Result<MptNode> {
 
 /// Verifies that the given proof is a valid proof of exclusion for the given key.
 pub fn is_not_included(key: &[u8], proof_nodes: &[MptNode]) -> Result<bool> {
-    let proof_trie = mpt_from_proof(proof_nodes).unwrap();
+    let proof_trie = mpt_from_proof(proof_nodes)?;
     // for valid proofs, the get must not fail
-    let value = proof_trie.get(key).unwrap();
+    let value = proof_trie
+        .get(key)
+        .context("proof does not resolve the path of the key")?;
 
     Ok(value.is_none())
 }
@@ -996,13 +1235,13 @@ pub fn resolve_nodes(root: &MptNode, node_store: &HashMap<MptNodeReference, MptN
                 .map(|child| {
                     child
                         .as_ref()
-                        .map(|node| Box::new(resolve_nodes(node, node_store)))
+                        .map(|node| Arc::new(resolve_nodes(node, node_store)))
                 })
                 .collect();
             MptNodeData::Branch(children.try_into().unwrap()).into()
         }
         MptNodeData::Extension(prefix, target) => {
-            MptNodeData::Extension(prefix.clone(), Box::new(resolve_nodes(target, node_store)))
+            MptNodeData::Extension(prefix.clone(), Arc::new(resolve_nodes(target, node_store)))
                 .into()
         }
         MptNodeData::Digest(digest) => {
@@ -1045,10 +1284,71 @@ pub fn shorten_node_path(node: &MptNode) -> Vec<MptNode> {
     res
 }
 
-pub fn proofs_to_tries(
+/// The nodes of an account proof and of the proofs of its storage slots, which the tries of a
+/// witness are built from.
+///
+/// This is implemented for the EIP-1186 responses of `eth_getProof` as well as for the proofs a
+/// reth node generates itself, so that witnesses can be built from either.
+pub trait AccountProofNodes {
+    /// The type of an RLP-encoded node.
+    type Node: AsRef<[u8]>;
+
+    /// Returns the RLP-encoded nodes on the path of the account in the state trie.
+    fn account_proof(&self) -> &[Self::Node];
+
+    /// Returns the storage root of the account.
+    fn storage_root(&self) -> B256;
+
+    /// Returns the proven storage slots, each with the RLP-encoded nodes on its path in the
+    /// storage trie.
+    fn storage_proofs(&self) -> Vec<(B256, &[Self::Node])>;
+}
+
+impl AccountProofNodes for EIP1186AccountProofResponse {
+    type Node = alloy_primitives::Bytes;
+
+    fn account_proof(&self) -> &[Self::Node] {
+        &self.account_proof
+    }
+
+    fn storage_root(&self) -> B256 {
+        self.storage_hash
+    }
+
+    fn storage_proofs(&self) -> Vec<(B256, &[Self::Node])> {
+        self.storage_proof
+            .iter()
+            .map(|proof| (proof.key.0, proof.proof.as_slice()))
+            .collect()
+    }
+}
+
+impl AccountProofNodes for AccountProof {
+    type Node = reth_primitives::Bytes;
+
+    fn account_proof(&self) -> &[Self::Node] {
+        &self.proof
+    }
+
+    fn storage_root(&self) -> B256 {
+        self.storage_root
+    }
+
+    fn storage_proofs(&self) -> Vec<(B256, &[Self::Node])> {
+        self.storage_proofs
+            .iter()
+            .map(|proof| (proof.key, proof.proof.as_slice()))
+            .collect()
+    }
+}
+
+/// Builds the state trie and the storage tries of a witness from the proofs of the touched
+/// accounts against the parent state, and from their proofs against the state after the block,
+/// which provide the nodes needed to delete accounts and slots.
+pub fn proofs_to_tries<P: AccountProofNodes>(
     state_root: B256,
-    parent_proofs: HashMap<Address, EIP1186AccountProofResponse>,
-    proofs: HashMap<Address, EIP1186AccountProofResponse>,
+    parent_proofs: HashMap<Address, P>,
+    proofs: HashMap<Address, P>,
 ) -> Result<(MptNode, HashMap<Address, StorageEntry>)> {
     // if no addresses are provided, return the trie only consisting of the state root
     if parent_proofs.is_empty() {
@@ -1060,7 +1360,7 @@ pub fn proofs_to_tries(
     let mut state_nodes = HashMap::new();
     let mut state_root_node = MptNode::default();
     for (address, proof) in parent_proofs {
-        let proof_nodes = parse_proof(&proof.account_proof).unwrap();
+        let proof_nodes = parse_proof(proof.account_proof()).unwrap();
         mpt_from_proof(&proof_nodes).unwrap();
 
         // the first node in the proof is the root
@@ -1075,11 +1375,16 @@ pub fn proofs_to_tries(
         let fini_proofs = proofs.get(&address).unwrap();
 
         // assure that addresses can be deleted from the state trie
-        add_orphaned_leafs(address, &fini_proofs.account_proof, &mut state_nodes)?;
+        add_orphaned_nodes(
+            &keccak(address),
+            fini_proofs.account_proof(),
+            &mut state_nodes,
+        )?;
 
         // if no slots are provided, return the trie only consisting of the storage root
-        let storage_root = proof.storage_hash;
-        if proof.storage_proof.is_empty() {
+        let storage_root = proof.storage_root();
+        let storage_proofs = proof.storage_proofs();
+        if storage_proofs.is_empty() {
             let storage_root_node = node_from_digest(storage_root);
             storage.insert(address, (storage_root_node, vec![]));
             continue;
@@ -1087,8 +1392,8 @@ pub fn proofs_to_tries(
 
         let mut storage_nodes = HashMap::new();
         let mut storage_root_node = MptNode::default();
-        for storage_proof in &proof.storage_proof {
-            let proof_nodes = parse_proof(&storage_proof.proof).unwrap();
+        for (_, storage_proof) in &storage_proofs {
+            let proof_nodes = parse_proof(*storage_proof).unwrap();
             mpt_from_proof(&proof_nodes).unwrap();
 
             // the first node in the proof is the root
@@ -1102,22 +1407,17 @@ pub fn proofs_to_tries(
         }
 
         // assure that slots can be deleted from the storage trie
-        for storage_proof in &fini_proofs.storage_proof {
-            add_orphaned_leafs(
-                storage_proof.key.0 .0,
-                &storage_proof.proof,
-                &mut storage_nodes,
-            )?;
+        for (key, storage_proof) in fini_proofs.storage_proofs() {
+            add_orphaned_nodes(&keccak(key), storage_proof, &mut storage_nodes)?;
         }
         // create the storage trie, from all the relevant nodes
         let storage_trie = resolve_nodes(&storage_root_node, &storage_nodes);
         assert_eq!(storage_trie.hash(), storage_root);
 
         // convert the slots to a vector of U256
-        let slots = proof
-            .storage_proof
+        let slots = storage_proofs
             .iter()
-            .map(|p| U256::from_be_bytes(p.key.0 .0))
+            .map(|(key, _)| U256::from_be_bytes(key.0))
             .collect();
         storage.insert(address, (storage_trie, slots));
     }
@@ -1127,21 +1427,35 @@ pub fn proofs_to_tries(
     Ok((state_trie, storage))
 }
 
-/// Adds all the leaf nodes of non-inclusion proofs to the nodes.
-fn add_orphaned_leafs(
-    key: impl AsRef<[u8]>,
+/// Adds the potential orphans of a deleted key to the nodes, given the proof of its exclusion
+/// from the trie after the block.
+///
+/// Deleting a key can collapse a branch onto its only remaining child. If that child is a leaf or
+/// an extension, it absorbs the nibble of the branch, so it must be resolved even when the witness
+/// only contains its hash. After the block, the collapsed node is the node where the path of the
+/// deleted key diverges, i.e. the last node of its exclusion proof: every version of it with a
+/// shortened path is added, one of which is the orphan as it is referenced before the block.
+fn add_orphaned_nodes(
+    key: &[u8],
     proof: &[impl AsRef<[u8]>],
     nodes_by_reference: &mut HashMap<MptNodeReference, MptNode>,
 ) -> Result<()> {
-    if !proof.is_empty() {
-        let proof_nodes = parse_proof(proof).context("invalid proof encoding")?;
-        if is_not_included(&keccak(key), &proof_nodes)? {
-            // add the leaf node to the nodes
-            let leaf = proof_nodes.last().unwrap();
-            shorten_node_path(leaf).into_iter().for_each(|node| {
-                nodes_by_reference.insert(node.reference(), node);
-            });
-        }
+    if proof.is_empty() {
+        return Ok(());
+    }
+    // some clients also list the nodes embedded in their parent, which are part of their parent
+    let proof_nodes = parse_proof(proof)
+        .context("invalid proof encoding")?
+        .into_iter()
+        .enumerate()
+        .filter(|(i, node)| *i == 0 || matches!(node.reference(), MptNodeReference::Digest(_)))
+        .map(|(_, node)| node)
+        .collect::<Vec<_>>();
+    if is_not_included(key, &proof_nodes)? {
+        let diverging = proof_nodes.last().unwrap();
+        shorten_node_path(diverging).into_iter().for_each(|node| {
+            nodes_by_reference.insert(node.reference(), node);
+        });
     }
 
     Ok(())
@@ -1158,6 +1472,8 @@ fn node_from_digest(digest: B256) -> MptNode {
 #[cfg(test)]
 mod tests {
     use hex_literal::hex;
+    use proptest::prelude::*;
+    use std::collections::BTreeMap;
 
     use super::*;
 
@@ -1194,6 +1510,22 @@ mod tests {
         assert_eq!(to_encoded_path(&nibbles, true), vec![0x3a, 0xbc]);
     }
 
+    #[test]
+    pub fn test_strip_encoded_path() {
+        let nibs = [0x1, 0x2, 0x3, 0x4, 0x5];
+        for len in 0..=nibs.len() {
+            for is_leaf in [false, true] {
+                let prefix = to_encoded_path(&nibs[..len], is_leaf);
+                assert_eq!(strip_encoded_path(&prefix, &nibs), Some(&nibs[len..]));
+                assert_eq!(strip_encoded_path(&prefix, &nibs[..len]), Some(&[][..]));
+                if len > 0 {
+                    assert_eq!(strip_encoded_path(&prefix, &nibs[..len - 1]), None);
+                    assert_eq!(strip_encoded_path(&prefix, &nibs[1..]), None);
+                }
+            }
+        }
+    }
+
     #[test]
     pub fn test_lcp() {
         let cases = [
@@ -1285,7 +1617,7 @@ mod tests {
         let MptNodeData::Extension(_, node) = &mut trie.data else {
             panic!("extension expected")
         };
-        **node = MptNodeData::Digest(node.hash()).into();
+        *node = Arc::new(MptNodeData::Digest(node.hash()).into());
         assert!(node.is_digest());
 
         let trie = MptNode::decode(trie.to_rlp()).unwrap();
@@ -1296,6 +1628,111 @@ mod tests {
         trie.get(b"a0").unwrap_err();
     }
 
+    #[test]
+    pub fn test_copy_on_write() {
+        let mut trie = MptNode::default();
+        for i in 0..16usize {
+            trie.insert_rlp(&keccak(i.to_be_bytes()), i).unwrap();
+        }
+        let exp_hash = trie.hash();
+
+        // modifying a clone must not affect the original trie
+        let mut clone = trie.clone();
+        clone
+            .insert_rlp(&keccak(16usize.to_be_bytes()), 16usize)
+            .unwrap();
+        assert!(clone.delete(&keccak(0usize.to_be_bytes())).unwrap());
+        assert_ne!(clone.hash(), exp_hash);
+        assert_eq!(trie.hash(), exp_hash);
+        assert_eq!(
+            trie.get_rlp(&keccak(0usize.to_be_bytes())).unwrap(),
+            Some(0usize)
+        );
+        assert!(trie.get(&keccak(16usize.to_be_bytes())).unwrap().is_none());
+    }
+
+    #[test]
+    pub fn test_reference_cache() {
+        let mut trie = MptNode::default();
+        for i in 0..256usize {
+            trie.insert_rlp(&keccak(i.to_be_bytes()), i).unwrap();
+        }
+        trie.hash();
+
+        // only the path of the inserted key loses its cached reference
+        let key = keccak(256usize.to_be_bytes());
+        trie.insert_rlp(&key, 256usize).unwrap();
+        assert!(trie.cached_reference.get().is_none());
+        let MptNodeData::Branch(children) = trie.as_data() else {
+            panic!("root is not a branch");
+        };
+        for (nib, child) in children.iter().enumerate() {
+            let cached = child.as_ref().unwrap().cached_reference.get().is_some();
+            assert_eq!(cached, nib != (key[0] >> 4) as usize);
+        }
+
+        // the partially cached trie hashes like one built from scratch
+        let mut expected = MptNode::default();
+        for i in 0..=256usize {
+            expected.insert_rlp(&keccak(i.to_be_bytes()), i).unwrap();
+        }
+        assert_eq!(trie.hash(), expected.hash());
+    }
+
+    #[test]
+    pub fn test_unchanged_update() {
+        let mut trie = MptNode::default();
+        for i in 0..256usize {
+            trie.insert_rlp(&keccak(i.to_be_bytes()), i).unwrap();
+        }
+        trie.hash();
+
+        // writing back an unchanged value or deleting an absent key neither copies the shared
+        // nodes nor clears any cached reference
+        let mut clone = trie.clone();
+        assert!(!clone
+            .insert_rlp(&keccak(0usize.to_be_bytes()), 0usize)
+            .unwrap());
+        assert!(!clone.delete(&keccak(256usize.to_be_bytes())).unwrap());
+        assert!(clone.cached_reference.get().is_some());
+        let (MptNodeData::Branch(children), MptNodeData::Branch(clone_children)) =
+            (trie.as_data(), clone.as_data())
+        else {
+            panic!("root is not a branch");
+        };
+        for (child, clone_child) in children.iter().zip(clone_children) {
+            assert!(Arc::ptr_eq(
+                child.as_ref().unwrap(),
+                clone_child.as_ref().unwrap()
+            ));
+        }
+    }
+
+    #[test]
+    pub fn test_scratch_nibs() {
+        let mut trie = MptNode::default();
+        let mut reference = MptNode::default();
+        let mut nibs = Vec::new();
+        for i in 0..64usize {
+            trie.insert_rlp_with(&keccak(i.to_be_bytes()), i, &mut nibs)
+                .unwrap();
+            reference.insert_rlp(&keccak(i.to_be_bytes()), i).unwrap();
+        }
+        assert_eq!(trie.hash(), reference.hash());
+
+        // reusing the scratch space across deletions must match the allocating variant
+        for i in 0..32usize {
+            assert!(trie
+                .delete_with(&keccak(i.to_be_bytes()), &mut nibs)
+                .unwrap());
+            assert!(reference.delete(&keccak(i.to_be_bytes())).unwrap());
+        }
+        assert!(!trie
+            .delete_with(&keccak(0usize.to_be_bytes()), &mut nibs)
+            .unwrap());
+        assert_eq!(trie.hash(), reference.hash());
+    }
+
     #[test]
     pub fn test_branch_value() {
         let mut trie = MptNode::default();
@@ -1380,6 +1817,236 @@ mod tests {
         assert!(trie.is_empty());
     }
 
+    #[test]
+    pub fn test_verify_proof() {
+        let mut trie = MptNode::default();
+        for i in 0..256usize {
+            trie.insert_rlp(&keccak(i.to_be_bytes()), i).unwrap();
+        }
+        let root = trie.hash();
+
+        // inclusion and exclusion
+        for i in 0..512usize {
+            let key = keccak(i.to_be_bytes());
+            let value = MptNode::verify_proof(root, &key, &trie.prove(&key).unwrap()).unwrap();
+            assert_eq!(value.as_deref(), trie.get(&key).unwrap());
+        }
+
+        // a truncated proof does not resolve the key
+        let key = keccak(0usize.to_be_bytes());
+        let mut truncated = trie.prove(&key).unwrap();
+        truncated.pop();
+        assert!(matches!(
+            MptNode::verify_proof(root, &key, &truncated),
+            Err(Error::NodeNotResolved(_))
+        ));
+
+        // a tampered node no longer matches the hash referencing it
+        let mut tampered = trie
+            .prove(&key)
+            .unwrap()
+            .iter()
+            .map(|node| node.to_vec())
+            .collect::<Vec<_>>();
+        let leaf = tampered.last_mut().unwrap();
+        *leaf.last_mut().unwrap() ^= 1;
+        assert!(MptNode::verify_proof(root, &key, &tampered).is_err());
+
+        // the empty trie needs no proof
+        assert_eq!(
+            MptNode::verify_proof(EMPTY_ROOT, &key, &[] as &[Vec<u8>]).unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    pub fn test_prove() {
+        let mut trie = MptNode::default();
+        for i in 0..256usize {
+            trie.insert_rlp(&keccak(i.to_be_bytes()), i).unwrap();
+        }
+        let root = trie.hash();
+
+        // proofs can be parsed into a partial trie, like the proofs of an RPC
+        for i in 0..512usize {
+            let key = keccak(i.to_be_bytes());
+            let proof = trie.prove(&key).unwrap();
+            assert_eq!(keccak(&proof[0]), root.0);
+            let proof_nodes = parse_proof(&proof).unwrap();
+            assert_eq!(mpt_from_proof(&proof_nodes).unwrap().hash(), root);
+            assert_eq!(is_not_included(&key, &proof_nodes).unwrap(), i >= 256);
+        }
+
+        // the empty trie needs no proof
+        assert!(MptNode::default().prove(&[0; 32]).unwrap().is_empty());
+
+        // keys below unresolved nodes cannot be proven
+        let partial = resolve_nodes(&node_from_digest(root), &HashMap::new());
+        assert!(matches!(
+            partial.prove(&[0; 32]),
+            Err(Error::NodeNotResolved(_))
+        ));
+    }
+
+    /// Deletes the key from the trie resolved from the proof of the key before the deletion, and
+    /// returns the root, optionally resolving the orphans given by the proof after the deletion.
+    fn delete_from_witness(trie: &MptNode, key: &[u8], with_orphans: bool) -> B256 {
+        let mut nodes = HashMap::new();
+        for node in parse_proof(&trie.prove(key).unwrap()).unwrap() {
+            nodes.insert(node.reference(), node);
+        }
+        if with_orphans {
+            let mut fini = trie.clone();
+            fini.delete(key).unwrap();
+            add_orphaned_nodes(key, &fini.prove(key).unwrap(), &mut nodes).unwrap();
+        }
+        let mut witness = resolve_nodes(&node_from_digest(trie.hash()), &nodes);
+        assert!(witness.delete(key).unwrap());
+        witness.hash()
+    }
+
+    #[test]
+    pub fn test_orphaned_nodes() {
+        let deleted = [0x00; 32];
+        let value = vec![0x42; 40];
+
+        // the branch collapses onto a leaf only known by its hash
+        let mut trie = MptNode::default();
+        trie.insert(&deleted, value.clone()).unwrap();
+        trie.insert(&[0x10; 32], value.clone()).unwrap();
+        let mut expected = trie.clone();
+        expected.delete(&deleted).unwrap();
+        assert_ne!(delete_from_witness(&trie, &deleted, false), expected.hash());
+        assert_eq!(delete_from_witness(&trie, &deleted, true), expected.hash());
+
+        // the branch collapses onto an extension only known by its hash
+        let mut trie = MptNode::default();
+        trie.insert(&deleted, value.clone()).unwrap();
+        let mut key = [0x10; 32];
+        trie.insert(&key, value.clone()).unwrap();
+        key[31] = 0x11;
+        trie.insert(&key, value.clone()).unwrap();
+        let mut expected = trie.clone();
+        expected.delete(&deleted).unwrap();
+        assert_ne!(delete_from_witness(&trie, &deleted, false), expected.hash());
+        assert_eq!(delete_from_witness(&trie, &deleted, true), expected.hash());
+    }
+
+    /// Computes the root of the trie of the given entries from scratch, independently of the
+    /// insertion and deletion logic of [MptNode].
+    fn reference_root(entries: &BTreeMap<Vec<u8>, Vec<u8>>) -> B256 {
+        fn encode_list(items: &[Vec<u8>]) -> Vec<u8> {
+            let payload_length = items.iter().map(Vec::len).sum();
+            let mut out = Vec::new();
+            alloy_rlp::Header {
+                list: true,
+                payload_length,
+            }
+            .encode(&mut out);
+            items.iter().for_each(|item| out.extend_from_slice(item));
+            out
+        }
+
+        fn reference(encoded: Vec<u8>) -> Vec<u8> {
+            if encoded.len() < 32 {
+                encoded
+            } else {
+                alloy_rlp::encode(B256::from(keccak(encoded)))
+            }
+        }
+
+        fn encode_node(entries: &[(Vec<u8>, &Vec<u8>)], depth: usize) -> Vec<u8> {
+            match entries {
+                [] => vec![alloy_rlp::EMPTY_STRING_CODE],
+                [(nibs, value)] => encode_list(&[
+                    alloy_rlp::encode(to_encoded_path(&nibs[depth..], true).as_slice()),
+                    alloy_rlp::encode(value.as_slice()),
+                ]),
+                _ => {
+                    let (first, last) = (&entries[0].0, &entries[entries.len() - 1].0);
+                    let shared = lcp(&first[depth..], &last[depth..]);
+                    if shared > 0 {
+                        let path = to_encoded_path(&first[depth..depth + shared], false);
+                        return encode_list(&[
+                            alloy_rlp::encode(path.as_slice()),
+                            reference(encode_node(entries, depth + shared)),
+                        ]);
+                    }
+                    let mut items: Vec<_> = (0..16u8)
+                        .map(|nib| {
+                            let children: Vec<_> = entries
+                                .iter()
+                                .filter(|(nibs, _)| nibs[depth] == nib)
+                                .cloned()
+                                .collect();
+                            if children.is_empty() {
+                                vec![alloy_rlp::EMPTY_STRING_CODE]
+                            } else {
+                                reference(encode_node(&children, depth + 1))
+                            }
+                        })
+                        .collect();
+                    items.push(vec![alloy_rlp::EMPTY_STRING_CODE]);
+                    encode_list(&items)
+                }
+            }
+        }
+
+        let entries: Vec<_> = entries
+            .iter()
+            .map(|(key, value)| (to_nibs(key), value))
+            .collect();
+        if entries.is_empty() {
+            EMPTY_ROOT
+        } else {
+            keccak(encode_node(&entries, 0)).into()
+        }
+    }
+
+    #[derive(Debug, Clone)]
+    enum Op {
+        Insert(Vec<u8>, Vec<u8>),
+        Delete(Vec<u8>),
+    }
+
+    /// Generates keys from a small alphabet, so that they often share prefixes and updates split
+    /// extensions and collapse branches, and values of lengths around the 32 bytes below which
+    /// nodes are embedded in their parent.
+    fn op() -> impl Strategy<Value = Op> {
+        let key = prop::collection::vec(prop::sample::select(vec![0x00u8, 0x01, 0x10, 0xab]), 3);
+        let value = prop::collection::vec(any::<u8>(), 1..40);
+        prop_oneof![
+            2 => (key.clone(), value).prop_map(|(key, value)| Op::Insert(key, value)),
+            1 => key.prop_map(Op::Delete),
+        ]
+    }
+
+    proptest! {
+        #[test]
+        fn test_reference_trie(ops in prop::collection::vec(op(), 1..64)) {
+            let mut trie = MptNode::default();
+            let mut entries = BTreeMap::new();
+            for op in ops {
+                match op {
+                    Op::Insert(key, value) => {
+                        let changed = entries.get(&key) != Some(&value);
+                        prop_assert_eq!(trie.insert(&key, value.clone()).unwrap(), changed);
+                        entries.insert(key, value);
+                    }
+                    Op::Delete(key) => {
+                        let removed = entries.remove(&key).is_some();
+                        prop_assert_eq!(trie.delete(&key).unwrap(), removed);
+                    }
+                }
+                prop_assert_eq!(trie.hash(), reference_root(&entries));
+            }
+            for (key, value) in &entries {
+                prop_assert_eq!(trie.get(key).unwrap(), Some(value.as_slice()));
+            }
+            prop_assert_eq!(trie.iter().count(), entries.len());
+        }
+    }
+
     #[test]
     pub fn test_index_trie() {
         const N: usize = 512;
@@ -1419,4 +2086,47 @@ mod tests {
         }
         assert!(trie.is_empty());
     }
+
+    #[test]
+    pub fn test_iter() {
+        assert_eq!(MptNode::default().iter().count(), 0);
+
+        let mut trie = MptNode::default();
+        let mut expected = Vec::new();
+        for i in 0..256u64 {
+            let key = keccak(i.to_be_bytes());
+            trie.insert_rlp(&key, i).unwrap();
+            expected.push((to_nibs(&key), i.to_rlp()));
+        }
+        expected.sort();
+
+        let entries = trie
+            .iter()
+            .map(|entry| entry.map(|(nibs, value)| (nibs, value.to_vec())))
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(entries, expected);
+
+        // unresolved subtries are reported, the rest of the trie is still walked
+        let mut trie = MptNode::default();
+        trie.insert_rlp(b"aa", 0u8).unwrap();
+        trie.insert_rlp(b"ab", 1u8).unwrap();
+        trie.insert_rlp(b"ba", 2u8).unwrap();
+        let MptNodeData::Extension(_, node) = &mut trie.data else {
+            panic!("extension expected")
+        };
+        let MptNodeData::Branch(children) = &mut Arc::make_mut(node).data else {
+            panic!("branch expected")
+        };
+        let child = children[1].as_mut().unwrap();
+        *child = Arc::new(MptNodeData::Digest(child.hash()).into());
+
+        let entries = trie.iter().collect::<Vec<_>>();
+        assert_eq!(entries.len(), 2);
+        assert!(matches!(entries[0], Err(Error::NodeNotResolved(_))));
+        assert_eq!(
+            entries[1].as_ref().unwrap(),
+            &(to_nibs(b"ba"), &2u8.to_rlp()[..])
+        );
+    }
 }
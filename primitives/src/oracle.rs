@@ -0,0 +1,71 @@
+//! A commitment to a block in the ABI of L1 block-hash oracle contracts, so that settlement
+//! contracts can consume proofs without decoding the serialization of the zkVM.
+
+use reth_primitives::{B256, U256};
+
+/// The length of an ABI encoded [BlockCommitment], made of three 32-byte words.
+pub const BLOCK_COMMITMENT_LEN: usize = 96;
+
+/// The number, hash and state root of a proven block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BlockCommitment {
+    /// The number of the block.
+    pub block_number: u64,
+
+    /// The hash of the block.
+    pub block_hash: B256,
+
+    /// The state root of the block.
+    pub state_root: B256,
+}
+
+impl BlockCommitment {
+    /// Encodes the commitment as `abi.encode(uint256 blockNumber, bytes32 blockHash,
+    /// bytes32 stateRoot)`.
+    pub fn abi_encode(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(BLOCK_COMMITMENT_LEN);
+        out.extend_from_slice(&U256::from(self.block_number).to_be_bytes::<32>());
+        out.extend_from_slice(self.block_hash.as_slice());
+        out.extend_from_slice(self.state_root.as_slice());
+        out
+    }
+
+    /// Decodes a commitment encoded by [BlockCommitment::abi_encode].
+    ///
+    /// Returns [None] if the data has the wrong length or the block number does not fit in 64
+    /// bits.
+    pub fn abi_decode(data: &[u8]) -> Option<Self> {
+        if data.len() != BLOCK_COMMITMENT_LEN {
+            return None;
+        }
+        let block_number = U256::from_be_slice(&data[..32]).try_into().ok()?;
+        Some(Self {
+            block_number,
+            block_hash: B256::from_slice(&data[32..64]),
+            state_root: B256::from_slice(&data[64..]),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_abi_round_trip() {
+        let commitment = BlockCommitment {
+            block_number: 19_000_000,
+            block_hash: B256::repeat_byte(1),
+            state_root: B256::repeat_byte(2),
+        };
+        let encoded = commitment.abi_encode();
+        assert_eq!(encoded.len(), BLOCK_COMMITMENT_LEN);
+        assert_eq!(BlockCommitment::abi_decode(&encoded), Some(commitment));
+
+        // Other lengths and block numbers beyond 64 bits are rejected.
+        assert_eq!(BlockCommitment::abi_decode(&encoded[1..]), None);
+        let mut large_number = encoded;
+        large_number[0] = 1;
+        assert_eq!(BlockCommitment::abi_decode(&large_number), None);
+    }
+}
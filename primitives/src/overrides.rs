@@ -0,0 +1,49 @@
+//! Overrides of the block environment used to simulate variants of real blocks.
+//!
+//! Like execution filters, overrides are only meant for simulations: a block executed with
+//! overrides does not match the canonical block, so the overrides are committed alongside the
+//! result.
+
+use crate::SP1RethInput;
+use reth_primitives::{Address, B256};
+use serde::{Deserialize, Serialize};
+
+/// Fields of the block replaced before the block is executed. Fields left as [None] keep the
+/// value of the block.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlockOverrides {
+    /// The timestamp of the block, which also decides the active hardforks.
+    pub timestamp: Option<u64>,
+
+    /// The beneficiary the fees and rewards of the block are credited to.
+    pub beneficiary: Option<Address>,
+
+    /// The gas limit of the block.
+    pub gas_limit: Option<u64>,
+
+    /// The value returned by the `PREVRANDAO` opcode, stored as the mix hash of the header.
+    pub prevrandao: Option<B256>,
+}
+
+impl BlockOverrides {
+    /// Replaces the overridden fields of the block of the input.
+    pub fn apply(&self, input: &mut SP1RethInput) {
+        if let Some(timestamp) = self.timestamp {
+            input.timestamp = timestamp;
+        }
+        if let Some(beneficiary) = self.beneficiary {
+            input.beneficiary = beneficiary;
+        }
+        if let Some(gas_limit) = self.gas_limit {
+            input.gas_limit = gas_limit;
+        }
+        if let Some(prevrandao) = self.prevrandao {
+            input.mix_hash = prevrandao;
+        }
+    }
+
+    /// Returns whether no field is overridden.
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
@@ -0,0 +1,102 @@
+//! The proof-of-work rules of blocks produced before the merge.
+//!
+//! Only the header fields derived by consensus are validated here: the ethash seal itself requires
+//! the epoch's dataset and is not verified.
+
+use crate::chain::{ChainConfig, Consensus, Hardfork};
+use reth_primitives::constants::EMPTY_OMMER_ROOT_HASH;
+use reth_primitives::{Header, U256};
+
+/// The minimum difficulty of a block.
+///
+/// Reference: https://github.com/ethereum/go-ethereum/blob/master/params/protocol_params.go
+pub const MINIMUM_DIFFICULTY: u64 = 131_072;
+
+/// The bound divisor of the difficulty, used in the difficulty adjustment.
+pub const DIFFICULTY_BOUND_DIVISOR: u64 = 2048;
+
+/// The number of blocks in a period of the difficulty bomb.
+pub const EXP_DIFFICULTY_PERIOD: u64 = 100_000;
+
+/// The hardforks delaying the difficulty bomb, together with the delay they introduce, in
+/// activation order.
+///
+/// Reference: https://github.com/ethereum/go-ethereum/blob/master/consensus/ethash/consensus.go
+const BOMB_DELAYS: [(Hardfork, u64); 6] = [
+    (Hardfork::Byzantium, 3_000_000),
+    (Hardfork::Constantinople, 5_000_000),
+    (Hardfork::MuirGlacier, 9_000_000),
+    (Hardfork::London, 9_700_000),
+    (Hardfork::ArrowGlacier, 10_700_000),
+    (Hardfork::GrayGlacier, 11_400_000),
+];
+
+/// Computes the difficulty of a block from its parent.
+///
+/// Reference: https://github.com/ethereum/go-ethereum/blob/master/consensus/ethash/consensus.go
+pub fn calc_difficulty(config: &ChainConfig, parent: &Header, number: u64, timestamp: u64) -> U256 {
+    let is_active = |hardfork| config.is_active(hardfork, number, timestamp);
+    let elapsed = timestamp.saturating_sub(parent.timestamp);
+    let adjustment = parent.difficulty / U256::from(DIFFICULTY_BOUND_DIVISOR);
+
+    // Compute the difficulty adjustment factor, bounded below by -99.
+    let (increase, factor) = if is_active(Hardfork::Byzantium) {
+        let uncles = if parent.ommers_hash == EMPTY_OMMER_ROOT_HASH {
+            1
+        } else {
+            2
+        };
+        signed_factor(uncles, elapsed / 9)
+    } else if is_active(Hardfork::Homestead) {
+        signed_factor(1, elapsed / 10)
+    } else {
+        (elapsed < 13, 1)
+    };
+    let delta = adjustment * U256::from(factor);
+    let mut difficulty = if increase {
+        parent.difficulty.saturating_add(delta)
+    } else {
+        parent.difficulty.saturating_sub(delta)
+    };
+    difficulty = difficulty.max(U256::from(MINIMUM_DIFFICULTY));
+
+    // Add the difficulty bomb, computed from a fake block number once it has been delayed.
+    let delay = BOMB_DELAYS
+        .iter()
+        .rev()
+        .find(|(hardfork, _)| is_active(*hardfork))
+        .map(|(_, delay)| *delay)
+        .unwrap_or_default();
+    let period = number.saturating_sub(delay) / EXP_DIFFICULTY_PERIOD;
+    if period > 1 {
+        difficulty = difficulty.saturating_add(U256::from(1) << (period - 2) as usize);
+    }
+
+    difficulty
+}
+
+/// Returns the direction and magnitude of `max(base - sub, -99)`.
+fn signed_factor(base: u64, sub: u64) -> (bool, u64) {
+    if base >= sub {
+        (true, base - sub)
+    } else {
+        (false, (sub - base).min(99))
+    }
+}
+
+/// Returns the static reward of the miner of a block, which is zero after the merge.
+///
+/// Reference: https://github.com/paradigmxyz/reth/blob/main/crates/consensus/common/src/calc.rs
+pub fn block_reward(config: &ChainConfig, number: u64, timestamp: u64) -> U256 {
+    let is_active = |hardfork| config.is_active(hardfork, number, timestamp);
+    let ether = U256::from(1_000_000_000_000_000_000u128);
+    if config.consensus != Consensus::Ethereum || is_active(Hardfork::Paris) {
+        U256::ZERO
+    } else if is_active(Hardfork::Constantinople) {
+        ether * U256::from(2)
+    } else if is_active(Hardfork::Byzantium) {
+        ether * U256::from(3)
+    } else {
+        ether * U256::from(5)
+    }
+}
@@ -16,24 +16,63 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use crate::chain::Consensus;
+use crate::chain::Hardfork;
+use crate::chain::SYSTEM_ADDRESS;
+#[cfg(feature = "optimism")]
+use crate::chain::{CREATE2_DEPLOYER_ADDRESS, CREATE2_DEPLOYER_CODE_HASH};
+use crate::diff::BlockStateDiff;
+use crate::filter::SkippedTransaction;
+use crate::hooks::ExecutionHooks;
+use crate::mpt;
 use crate::mpt::keccak;
 use crate::mpt::RlpBytes;
 use crate::mpt::StateAccount;
+use crate::mpt::KECCAK_EMPTY;
+use crate::pow;
+use crate::receipts::CompactReceipts;
+use crate::senders::verify_sender_hint;
+use crate::state::StateRootComputer;
+use crate::validation::{BlockValidator, EthereumBeaconValidator};
+use crate::withdrawals::system_withdrawals_call;
 use crate::SP1RethInput;
 
-use anyhow::anyhow;
-use reth_primitives::proofs::ordered_trie_root_with_encoder;
-use reth_primitives::revm_primitives::Account;
-use reth_primitives::{Address, Bloom, Transaction, TransactionKind, TransactionSigned};
-use reth_primitives::{BaseFeeParams, Receipt, ReceiptWithBloom};
-use reth_primitives::{Header, U256};
+use alloy_primitives::address;
+use reth_primitives::constants::{EIP1559_INITIAL_BASE_FEE, EMPTY_ROOT_HASH};
+use reth_primitives::proofs::{calculate_ommers_root, ordered_trie_root_with_encoder};
+use reth_primitives::revm_primitives::{Account, AccountInfo};
+use reth_primitives::TransactionSignedNoHash;
+use reth_primitives::{
+    Address, BaseFeeParams, Bloom, Transaction, TransactionKind, TransactionSigned,
+};
+use reth_primitives::{Bytes, Receipt, ReceiptWithBloom, Withdrawal};
+use reth_primitives::{Header, B256, U256};
 use revm::db::AccountState;
 use revm::db::InMemoryDB;
-use revm::interpreter::Host;
-use revm::primitives::{SpecId, TransactTo, TxEnv};
-use revm::{Database, DatabaseCommit, Evm};
-use std::mem;
-use std::mem::take;
+use revm::handler::register::HandleRegister;
+#[cfg(feature = "strict")]
+use revm::interpreter::Gas;
+use revm::interpreter::{Host, InterpreterResult};
+#[cfg(feature = "optimism")]
+use revm::primitives::OptimismFields;
+use revm::primitives::SpecId;
+use revm::primitives::{
+    BlobExcessGasAndPrice, BlockEnv, EVMError, ExecutionResult, State, TransactTo, TxEnv,
+};
+use revm::primitives::{
+    GAS_PER_BLOB, MAX_CODE_SIZE, MAX_INITCODE_SIZE, VERSIONED_HASH_VERSION_KZG,
+};
+use revm::{inspector_handle_register, Inspector};
+use revm::{Context, CreateFrame, Database, DatabaseCommit, Evm};
+use serde::{Deserialize, Serialize};
+use std::borrow::Borrow;
+#[cfg(feature = "strict")]
+use std::cell::Cell;
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::rc::Rc;
+use std::sync::Arc;
+use thiserror::Error as ThisError;
 
 /// The divisor for the gas limit bound.
 ///
@@ -50,6 +89,295 @@ pub const MINIMUM_GAS_LIMIT: u64 = 5000;
 /// Reference: https://github.com/paradigmxyz/reth/blob/main/crates/primitives/src/constants/mod.rs#L19
 pub const MAXIMUM_EXTRA_DATA_SIZE: usize = 32;
 
+/// The number of vanity bytes at the start of the extra data of Bor blocks.
+///
+/// Reference: https://github.com/maticnetwork/bor/blob/master/consensus/bor/bor.go
+#[cfg(feature = "polygon")]
+pub const BOR_EXTRA_DATA_VANITY: usize = 32;
+
+/// The number of bytes of the validator seal at the end of the extra data of Bor blocks.
+#[cfg(feature = "polygon")]
+pub const BOR_EXTRA_DATA_SEAL: usize = 65;
+
+/// The address of the KZG point evaluation precompile.
+///
+/// Reference: https://eips.ethereum.org/EIPS/eip-4844
+pub const POINT_EVALUATION_ADDRESS: Address = address!("000000000000000000000000000000000000000a");
+
+/// The gas limit of system contract calls, which EIP-4788 introduced for the beacon roots contract
+/// call.
+///
+/// Reference: https://eips.ethereum.org/EIPS/eip-4788
+pub const SYSTEM_CALL_GAS_LIMIT: u64 = 30_000_000;
+
+/// Represents the errors that can occur while processing a block.
+#[derive(Debug, ThisError)]
+pub enum ProcessorError {
+    /// Triggered when execution modified an account whose storage is not part of the witness.
+    #[error("missing witness for account {address}")]
+    MissingWitness { address: Address },
+
+    /// Triggered when execution accessed a storage slot that is not part of the witness. Such
+    /// reads silently resolve to zero in the database, so the block cannot be trusted.
+    #[error("storage slot {index} of account {address} is not part of the witness")]
+    IncompleteStorageWitness { address: Address, index: U256 },
+
+    /// Triggered in strict mode when the state revm assigned to an account is inconsistent with
+    /// the changes made to it.
+    #[error("inconsistent state of account {address}: {reason}")]
+    InconsistentAccountState {
+        address: Address,
+        reason: &'static str,
+    },
+
+    /// Triggered when a transaction of the block fails the checks made before executing it.
+    #[error("invalid transaction {index}: {reason}")]
+    InvalidTransaction {
+        index: usize,
+        reason: TransactionError,
+    },
+
+    /// Triggered when the header derived from the input fails validation.
+    #[error("invalid header: {reason}")]
+    InvalidHeader { reason: String },
+
+    /// Triggered when the base fee of the header violates EIP-1559.
+    #[error("invalid base fee: {0}")]
+    InvalidBaseFee(#[from] BaseFeeError),
+
+    /// Triggered when the chain configuration of the input cannot be used.
+    #[error("invalid chain config: {reason}")]
+    InvalidChainConfig { reason: &'static str },
+
+    /// Triggered when the database fails to read or update the state.
+    #[error("database error: {reason}")]
+    Database { reason: String },
+
+    /// Triggered when the tries of the witness cannot be updated with the post-state.
+    #[error("trie error: {0}")]
+    Trie(#[from] mpt::Error),
+
+    /// Triggered when a system contract call of the block fails.
+    #[error("system call failed: {reason}")]
+    SystemCall { reason: String },
+
+    /// Triggered when the transactions of the input do not match the expected transactions root.
+    #[error("transactions root {root} does not match the expected transactions root {expected}")]
+    TransactionsRootMismatch { root: B256, expected: B256 },
+
+    /// Triggered when the finalized header does not hash to the expected block hash of the input.
+    #[error("block hash {hash} does not match the expected block hash {expected}")]
+    BlockHashMismatch { hash: B256, expected: B256 },
+
+    /// Triggered in strict mode when the beneficiary of a proof-of-work block is not credited
+    /// exactly its block reward.
+    #[error("beneficiary credited {credited} instead of the block reward {expected}")]
+    BlockRewardMismatch { credited: U256, expected: U256 },
+
+    /// Triggered when the options of the input controlling the execution cannot be used.
+    #[error("invalid input: {reason}")]
+    InvalidInput { reason: &'static str },
+
+    /// Triggered when the block may be valid but follows rules the processor does not implement.
+    #[error("unsupported block: {reason}")]
+    UnsupportedBlock { reason: &'static str },
+}
+
+impl ProcessorError {
+    /// Returns the code of the error used in abort records.
+    pub fn code(&self) -> u32 {
+        match self {
+            ProcessorError::MissingWitness { .. } => 1,
+            ProcessorError::IncompleteStorageWitness { .. } => 2,
+            ProcessorError::InconsistentAccountState { .. } => 3,
+            ProcessorError::InvalidTransaction { .. } => 4,
+            ProcessorError::InvalidHeader { .. } => 5,
+            ProcessorError::InvalidBaseFee(_) => 6,
+            ProcessorError::InvalidChainConfig { .. } => 7,
+            ProcessorError::Database { .. } => 9,
+            ProcessorError::Trie(_) => 10,
+            ProcessorError::SystemCall { .. } => 11,
+            ProcessorError::BlockHashMismatch { .. } => 12,
+            ProcessorError::TransactionsRootMismatch { .. } => 13,
+            ProcessorError::BlockRewardMismatch { .. } => 14,
+            ProcessorError::InvalidInput { .. } => 15,
+            ProcessorError::UnsupportedBlock { .. } => 16,
+        }
+    }
+
+    /// Returns whether the error is a violation of the consensus rules by the block, rather than
+    /// an incomplete witness, an inconsistent input or a limitation of the program. Only such
+    /// errors prove a block invalid, provided the block was executed against a
+    /// [crate::db::WitnessDb], on which an incomplete witness fails with a database error instead.
+    pub fn is_consensus_violation(&self) -> bool {
+        match self {
+            ProcessorError::InvalidTransaction { reason, .. } => !matches!(
+                reason,
+                TransactionError::PointEvaluationUnsupported
+                    | TransactionError::CodeTooLarge { .. }
+                    | TransactionError::BeneficiaryFeeMismatch { .. }
+            ),
+            ProcessorError::InvalidBaseFee(err) => {
+                !matches!(err, BaseFeeError::ZeroGasTarget { .. })
+            }
+            ProcessorError::InvalidHeader { .. } => true,
+            _ => false,
+        }
+    }
+
+    /// Returns the index of the transaction the error was triggered by, if any.
+    pub fn transaction_index(&self) -> Option<u32> {
+        match self {
+            ProcessorError::InvalidTransaction { index, .. } => (*index).try_into().ok(),
+            _ => None,
+        }
+    }
+}
+
+/// Represents the reasons a transaction can be rejected before it is executed.
+#[derive(Debug, ThisError)]
+pub enum TransactionError {
+    /// Triggered when the sender of the transaction has deployed code.
+    ///
+    /// Reference: https://eips.ethereum.org/EIPS/eip-3607
+    #[error("sender has deployed code with hash {code_hash}")]
+    SenderHasCode { code_hash: B256 },
+
+    /// Triggered when the gas limit of the transaction does not cover its intrinsic gas.
+    #[error("gas limit {gas_limit} is below the intrinsic gas {intrinsic_gas}")]
+    IntrinsicGasTooLow { gas_limit: u64, intrinsic_gas: u64 },
+
+    /// Triggered when a contract creation transaction has initcode larger than the limit.
+    ///
+    /// Reference: https://eips.ethereum.org/EIPS/eip-3860
+    #[error("initcode of {size} bytes exceeds the limit of {limit} bytes")]
+    InitCodeTooLarge { size: usize, limit: usize },
+
+    /// Triggered when the transaction deployed code larger than the limit. revm halts such
+    /// deployments itself, so this only guards against a misconfigured EVM.
+    ///
+    /// Reference: https://eips.ethereum.org/EIPS/eip-170
+    #[error("deployed code of {size} bytes at {address} exceeds the limit of {limit} bytes")]
+    CodeTooLarge {
+        address: Address,
+        size: usize,
+        limit: usize,
+    },
+
+    /// Triggered when a blob transaction does not reference any blob.
+    #[error("blob transaction without blobs")]
+    EmptyBlobHashes,
+
+    /// Triggered when a blob versioned hash does not use the KZG commitment version.
+    #[error("unsupported version of blob versioned hash {hash}")]
+    UnsupportedBlobVersion { hash: B256 },
+
+    /// Triggered when a blob transaction references more blobs than fit in a block.
+    #[error("blob transaction with {count} blobs")]
+    TooManyBlobs { count: usize },
+
+    /// Triggered when a blob transaction creates a contract.
+    #[error("blob transaction creating a contract")]
+    BlobContractCreation,
+
+    /// Triggered when the max fee per blob gas of the transaction is below the blob gas price.
+    #[error(
+        "max fee per blob gas {max_fee_per_blob_gas} is below the blob gas price {blob_gas_price}"
+    )]
+    BlobFeeCapTooLow {
+        max_fee_per_blob_gas: U256,
+        blob_gas_price: U256,
+    },
+
+    /// Triggered when the nonce of the transaction does not match the nonce of the sender.
+    #[error("nonce {tx_nonce} does not match the sender nonce {sender_nonce}")]
+    NonceMismatch { tx_nonce: u64, sender_nonce: u64 },
+
+    /// Triggered when the max fee per gas of the transaction is below the block base fee.
+    #[error("max fee per gas {max_fee_per_gas} is below the base fee {base_fee}")]
+    FeeCapTooLow {
+        max_fee_per_gas: U256,
+        base_fee: U256,
+    },
+
+    /// Triggered when the priority fee of the transaction exceeds its max fee per gas.
+    #[error("priority fee {priority_fee} exceeds the max fee per gas {max_fee_per_gas}")]
+    PriorityFeeTooHigh {
+        priority_fee: U256,
+        max_fee_per_gas: U256,
+    },
+
+    /// Triggered when the balance of the sender does not cover the maximum cost of the
+    /// transaction.
+    #[error("sender balance {balance} does not cover the maximum cost {max_cost}")]
+    InsufficientFunds { balance: U256, max_cost: U256 },
+
+    /// Triggered when the type of the transaction is not active at the block.
+    #[error("unsupported tx type {tx_type}")]
+    UnsupportedType { tx_type: u8 },
+
+    /// Triggered when the gas limit of the transaction exceeds the gas left in the block.
+    #[error("gas limit {gas_limit} exceeds the {available} gas left in the block")]
+    GasExceedsBlockLimit { gas_limit: u64, available: U256 },
+
+    /// Triggered when the sender of the transaction cannot be recovered from its signature.
+    #[error("invalid signature")]
+    InvalidSignature,
+
+    /// Triggered when the transaction calls the point evaluation precompile without the `kzg`
+    /// feature.
+    #[error("the point evaluation precompile requires the kzg feature")]
+    PointEvaluationUnsupported,
+
+    /// Triggered when revm rejects the transaction.
+    #[error("execution failed: {reason}")]
+    Execution { reason: String },
+
+    /// Triggered in strict mode when the beneficiary is not credited exactly the priority fees of
+    /// the gas used by the transaction.
+    #[error("beneficiary credited {credited} instead of the priority fees {expected}")]
+    BeneficiaryFeeMismatch { credited: U256, expected: U256 },
+}
+
+/// Represents the ways the base fee of a block can violate EIP-1559.
+#[derive(Debug, PartialEq, Eq, ThisError)]
+pub enum BaseFeeError {
+    /// Triggered when a block after London has no base fee.
+    #[error("base fee is missing after London")]
+    Missing,
+
+    /// Triggered when a block before London has a base fee.
+    #[error("base fee {base_fee} is set before London")]
+    Unexpected { base_fee: u64 },
+
+    /// Triggered when the base fee does not follow from the gas usage of the parent.
+    #[error("base fee {base_fee} does not match the expected base fee {expected}")]
+    Mismatch { base_fee: u64, expected: u64 },
+
+    /// Triggered when the gas limit of the parent is below the elasticity multiplier, so that it
+    /// has no gas target to compute the base fee from.
+    #[error("parent gas limit {gas_limit} has no gas target")]
+    ZeroGasTarget { gas_limit: u64 },
+}
+
+/// A contract deployed by a transaction of the block, through a contract creation transaction or
+/// the `CREATE` and `CREATE2` opcodes.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CreatedContract {
+    /// The index of the deploying transaction in the block.
+    pub transaction_index: u32,
+
+    /// The account that executed the deployment: the sender of a contract creation transaction,
+    /// or the contract executing the opcode.
+    pub deployer: Address,
+
+    /// The address of the deployed contract.
+    pub address: Address,
+
+    /// The hash of the code of the deployed contract.
+    pub code_hash: B256,
+}
+
 /// A processor that executes EVM transactions.
 pub struct EvmProcessor<D> {
     /// An input containing all necessary data to execute the block.
@@ -60,76 +388,205 @@ pub struct EvmProcessor<D> {
 
     /// The header to be finalized.
     pub header: Option<Header>,
+
+    /// The compact receipts of the executed transactions.
+    pub compact_receipts: Option<CompactReceipts>,
+
+    /// The full receipts of the executed transactions, including their logs and blooms.
+    pub receipts: Option<Vec<ReceiptWithBloom>>,
+
+    /// The transactions skipped by the execution filter of the input.
+    pub skipped_transactions: Vec<SkippedTransaction>,
+
+    /// Optional hooks customizing the execution of the block.
+    pub hooks: Option<Box<dyn ExecutionHooks>>,
+
+    /// The rules validating the header of the block, defaulting to [EthereumBeaconValidator].
+    pub validator: Option<Box<dyn BlockValidator>>,
+
+    /// Computes the state root of the database at the checkpoints of the state root interval of
+    /// the input. Required to execute an input with a state root interval.
+    pub checkpoint_state_root: Option<fn(&SP1RethInput, &D) -> Result<B256, ProcessorError>>,
+
+    /// The state roots after every state root interval of transactions, excluding the state root
+    /// of the block.
+    pub intermediate_state_roots: Vec<B256>,
+
+    /// The hashes and senders of the transactions whose sender hint was verified and used.
+    pub used_sender_hints: Vec<(B256, Address)>,
+
+    /// The contracts deployed by the executed transactions, in the order of their deployment.
+    /// Deployments that were reverted, or destroyed within their transaction, are left out.
+    pub created_contracts: Vec<CreatedContract>,
 }
 
 impl<D> EvmProcessor<D> {
-    /// Validate the header standalone.
-    ///
-    /// Reference: https://github.com/paradigmxyz/reth/blob/main/crates/consensus/common/src/validation.rs#L14
-    pub fn validate_header_standalone(&self) {
-        let header = self.header.as_ref().unwrap();
-
-        // Gas used needs to be less then gas limit. Gas used is going to be check after execution.
-        if header.gas_used > header.gas_limit {
-            panic!("Gas used exceeds gas limit");
+    /// Creates a processor executing the block of the input on top of the database, with the
+    /// default hooks and validator and without a way to compute intermediate state roots.
+    pub fn new(input: SP1RethInput, db: D) -> Self {
+        Self {
+            input,
+            db: Some(db),
+            header: None,
+            compact_receipts: None,
+            receipts: None,
+            skipped_transactions: Vec::new(),
+            hooks: None,
+            validator: None,
+            checkpoint_state_root: None,
+            intermediate_state_roots: Vec::new(),
+            used_sender_hints: Vec::new(),
+            created_contracts: Vec::new(),
         }
     }
 
-    /// Validates the integrity and consistency of a block header in relation to it's parent.
-    ///
-    /// Reference: https://github.com/paradigmxyz/reth/blob/main/crates/primitives/src/header.rs#L800
-    pub fn validate_against_parent(&self) {
-        let parent_header = &self.input.parent_header;
-        let header = self.header.as_ref().unwrap();
+    /// Sets the hooks customizing the execution of the block.
+    pub fn with_hooks(mut self, hooks: Box<dyn ExecutionHooks>) -> Self {
+        self.hooks = Some(hooks);
+        self
+    }
 
-        // Parent number is consistent.
-        if parent_header.number + 1 != header.number {
-            panic!("Parent number is inconsistent with header number");
-        }
+    /// Sets the rules validating the header of the block.
+    pub fn with_validator(mut self, validator: Box<dyn BlockValidator>) -> Self {
+        self.validator = Some(validator);
+        self
+    }
 
-        // Parent hash is consistent.
-        if parent_header.hash_slow() != header.parent_hash {
-            panic!("Parent hash is inconsistent with header parent hash");
-        }
+    /// Sets how the state root of the database is computed at the checkpoints of the state root
+    /// interval of the input.
+    pub fn with_checkpoint_state_root(
+        mut self,
+        checkpoint_state_root: fn(&SP1RethInput, &D) -> Result<B256, ProcessorError>,
+    ) -> Self {
+        self.checkpoint_state_root = Some(checkpoint_state_root);
+        self
+    }
+
+    /// Returns the rules validating the header of the block.
+    pub fn validator(&self) -> &dyn BlockValidator {
+        self.validator
+            .as_deref()
+            .unwrap_or(&EthereumBeaconValidator)
+    }
 
-        // Timestamp in past check.
-        if parent_header.timestamp > header.timestamp {
-            panic!("Timestamp is in the future");
+    /// Validates the EIP-1559 and EIP-4844 parameters of the chain, which would otherwise cause a
+    /// division by zero when computing the base fee or the blob gas price, and leave blocks
+    /// without parameters.
+    pub fn validate_base_fee_params(&self) -> Result<(), ProcessorError> {
+        let schedule = &self.input.chain_config.base_fee_params;
+        if !schedule
+            .first()
+            .is_some_and(|(condition, _)| condition.is_active_at(0, 0))
+        {
+            return Err(ProcessorError::InvalidChainConfig {
+                reason: "no base fee parameters are active at genesis",
+            });
         }
+        for (_, params) in schedule {
+            if params.max_change_denominator == 0 {
+                return Err(ProcessorError::InvalidChainConfig {
+                    reason: "base fee max change denominator is zero",
+                });
+            }
+            if params.elasticity_multiplier == 0 {
+                return Err(ProcessorError::InvalidChainConfig {
+                    reason: "base fee elasticity multiplier is zero",
+                });
+            }
+        }
+        if self.input.chain_config.blob_params.update_fraction == 0 {
+            return Err(ProcessorError::InvalidChainConfig {
+                reason: "blob gas price update fraction is zero",
+            });
+        }
+        Ok(())
     }
 
-    /// Checks the gas limit for consistency between parent and self headers.
-    ///
-    /// Reference: https://github.com/paradigmxyz/reth/blob/main/crates/primitives/src/header.rs#L738
-    pub fn validate_gas_limit(&self) {
-        let parent_header = &self.input.parent_header;
-        let header = self.header.as_ref().unwrap();
-        let parent_gas_limit = parent_header.gas_limit;
+    /// Rejects the blocks whose execution rules are not implemented, rather than deriving a wrong
+    /// header for them:
+    /// - Receipts before Byzantium commit to an intermediate state root instead of a status, and
+    ///   this also excludes the irregular state change of the DAO fork on mainnet.
+    /// - The ommers of a proof-of-work block must be siblings of its recent ancestors that were
+    ///   not included before, which cannot be checked without the headers of these ancestors.
+    ///   Ommers of other blocks are rejected as invalid by the [BlockValidator].
+    /// - Blocks before the first supported hardfork of the chain follow other consensus rules.
+    /// - Blocks from the first unsupported hardfork of the chain on follow rules that are not
+    ///   implemented yet.
+    pub fn validate_supported_block(&self) -> Result<(), ProcessorError> {
+        let config = &self.input.chain_config;
+        let number = self.input.parent_header.number + 1;
+        let timestamp = self.input.timestamp;
+        if !config.is_active(config.supported_from, number, timestamp) {
+            return Err(ProcessorError::UnsupportedBlock {
+                reason: "blocks before the first supported hardfork of the chain are not supported",
+            });
+        }
+        if config
+            .supported_until
+            .is_some_and(|condition| condition.is_active_at(number, timestamp))
+        {
+            return Err(ProcessorError::UnsupportedBlock {
+                reason: "blocks after the last supported hardfork of the chain are not supported",
+            });
+        }
+        if !config.is_active(Hardfork::Byzantium, number, timestamp) {
+            return Err(ProcessorError::UnsupportedBlock {
+                reason: "receipts before Byzantium are not supported",
+            });
+        }
+        let is_pow = config.consensus == Consensus::Ethereum
+            && !config.is_active(Hardfork::Paris, number, timestamp);
+        if is_pow && !self.input.ommers.is_empty() {
+            return Err(ProcessorError::UnsupportedBlock {
+                reason: "the ancestry of ommers is not validated",
+            });
+        }
+        Ok(())
+    }
 
-        // Check for an increase in gas limit beyond the allowed threshold.
-        if header.gas_limit > parent_gas_limit {
-            if header.gas_limit - parent_gas_limit >= parent_gas_limit / 1024 {
-                panic!("Gas limit invalid increase");
-            }
+    /// Validates the options of the input controlling which transactions are executed and which
+    /// state roots are committed.
+    pub fn validate_execution_options(&self) -> Result<(), ProcessorError> {
+        if self.input.state_root_interval == Some(0) {
+            return Err(ProcessorError::InvalidInput {
+                reason: "state root interval is zero",
+            });
         }
-        // Check for a decrease in gas limit beyond the allowed threshold.
-        else if parent_gas_limit - header.gas_limit >= parent_gas_limit / 1024 {
-            panic!("Gas limit invalid decrease");
+        if self.input.state_root_interval.is_some() && self.checkpoint_state_root.is_none() {
+            return Err(ProcessorError::InvalidInput {
+                reason: "state root interval requires a checkpoint state root",
+            });
         }
-        // Check if the self gas limit is below the minimum required limit.
-        else if parent_gas_limit < MINIMUM_GAS_LIMIT {
-            panic!("Gas limit below minimum");
+        let tx_count = self.input.transactions.len();
+        if let Some(limit) = self.input.transaction_limit {
+            if limit as usize > tx_count {
+                return Err(ProcessorError::InvalidInput {
+                    reason: "transaction limit exceeds the transactions of the block",
+                });
+            }
+        }
+        let end = self
+            .input
+            .transaction_limit
+            .map_or(tx_count, |limit| limit as usize);
+        if self.input.transaction_start.unwrap_or_default() as usize > end {
+            return Err(ProcessorError::InvalidInput {
+                reason: "transaction range starts after its end",
+            });
         }
+        Ok(())
     }
 
-    /// Validates the header's extradata according to the beacon consensus rules.
-    ///
-    /// Reference: https://github.com/paradigmxyz/reth/blob/main/crates/consensus/beacon-core/src/lib.rs#L118
-    pub fn validate_header_extradata(&self) {
-        let header = self.header.as_ref().unwrap();
-        if header.extra_data.len() > MAXIMUM_EXTRA_DATA_SIZE {
-            panic!("Extra data too large");
+    /// Validates the transactions of the input against the expected transactions root, if any, so
+    /// that a corrupted witness is rejected before executing it.
+    pub fn validate_transactions_root(&self) -> Result<(), ProcessorError> {
+        if let Some(expected) = self.input.expected_transactions_root {
+            let root = transactions_root(&self.input.transactions);
+            if root != expected {
+                return Err(ProcessorError::TransactionsRootMismatch { root, expected });
+            }
         }
+        Ok(())
     }
 }
 
@@ -139,75 +596,434 @@ where
 {
     /// Validate input values against the parent header and initialize the current header's
     /// computed fields.
-    pub fn initialize(&mut self) {
-        let params = BaseFeeParams::ethereum();
-        let base_fee = self.input.parent_header.next_block_base_fee(params);
-        let header = Header {
+    pub fn initialize(&mut self) -> Result<(), ProcessorError> {
+        if let Some(overrides) = self.input.block_overrides {
+            overrides.apply(&mut self.input);
+        }
+        self.validate_base_fee_params()?;
+        self.validate_supported_block()?;
+        self.validate_execution_options()?;
+        self.validate_transactions_root()?;
+        let number = self.input.parent_header.number.checked_add(1).unwrap();
+        let mut header = Header {
             parent_hash: self.input.parent_header.hash_slow(),
-            number: self.input.parent_header.number.checked_add(1).unwrap(),
-            base_fee_per_gas: base_fee,
+            number,
+            base_fee_per_gas: self.input.base_fee_per_gas,
             beneficiary: self.input.beneficiary,
             gas_limit: self.input.gas_limit,
             timestamp: self.input.timestamp,
             mix_hash: self.input.mix_hash,
+            difficulty: self.input.difficulty,
+            nonce: self.input.nonce,
+            ommers_hash: calculate_ommers_root(&self.input.ommers),
             extra_data: self.input.extra_data.clone(),
+            excess_blob_gas: self.input.excess_blob_gas,
+            parent_beacon_block_root: self.input.parent_beacon_block_root,
             ..Default::default()
         };
+        if let Some(hooks) = &self.hooks {
+            hooks.header(&mut header);
+        }
         self.header = Some(header);
-        self.validate_against_parent();
-        self.validate_header_extradata();
+        self.validate_claimed_header()?;
+        let header = self.header.as_ref().unwrap();
+        self.validator().validate_header(&self.input, header)
+    }
+
+    /// Validates that the claimed header of a block to be proven invalid matches the fields of the
+    /// input known before execution, so that the proof refutes that header.
+    pub fn validate_claimed_header(&self) -> Result<(), ProcessorError> {
+        if !self.input.prove_invalid || !self.input.proves_block() {
+            return Ok(());
+        }
+        let Some(claimed) = &self.input.claimed_header else {
+            return Err(ProcessorError::InvalidInput {
+                reason: "proving a block invalid requires its claimed header",
+            });
+        };
+        let header = self.header.as_ref().expect("Header not initialized");
+        let has_withdrawals_root = self.input.chain_config.has_header_fields(
+            Hardfork::Shanghai,
+            header.number,
+            header.timestamp,
+        );
+        // The fields filled in by the execution are compared with the input separately.
+        let unexecuted = Header {
+            state_root: header.state_root,
+            transactions_root: header.transactions_root,
+            receipts_root: header.receipts_root,
+            withdrawals_root: header.withdrawals_root,
+            logs_bloom: header.logs_bloom,
+            gas_used: header.gas_used,
+            blob_gas_used: header.blob_gas_used,
+            ..claimed.clone()
+        };
+        let matches = unexecuted == *header
+            && claimed.transactions_root == transactions_root(&self.input.transactions)
+            && claimed.withdrawals_root
+                == has_withdrawals_root.then(|| withdrawals_root(&self.input.withdrawals))
+            && self
+                .input
+                .expected_gas_used
+                .map_or(true, |gas_used| claimed.gas_used == gas_used)
+            && self
+                .input
+                .expected_blob_gas_used
+                .map_or(true, |blob_gas_used| {
+                    claimed.blob_gas_used == Some(blob_gas_used)
+                });
+        if !matches {
+            return Err(ProcessorError::InvalidInput {
+                reason: "claimed header does not match the block of the input",
+            });
+        }
+        Ok(())
     }
 
     /// Processes each transaction and collect receipts and storage changes.
-    pub fn execute(&mut self) {
+    ///
+    /// If a transaction is invalid, execution stops and the database is not returned to the
+    /// processor.
+    pub fn execute(&mut self) -> Result<(), ProcessorError> {
+        self.execute_with_external((), None)?;
+        Ok(())
+    }
+
+    /// Processes the transactions like [EvmProcessor::execute] with an inspector attached to the
+    /// EVM, and returns the inspector afterwards.
+    ///
+    /// This lets host-side tooling collect traces, gas profiles or access lists while executing
+    /// the block natively. The program never attaches one, since inspecting every instruction
+    /// adds cycles.
+    pub fn execute_with_inspector<I: Inspector<D>>(
+        &mut self,
+        inspector: I,
+    ) -> Result<I, ProcessorError> {
+        self.execute_with_external(inspector, Some(inspector_handle_register))
+    }
+
+    /// Processes the transactions with the given external context of the EVM, applying the
+    /// handler register of the context if any.
+    fn execute_with_external<EXT>(
+        &mut self,
+        external: EXT,
+        register: Option<HandleRegister<EXT, D>>,
+    ) -> Result<EXT, ProcessorError> {
+        // Only execute the transaction prefix of the input, if any, and skip the transactions
+        // already reflected in the parent tries of a transaction range.
+        let end = self
+            .input
+            .transaction_limit
+            .map_or(self.input.transactions.len(), |limit| limit as usize);
+        let start = self.input.transaction_start.unwrap_or_default() as usize;
+        if self.is_empty_block(start) {
+            self.execute_empty_block()?;
+            return Ok(external);
+        }
         let gwei_to_wei: U256 = U256::from(1_000_000_000);
-        let spec_id = SpecId::SHANGHAI;
-        let mut evm = Evm::builder()
+        let header = self.header.as_ref().unwrap();
+        let spec_id = self
+            .input
+            .chain_config
+            .spec_id(header.number, header.timestamp);
+        let has_withdrawals_root = self.input.chain_config.has_header_fields(
+            Hardfork::Shanghai,
+            header.number,
+            header.timestamp,
+        );
+        let is_london =
+            self.input
+                .chain_config
+                .is_active(Hardfork::London, header.number, header.timestamp);
+        let is_shanghai =
+            self.input
+                .chain_config
+                .is_active(Hardfork::Shanghai, header.number, header.timestamp);
+        let fee_collector = self
+            .input
+            .chain_config
+            .fee_collector_at(header.number, header.timestamp);
+        let block_reward =
+            pow::block_reward(&self.input.chain_config, header.number, header.timestamp);
+        let number = header.number;
+        let precompiles = self
+            .hooks
+            .as_ref()
+            .map(|hooks| hooks.precompiles())
+            .unwrap_or_default();
+        let deployments = Rc::new(RefCell::new(Vec::new()));
+        let builder = Evm::builder()
+            .with_db(self.db.take().unwrap())
+            .with_external_context(external);
+        #[cfg(feature = "optimism")]
+        let builder = builder.optimism();
+        let builder = builder
             .with_spec_id(spec_id)
             .modify_cfg_env(|cfg_env| {
-                cfg_env.chain_id = 1;
+                cfg_env.chain_id = self.input.chain_config.chain_id;
+                // Pin the limits of EIP-170 and EIP-3860 instead of relying on the default.
+                cfg_env.limit_contract_code_size = Some(MAX_CODE_SIZE);
             })
             .modify_block_env(|blk_env| {
                 blk_env.number = self.header.as_mut().unwrap().number.try_into().unwrap();
                 blk_env.coinbase = self.input.beneficiary;
                 blk_env.timestamp = U256::from(self.header.as_mut().unwrap().timestamp);
-                blk_env.difficulty = U256::ZERO;
+                blk_env.difficulty = self.header.as_mut().unwrap().difficulty;
                 blk_env.prevrandao = Some(self.header.as_mut().unwrap().mix_hash);
-                blk_env.basefee =
-                    U256::from(self.header.as_mut().unwrap().base_fee_per_gas.unwrap());
+                blk_env.basefee = U256::from(
+                    self.header
+                        .as_mut()
+                        .unwrap()
+                        .base_fee_per_gas
+                        .unwrap_or_default(),
+                );
                 blk_env.gas_limit = U256::from(self.header.as_mut().unwrap().gas_limit);
+                if let Some(excess_blob_gas) = self.header.as_mut().unwrap().excess_blob_gas {
+                    blk_env.blob_excess_gas_and_price = Some(BlobExcessGasAndPrice {
+                        excess_blob_gas,
+                        blob_gasprice: self
+                            .input
+                            .chain_config
+                            .blob_params
+                            .blob_gas_price(excess_blob_gas),
+                    });
+                }
             })
-            .with_db(self.db.take().unwrap())
-            .build();
+            .append_handler_register_box(Box::new(move |handler| {
+                let load_precompiles = handler.pre_execution.load_precompiles.clone();
+                let precompiles = precompiles.clone();
+                handler.pre_execution.load_precompiles = Arc::new(move || {
+                    let mut loaded = load_precompiles();
+                    loaded.extend(precompiles.clone());
+                    loaded
+                });
+            }))
+            .append_handler_register_box(Box::new({
+                // Record the deployer and address of every completed create frame.
+                let deployments = deployments.clone();
+                move |handler| {
+                    let create_return = handler.execution.create_return.clone();
+                    let deployments = deployments.clone();
+                    handler.execution.create_return = Arc::new(
+                        move |context: &mut Context<EXT, D>,
+                              frame: Box<CreateFrame>,
+                              result: InterpreterResult| {
+                            let deployer = frame.frame_data.interpreter.contract.caller;
+                            let outcome = create_return(context, frame, result)?;
+                            if let Some(address) = outcome.address {
+                                deployments.borrow_mut().push((deployer, address));
+                            }
+                            Ok(outcome)
+                        },
+                    );
+                }
+            }));
+        // Record the balance of the beneficiary around the payment of the fees of every
+        // transaction. Transfers made by the transaction itself are excluded, so that the
+        // credited fees can be checked exactly.
+        #[cfg(feature = "strict")]
+        let beneficiary_balances = Rc::new(Cell::new(None));
+        #[cfg(feature = "strict")]
+        let builder = builder.append_handler_register_box(Box::new({
+            let beneficiary_balances = beneficiary_balances.clone();
+            move |handler| {
+                let reward_beneficiary = handler.post_execution.reward_beneficiary.clone();
+                let beneficiary_balances = beneficiary_balances.clone();
+                handler.post_execution.reward_beneficiary =
+                    Arc::new(move |context: &mut Context<EXT, D>, gas: &Gas| {
+                        let beneficiary = context.evm.env.block.coinbase;
+                        let (before, _) = context.evm.balance(beneficiary)?;
+                        reward_beneficiary(context, gas)?;
+                        let (after, _) = context.evm.balance(beneficiary)?;
+                        beneficiary_balances.set(Some((before, after)));
+                        Ok(())
+                    });
+            }
+        }));
+        let builder = match register {
+            Some(register) => builder.append_handler_register(register),
+            None => builder,
+        };
+        let mut evm = builder.build();
 
         let mut logs_bloom = Bloom::default();
         let mut cumulative_gas_used = U256::ZERO;
         let mut receipts = Vec::new();
+        let mut compact_receipts = CompactReceipts::default();
+        let mut blob_gas_used = 0u64;
+        let mut destroyed_accounts = HashSet::new();
+
+        // Deploy the create2deployer in the first block of Canyon, unless the parent tries of a
+        // transaction range already hold it. Every OP Stack block starts with a deposit, so such a
+        // block is never empty.
+        #[cfg(feature = "optimism")]
+        if start == 0
+            && self.input.chain_config.is_activation_block(
+                Hardfork::Canyon,
+                self.input.parent_header.number,
+                self.input.parent_header.timestamp,
+                self.input.timestamp,
+            )
+        {
+            deploy_create2_deployer(&mut evm.context.evm.db, &self.input.contracts)?;
+        }
+
+        // Store the parent beacon block root in the beacon roots contract, unless the parent tries
+        // of a transaction range already hold it.
+        if let (0, Some(parent_beacon_block_root), Some(beacon_roots)) = (
+            start,
+            self.input.parent_beacon_block_root,
+            self.input.chain_config.system_contracts.beacon_roots,
+        ) {
+            apply_beacon_root_contract_call(&mut evm, beacon_roots, parent_beacon_block_root)?;
+        }
+
+        for (tx_no, tx) in self
+            .input
+            .transactions
+            .iter()
+            .enumerate()
+            .take(end)
+            .skip(start)
+        {
+            // Commit to the state after every interval of transactions, counting skipped ones.
+            if let Some(interval) = self.input.state_root_interval {
+                if tx_no > start && tx_no % interval as usize == 0 {
+                    let checkpoint_state_root = self
+                        .checkpoint_state_root
+                        .expect("checkpoint state root validated on initialization");
+                    let state_root = checkpoint_state_root(&self.input, &evm.context.evm.db)?;
+                    self.intermediate_state_roots.push(state_root);
+                }
+            }
+
+            // Report the cycles of every transaction, including the recovery of its sender,
+            // through the cycle tracker of SP1.
+            #[cfg(target_os = "zkvm")]
+            println!("cycle-tracker-start: tx {}", tx_no);
+
+            let invalid = |reason| ProcessorError::InvalidTransaction {
+                index: tx_no,
+                reason,
+            };
+
+            // Take the sender from a valid sender hint, or recover it from the transaction
+            // signature.
+            let index = u32::try_from(tx_no).unwrap();
+            let hinted = self
+                .input
+                .sender_hints
+                .get(&index)
+                .and_then(|hint| verify_sender_hint(tx, hint));
+            let tx_from = match hinted {
+                Some(tx_from) => {
+                    self.used_sender_hints.push((tx.hash(), tx_from));
+                    tx_from
+                }
+                None => tx
+                    .recover_signer()
+                    .ok_or_else(|| invalid(TransactionError::InvalidSignature))?,
+            };
 
-        for (tx_no, tx) in self.input.transactions.iter().enumerate() {
-            // Recover the sender from the transaction signature.
-            let tx_from = tx.recover_signer().unwrap();
+            // Skip the transaction if it is excluded by the execution filter.
+            if let Some(filter) = &self.input.execution_filter {
+                if let Some(reason) = filter.skip_reason(tx_from, tx.transaction.to()) {
+                    self.skipped_transactions.push(SkippedTransaction {
+                        index: tx_no.try_into().unwrap(),
+                        reason,
+                    });
+                    #[cfg(target_os = "zkvm")]
+                    println!("cycle-tracker-end: tx {}", tx_no);
+                    continue;
+                }
+            }
+
+            // Validate tx type.
+            let tx_type = u8::from(tx.transaction.tx_type());
+            if !self
+                .input
+                .chain_config
+                .is_tx_type_active(tx_type, number, self.input.timestamp)
+            {
+                return Err(invalid(TransactionError::UnsupportedType { tx_type }));
+            }
 
             // Validate tx gas.
             let block_available_gas = U256::from(self.input.gas_limit) - cumulative_gas_used;
             if block_available_gas < U256::from(tx.transaction.gas_limit()) {
-                panic!("Error at transaction {}: gas exceeds block limit", tx_no);
+                return Err(invalid(TransactionError::GasExceedsBlockLimit {
+                    gas_limit: tx.transaction.gas_limit(),
+                    available: block_available_gas,
+                }));
+            }
+
+            // Account for the blob gas of the transaction.
+            if let Transaction::Eip4844(blob_tx) = &tx.transaction {
+                blob_gas_used += blob_tx.blob_versioned_hashes.len() as u64 * GAS_PER_BLOB;
             }
 
             // Setup EVM from tx.
             fill_eth_tx_env(&mut evm.env_mut().tx, &tx.transaction, tx_from);
+            #[cfg(feature = "optimism")]
+            fill_optimism_tx_env(&mut evm.env_mut().tx, tx);
+
+            // Validate the tx against the sender account before handing it to revm.
+            let sender = evm
+                .context
+                .evm
+                .db
+                .basic(tx_from)
+                .map_err(database_error)?
+                .unwrap_or_default();
+            let env = evm.env_mut();
+            #[cfg(feature = "optimism")]
+            let is_deposit = matches!(tx.transaction, Transaction::Deposit(_));
+            #[cfg(not(feature = "optimism"))]
+            let is_deposit = false;
+            let is_system = self
+                .hooks
+                .as_ref()
+                .is_some_and(|hooks| hooks.is_system_transaction(tx_no, tx));
+            if !is_deposit && !is_system {
+                let max_blobs_per_block = self.input.chain_config.blob_params.max_blobs_per_block;
+                validate_tx_env(&env.tx, &env.block, &sender, spec_id, max_blobs_per_block)
+                    .map_err(invalid)?;
+            }
             // Execute transaction.
-            let res = evm
-                .transact()
-                .map_err(|e| {
-                    println!("Error at transaction {}: {:?}", tx_no, e);
-                    e
-                })
-                .unwrap();
+            #[cfg(feature = "strict")]
+            beneficiary_balances.set(None);
+            let res = evm.transact().map_err(|err| match err {
+                EVMError::Database(err) => database_error(err),
+                err => invalid(TransactionError::Execution {
+                    reason: format!("{:?}", err),
+                }),
+            })?;
 
             // Update cumulative gas used.
-            let gas_used = res.result.gas_used().try_into().unwrap();
-            cumulative_gas_used = cumulative_gas_used.checked_add(gas_used).unwrap();
+            let gas_used = res.result.gas_used();
+            cumulative_gas_used = cumulative_gas_used
+                .checked_add(U256::from(gas_used))
+                .unwrap();
+
+            // Check that the beneficiary was credited exactly the priority fees of the gas used,
+            // or the full gas price before London.
+            #[cfg(feature = "strict")]
+            if let (Some((before, after)), false) =
+                (beneficiary_balances.take(), is_deposit || is_system)
+            {
+                let env = evm.env_mut();
+                let mut fee_per_gas = env.effective_gas_price();
+                if is_london {
+                    fee_per_gas = fee_per_gas.saturating_sub(env.block.basefee);
+                }
+                let expected = fee_per_gas * U256::from(gas_used);
+                if before.checked_add(expected) != Some(after) {
+                    return Err(invalid(TransactionError::BeneficiaryFeeMismatch {
+                        credited: after.saturating_sub(before),
+                        expected,
+                    }));
+                }
+            }
 
             // Create receipt.
             let receipt = Receipt {
@@ -220,114 +1036,408 @@ where
                     .into_iter()
                     .map(|log| log.into())
                     .collect(),
+                #[cfg(feature = "optimism")]
+                deposit_nonce: is_deposit.then_some(sender.nonce),
+                #[cfg(feature = "optimism")]
+                deposit_receipt_version: (is_deposit
+                    && self.input.chain_config.is_active(
+                        Hardfork::Canyon,
+                        number,
+                        self.input.timestamp,
+                    ))
+                .then_some(1),
             };
 
             // Update logs bloom.
             logs_bloom.accrue_bloom(&receipt.bloom_slow());
+            compact_receipts.push(
+                receipt.success,
+                gas_used,
+                receipt.logs.len().try_into().unwrap(),
+            );
             let receipt = ReceiptWithBloom::from(receipt);
             receipts.push(receipt);
 
+            // Without the `kzg` feature revm does not include the point evaluation precompile, so
+            // calling it would silently succeed. Refuse to prove such blocks instead.
+            #[cfg(not(feature = "kzg"))]
+            if spec_id >= SpecId::CANCUN && res.state.contains_key(&POINT_EVALUATION_ADDRESS) {
+                return Err(invalid(TransactionError::PointEvaluationUnsupported));
+            }
+
+            // Keep the deployments that survived the transaction: reverted ones are no longer
+            // marked as created in its state.
+            for (deployer, address) in deployments.borrow_mut().drain(..) {
+                let Some(account) = res
+                    .state
+                    .get(&address)
+                    .filter(|account| account.is_created() && !account.is_selfdestructed())
+                else {
+                    continue;
+                };
+                let size = account.info.code.as_ref().map_or(0, |code| code.len());
+                if spec_id >= SpecId::SPURIOUS_DRAGON && size > MAX_CODE_SIZE {
+                    return Err(invalid(TransactionError::CodeTooLarge {
+                        address,
+                        size,
+                        limit: MAX_CODE_SIZE,
+                    }));
+                }
+                self.created_contracts.push(CreatedContract {
+                    transaction_index: index,
+                    deployer,
+                    address,
+                    code_hash: account.info.code_hash,
+                });
+            }
+
             // Commit state changes.
-            evm.context.evm.db.commit(res.state);
+            let mut state = res.state;
+            clear_destroyed_storage(&mut state, &mut destroyed_accounts);
+            evm.context.evm.db.commit(state);
+
+            // Credit the base fee to the fee collector of the chain instead of burning it. Before
+            // London there is no base fee, and revm pays the full gas price to the beneficiary.
+            if let Some(fee_collector) = fee_collector.filter(|_| is_london) {
+                let base_fee = evm.env_mut().block.basefee;
+                let amount_wei = base_fee.checked_mul(U256::from(gas_used)).unwrap();
+                increase_account_balance(&mut evm.context.evm.db, fee_collector, amount_wei)?;
+            }
+            #[cfg(target_os = "zkvm")]
+            println!("cycle-tracker-end: tx {}", tx_no);
         }
 
-        // Process consensus layer withdrawals.
-        for withdrawal in self.input.withdrawals.iter() {
+        // Process consensus layer withdrawals, which are not part of a transaction prefix. Chains
+        // with a withdrawals contract call it in every block after Shanghai, even without
+        // withdrawals, so that it retries the withdrawals that failed in earlier blocks.
+        let is_full_block = self.input.transaction_limit.is_none();
+        let withdrawals_contract = self.input.chain_config.system_contracts.withdrawals;
+        if let Some(contract) = withdrawals_contract.filter(|_| is_full_block && is_shanghai) {
+            apply_withdrawals_contract_call(&mut evm, contract, &self.input.withdrawals)?;
+        }
+        for withdrawal in self
+            .input
+            .withdrawals
+            .iter()
+            .filter(|_| is_full_block && withdrawals_contract.is_none())
+        {
             // Convert withdrawal amount (in gwei) to wei.
             let amount_wei = gwei_to_wei
                 .checked_mul(withdrawal.amount.try_into().unwrap())
                 .unwrap();
 
-            increase_account_balance(&mut evm.context.evm.db, withdrawal.address, amount_wei)
-                .unwrap();
+            increase_account_balance(&mut evm.context.evm.db, withdrawal.address, amount_wei)?;
         }
 
-        // Compute header roots and fill out other header fields.
+        // Apply the block reward of proof-of-work blocks.
+        if is_full_block && block_reward > U256::ZERO {
+            #[cfg(feature = "strict")]
+            let balance_before = evm
+                .context
+                .evm
+                .db
+                .basic(self.input.beneficiary)
+                .map_err(database_error)?
+                .unwrap_or_default()
+                .balance;
+            increase_account_balance(
+                &mut evm.context.evm.db,
+                self.input.beneficiary,
+                block_reward,
+            )?;
+
+            // Check that the beneficiary was credited exactly the block reward.
+            #[cfg(feature = "strict")]
+            {
+                let balance_after = evm
+                    .context
+                    .evm
+                    .db
+                    .basic(self.input.beneficiary)
+                    .map_err(database_error)?
+                    .unwrap_or_default()
+                    .balance;
+                if balance_before.checked_add(block_reward) != Some(balance_after) {
+                    return Err(ProcessorError::BlockRewardMismatch {
+                        credited: balance_after.saturating_sub(balance_before),
+                        expected: block_reward,
+                    });
+                }
+            }
+        }
+
+        // Compute header roots and fill out other header fields. The transactions root covers
+        // every transaction of the block, including those outside of an executed range.
         let h = self.header.as_mut().expect("Header not initialized");
-        let txs_signed = take(&mut self.input.transactions)
-            .into_iter()
-            .map(|tx| tx.into())
-            .collect::<Vec<TransactionSigned>>();
-        h.transactions_root = ordered_trie_root_with_encoder(&txs_signed, |tx, buf| {
-            tx.encode_with_signature(&tx.signature, buf, false);
-        });
+        let skipped = &self.skipped_transactions;
+        h.transactions_root = if skipped.is_empty() {
+            transactions_root(&self.input.transactions)
+        } else {
+            let txs = self
+                .input
+                .transactions
+                .iter()
+                .enumerate()
+                .filter(|(index, _)| !skipped.iter().any(|s| s.index as usize == *index))
+                .map(|(_, tx)| tx.clone())
+                .collect::<Vec<_>>();
+            transactions_root(&txs)
+        };
         h.receipts_root = ordered_trie_root_with_encoder(&receipts, |receipt, buf| {
             receipt.encode_inner(buf, false);
         });
-        h.withdrawals_root = Some(ordered_trie_root_with_encoder(
-            &self.input.withdrawals,
-            |withdrawal, buf| buf.put_slice(&withdrawal.to_rlp()),
-        ));
+        if has_withdrawals_root {
+            h.withdrawals_root = Some(withdrawals_root(&self.input.withdrawals));
+        }
         h.logs_bloom = logs_bloom;
         h.gas_used = cumulative_gas_used.try_into().unwrap();
+        if h.excess_blob_gas.is_some() {
+            h.blob_gas_used = Some(blob_gas_used);
+        }
+        let header = self.header.as_ref().unwrap();
+        self.validator()
+            .validate_executed_header(&self.input, header)?;
 
+        self.compact_receipts = Some(compact_receipts);
+        self.receipts = Some(receipts);
         self.db = Some(evm.context.evm.db);
+        Ok(evm.context.external)
+    }
+
+    /// Returns whether the block neither executes transactions or system calls nor credits
+    /// withdrawals or rewards, in which case its execution leaves the state untouched.
+    fn is_empty_block(&self, start: usize) -> bool {
+        let header = self.header.as_ref().expect("Header not initialized");
+        let applies_beacon_root = start == 0
+            && self.input.parent_beacon_block_root.is_some()
+            && self
+                .input
+                .chain_config
+                .system_contracts
+                .beacon_roots
+                .is_some();
+        let calls_withdrawals_contract = self.input.transaction_limit.is_none()
+            && self
+                .input
+                .chain_config
+                .system_contracts
+                .withdrawals
+                .is_some()
+            && self.input.chain_config.is_active(
+                Hardfork::Shanghai,
+                header.number,
+                header.timestamp,
+            );
+        let block_reward =
+            pow::block_reward(&self.input.chain_config, header.number, header.timestamp);
+        self.input.transactions.is_empty()
+            && self.input.withdrawals.is_empty()
+            && !applies_beacon_root
+            && !calls_withdrawals_contract
+            && block_reward == U256::ZERO
+    }
+
+    /// Fills out the header of an empty block without building the EVM.
+    fn execute_empty_block(&mut self) -> Result<(), ProcessorError> {
+        let h = self.header.as_mut().expect("Header not initialized");
+        h.transactions_root = EMPTY_ROOT_HASH;
+        h.receipts_root = EMPTY_ROOT_HASH;
+        if self
+            .input
+            .chain_config
+            .has_header_fields(Hardfork::Shanghai, h.number, h.timestamp)
+        {
+            h.withdrawals_root = Some(EMPTY_ROOT_HASH);
+        }
+        h.logs_bloom = Bloom::default();
+        h.gas_used = 0;
+        if h.excess_blob_gas.is_some() {
+            h.blob_gas_used = Some(0);
+        }
+        let header = self.header.as_ref().unwrap();
+        self.validator()
+            .validate_executed_header(&self.input, header)?;
+
+        self.compact_receipts = Some(CompactReceipts::default());
+        self.receipts = Some(Vec::new());
+        Ok(())
     }
 }
 
-impl EvmProcessor<InMemoryDB> {
+impl<D: Borrow<InMemoryDB>> EvmProcessor<D> {
     /// Process all state changes and finalize the header's state root.
-    pub fn finalize(&mut self) {
+    ///
+    /// The parent tries of the input are left untouched: modifications are applied to
+    /// copy-on-write clones, so the same prestate can be reused afterwards.
+    pub fn finalize(&mut self) -> Result<(), ProcessorError> {
         let db = self.db.take().expect("DB not initialized");
+        let db: &InMemoryDB = db.borrow();
 
-        let mut state_trie = mem::take(&mut self.input.parent_state_trie);
+        // Every storage slot accessed during execution must have been proven by the witness,
+        // either as an inclusion or as an exclusion proof.
         for (address, account) in &db.accounts {
-            // Ignore untouched accounts.
-            if account.account_state == AccountState::None {
+            if matches!(
+                account.account_state,
+                AccountState::NotExisting | AccountState::StorageCleared
+            ) {
+                continue;
+            }
+            let Some((_, slots)) = self.input.parent_storage.get(address) else {
                 continue;
+            };
+            if let Some(index) = account.storage.keys().find(|key| !slots.contains(key)) {
+                return Err(ProcessorError::IncompleteStorageWitness {
+                    address: *address,
+                    index: *index,
+                });
             }
+        }
 
-            let state_trie_index = keccak(address);
+        #[cfg(feature = "strict")]
+        self.audit_account_states(db)?;
 
-            // Remove from state trie if it has been deleted.
-            if account.account_state == AccountState::NotExisting {
-                state_trie.delete(&state_trie_index).unwrap();
-                continue;
+        // Update state trie root in header.
+        let state_root = state_root(&self.input, db)?;
+        let header = self.header.as_mut().expect("Header not initialized");
+        header.state_root = state_root;
+
+        println!("{:?}", header);
+
+        // Make sure the proof is about the expected block, and not one resulting from a bad
+        // witness.
+        if let Some(expected) = self.input.expected_block_hash {
+            let hash = header.hash_slow();
+            if hash != expected {
+                return Err(ProcessorError::BlockHashMismatch { hash, expected });
             }
+        }
+        Ok(())
+    }
 
-            // Update storage root for account.
-            let state_storage = &account.storage;
-            let storage_root = {
-                let (storage_trie, _) = self.input.parent_storage.get_mut(address).unwrap();
-                // If the account has been cleared, clear the storage trie.
-                if account.account_state == AccountState::StorageCleared {
-                    storage_trie.clear();
-                }
+    /// Finalizes the header like [EvmProcessor::finalize], and additionally returns the accounts
+    /// and storage slots changed by the block.
+    pub fn finalize_with_state_diff(&mut self) -> Result<BlockStateDiff, ProcessorError> {
+        let db: &InMemoryDB = self.db.as_ref().expect("DB not initialized").borrow();
+        let diff = BlockStateDiff::compute(&self.input, db)?;
+        self.finalize()?;
+        Ok(diff)
+    }
 
-                // Apply all storage changes to the storage trie.
-                for (key, value) in state_storage {
-                    let storage_trie_index = keccak(key.to_be_bytes::<32>());
-                    if value == &U256::ZERO {
-                        storage_trie.delete(&storage_trie_index).unwrap();
-                    } else {
-                        storage_trie
-                            .insert_rlp(&storage_trie_index, *value)
-                            .unwrap();
+    /// Cross-checks the state revm assigned to every account against the parent state, so that
+    /// a change in how revm tracks accounts is caught before it silently corrupts the state root.
+    ///
+    /// Untouched accounts must be identical to the parent state, and deleted accounts must be
+    /// empty.
+    #[cfg(feature = "strict")]
+    fn audit_account_states(&self, db: &InMemoryDB) -> Result<(), ProcessorError> {
+        for (address, account) in &db.accounts {
+            let inconsistent = |reason| {
+                Err(ProcessorError::InconsistentAccountState {
+                    address: *address,
+                    reason,
+                })
+            };
+            match account.account_state {
+                AccountState::None => {
+                    let parent = self
+                        .input
+                        .parent_state_trie
+                        .get_rlp::<StateAccount>(&keccak(address))
+                        .map_err(|_| ProcessorError::MissingWitness { address: *address })?
+                        .unwrap_or_default();
+                    if account.info.nonce != parent.nonce
+                        || account.info.balance != parent.balance
+                        || account.info.code_hash != parent.code_hash
+                    {
+                        return inconsistent("untouched account differs from the parent state");
+                    }
+                    let Some((storage_trie, _)) = self.input.parent_storage.get(address) else {
+                        continue;
+                    };
+                    for (index, value) in &account.storage {
+                        let parent_value = storage_trie
+                            .get_rlp::<U256>(&keccak(index.to_be_bytes::<32>()))
+                            .map_err(|_| ProcessorError::IncompleteStorageWitness {
+                                address: *address,
+                                index: *index,
+                            })?
+                            .unwrap_or_default();
+                        if *value != parent_value {
+                            return inconsistent("untouched storage differs from the parent state");
+                        }
+                    }
+                }
+                AccountState::NotExisting => {
+                    if account.info.nonce != 0
+                        || account.info.balance != U256::ZERO
+                        || account.info.code_hash != KECCAK_EMPTY
+                    {
+                        return inconsistent("deleted account is not empty");
+                    }
+                    if account.storage.values().any(|value| *value != U256::ZERO) {
+                        return inconsistent("deleted account has storage");
                     }
                 }
+                AccountState::Touched | AccountState::StorageCleared => {}
+            }
+        }
+        Ok(())
+    }
+}
 
-                storage_trie.hash()
-            };
+/// Computes the state root after applying the changes of the database to the parent tries of the
+/// input, which are left untouched.
+pub fn state_root(input: &SP1RethInput, db: &InMemoryDB) -> Result<B256, ProcessorError> {
+    let mut computer = StateRootComputer::from_input(input);
+    computer.apply(db)?;
+    Ok(computer.root())
+}
 
-            let state_account = StateAccount {
-                nonce: account.info.nonce,
-                balance: account.info.balance,
-                storage_root,
-                code_hash: account.info.code_hash,
-            };
-            state_trie
-                .insert_rlp(&state_trie_index, state_account)
-                .unwrap();
-        }
+/// Computes the root of the trie of the given transactions.
+pub fn transactions_root(txs: &[TransactionSignedNoHash]) -> B256 {
+    ordered_trie_root_with_encoder(txs, |tx, buf| {
+        tx.transaction
+            .encode_with_signature(&tx.signature, buf, false);
+    })
+}
 
-        // Update state trie root in header.
-        let header = self.header.as_mut().expect("Header not initialized");
-        header.state_root = state_trie.hash();
+/// Computes the withdrawals root of a block.
+pub fn withdrawals_root(withdrawals: &[Withdrawal]) -> B256 {
+    ordered_trie_root_with_encoder(withdrawals, |withdrawal, buf| {
+        buf.put_slice(&withdrawal.to_rlp())
+    })
+}
 
-        println!("{:?}", header);
+/// Marks the accounts destroyed by an earlier transaction of the block as created when they are
+/// touched again, and records the accounts destroyed by this transaction.
+///
+/// The database forgets that an account was destroyed once a later transaction touches it, e.g.
+/// by sending it ether, and would then fold its changes into the storage of the parent state,
+/// resurrecting the destroyed storage. Committing the account as created clears its storage.
+fn clear_destroyed_storage(state: &mut State, destroyed_accounts: &mut HashSet<Address>) {
+    for (address, account) in state.iter_mut() {
+        if account.is_selfdestructed() {
+            destroyed_accounts.insert(*address);
+        } else if account.is_touched() && destroyed_accounts.remove(address) {
+            account.mark_created();
+        }
     }
 }
 
+/// Returns a [ProcessorError::Database] error wrapping the given database error.
+fn database_error(err: impl core::fmt::Debug) -> ProcessorError {
+    ProcessorError::Database {
+        reason: format!("{:?}", err),
+    }
+}
+
+/// Returns an [ProcessorError::InvalidHeader] error with the given reason.
+pub(crate) fn invalid_header<T>(reason: impl Into<String>) -> Result<T, ProcessorError> {
+    Err(ProcessorError::InvalidHeader {
+        reason: reason.into(),
+    })
+}
+
 fn fill_eth_tx_env(tx_env: &mut TxEnv, essence: &Transaction, caller: Address) {
     match essence {
         Transaction::Legacy(tx) => {
@@ -345,6 +1455,8 @@ fn fill_eth_tx_env(tx_env: &mut TxEnv, essence: &Transaction, caller: Address) {
             tx_env.chain_id = tx.chain_id;
             tx_env.nonce = Some(tx.nonce);
             tx_env.access_list.clear();
+            tx_env.blob_hashes.clear();
+            tx_env.max_fee_per_blob_gas = None;
         }
         Transaction::Eip2930(tx) => {
             tx_env.caller = caller;
@@ -371,6 +1483,8 @@ fn fill_eth_tx_env(tx_env: &mut TxEnv, essence: &Transaction, caller: Address) {
                     )
                 })
                 .collect();
+            tx_env.blob_hashes.clear();
+            tx_env.max_fee_per_blob_gas = None;
         }
         Transaction::Eip1559(tx) => {
             tx_env.caller = caller;
@@ -397,37 +1511,1322 @@ fn fill_eth_tx_env(tx_env: &mut TxEnv, essence: &Transaction, caller: Address) {
                     )
                 })
                 .collect();
+            tx_env.blob_hashes.clear();
+            tx_env.max_fee_per_blob_gas = None;
         }
-        Transaction::Eip4844(_) => todo!(),
-    };
-}
-
-pub fn increase_account_balance<D>(
-    db: &mut D,
-    address: Address,
-    amount_wei: U256,
-) -> anyhow::Result<()>
-where
-    D: Database + DatabaseCommit,
-    <D as Database>::Error: core::fmt::Debug,
-{
-    // Read account from database
-    let mut account: Account = db
-        .basic(address)
-        .map_err(|db_err| {
-            anyhow!(
-                "Error increasing account balance for {}: {:?}",
-                address,
-                db_err
-            )
-        })?
-        .unwrap_or_default()
-        .into();
-    // Credit withdrawal amount
-    account.info.balance = account.info.balance.checked_add(amount_wei).unwrap();
-    account.mark_touch();
-    // Commit changes to database
-    db.commit([(address, account)].into());
-
+        Transaction::Eip4844(tx) => {
+            tx_env.caller = caller;
+            tx_env.gas_limit = tx.gas_limit;
+            tx_env.gas_price = U256::from(tx.max_fee_per_gas);
+            tx_env.gas_priority_fee = Some(U256::from(tx.max_priority_fee_per_gas));
+            tx_env.transact_to = if let TransactionKind::Call(to_addr) = tx.to {
+                TransactTo::Call(to_addr)
+            } else {
+                TransactTo::create()
+            };
+            tx_env.value = tx.value.into();
+            tx_env.data = tx.input.clone();
+            tx_env.chain_id = Some(tx.chain_id);
+            tx_env.nonce = Some(tx.nonce);
+            tx_env.access_list = tx
+                .access_list
+                .0
+                .iter()
+                .map(|item| {
+                    (
+                        item.address,
+                        item.storage_keys.iter().map(|key| (*key).into()).collect(),
+                    )
+                })
+                .collect();
+            tx_env.blob_hashes = tx.blob_versioned_hashes.clone();
+            tx_env.max_fee_per_blob_gas = Some(U256::from(tx.max_fee_per_blob_gas));
+        }
+        #[cfg(feature = "optimism")]
+        Transaction::Deposit(tx) => {
+            tx_env.caller = caller;
+            tx_env.gas_limit = tx.gas_limit;
+            tx_env.gas_price = U256::ZERO;
+            tx_env.gas_priority_fee = None;
+            tx_env.transact_to = if let TransactionKind::Call(to_addr) = tx.to {
+                TransactTo::Call(to_addr)
+            } else {
+                TransactTo::create()
+            };
+            tx_env.value = tx.value.into();
+            tx_env.data = tx.input.clone();
+            tx_env.chain_id = None;
+            tx_env.nonce = None;
+            tx_env.access_list.clear();
+            tx_env.blob_hashes.clear();
+            tx_env.max_fee_per_blob_gas = None;
+        }
+    };
+}
+
+/// Fills the OP Stack specific fields of the transaction environment.
+///
+/// The enveloped transaction is used by revm to charge the L1 data fee of the transaction.
+///
+/// Reference: https://github.com/paradigmxyz/reth/blob/main/crates/primitives/src/revm/env.rs
+#[cfg(feature = "optimism")]
+fn fill_optimism_tx_env(tx_env: &mut TxEnv, tx: &TransactionSignedNoHash) {
+    let envelope = TransactionSigned::from(tx.clone()).envelope_encoded();
+    tx_env.optimism = match &tx.transaction {
+        Transaction::Deposit(deposit) => OptimismFields {
+            source_hash: Some(deposit.source_hash),
+            mint: deposit.mint,
+            is_system_transaction: Some(deposit.is_system_transaction),
+            enveloped_tx: Some(envelope),
+        },
+        _ => OptimismFields {
+            source_hash: None,
+            mint: None,
+            is_system_transaction: Some(false),
+            enveloped_tx: Some(envelope),
+        },
+    };
+}
+
+/// Sets the code of the create2deployer, an irregular state change of the first block of Canyon.
+/// The code is taken from the contracts of the input.
+///
+/// Reference: https://github.com/ethereum-optimism/op-geth/blob/optimism/consensus/misc/create2deployer.go
+#[cfg(feature = "optimism")]
+fn deploy_create2_deployer<D>(db: &mut D, contracts: &[Bytes]) -> Result<(), ProcessorError>
+where
+    D: Database + DatabaseCommit,
+    <D as Database>::Error: core::fmt::Debug,
+{
+    let Some(code) = contracts
+        .iter()
+        .find(|code| B256::from(keccak(code)) == CREATE2_DEPLOYER_CODE_HASH)
+    else {
+        return Err(ProcessorError::InvalidInput {
+            reason: "the code of the create2deployer is not part of the contracts",
+        });
+    };
+    let mut account: Account = db
+        .basic(CREATE2_DEPLOYER_ADDRESS)
+        .map_err(database_error)?
+        .unwrap_or_default()
+        .into();
+    account.info.code_hash = CREATE2_DEPLOYER_CODE_HASH;
+    account.info.code = Some(revm::primitives::Bytecode::new_raw(code.clone()));
+    account.mark_touch();
+    db.commit([(CREATE2_DEPLOYER_ADDRESS, account)].into());
+    Ok(())
+}
+
+/// Applies the EIP-4788 system call that stores the parent beacon block root in the beacon roots
+/// contract.
+///
+/// Reference: https://github.com/paradigmxyz/reth/blob/main/crates/revm/src/state_change.rs
+fn apply_beacon_root_contract_call<EXT, D>(
+    evm: &mut Evm<'_, EXT, D>,
+    beacon_roots: Address,
+    parent_beacon_block_root: B256,
+) -> Result<(), ProcessorError>
+where
+    D: Database + DatabaseCommit,
+    <D as Database>::Error: core::fmt::Debug,
+{
+    let data = Bytes::copy_from_slice(parent_beacon_block_root.as_slice());
+    apply_system_call(evm, beacon_roots, data, "beacon root contract call")?;
+    Ok(())
+}
+
+/// Applies the system call through which the withdrawals contract of the chain credits the
+/// withdrawals of the block, and retries the withdrawals that failed in earlier blocks.
+///
+/// Reference: https://github.com/gnosischain/specs/blob/master/execution/withdrawals.md
+fn apply_withdrawals_contract_call<EXT, D>(
+    evm: &mut Evm<'_, EXT, D>,
+    contract: Address,
+    withdrawals: &[Withdrawal],
+) -> Result<(), ProcessorError>
+where
+    D: Database + DatabaseCommit,
+    <D as Database>::Error: core::fmt::Debug,
+{
+    let data = system_withdrawals_call(withdrawals);
+    let result = apply_system_call(evm, contract, data, "withdrawals contract call")?;
+    if !result.is_success() {
+        return Err(ProcessorError::SystemCall {
+            reason: format!("withdrawals contract call failed: {:?}", result),
+        });
+    }
+    Ok(())
+}
+
+/// Applies a call of the system address to a system contract, and returns its result.
+fn apply_system_call<EXT, D>(
+    evm: &mut Evm<'_, EXT, D>,
+    contract: Address,
+    data: Bytes,
+    name: &str,
+) -> Result<ExecutionResult, ProcessorError>
+where
+    D: Database + DatabaseCommit,
+    <D as Database>::Error: core::fmt::Debug,
+{
+    // The system call is neither subject to the block gas limit nor to the base fee.
+    let previous_block_env = evm.env_mut().block.clone();
+    let env = evm.env_mut();
+    env.tx = TxEnv {
+        caller: SYSTEM_ADDRESS,
+        transact_to: TransactTo::Call(contract),
+        nonce: None,
+        gas_limit: SYSTEM_CALL_GAS_LIMIT,
+        value: U256::ZERO,
+        data,
+        gas_price: U256::ZERO,
+        chain_id: None,
+        gas_priority_fee: None,
+        ..Default::default()
+    };
+    env.block.gas_limit = U256::from(SYSTEM_CALL_GAS_LIMIT);
+    env.block.basefee = U256::ZERO;
+
+    let mut res = evm.transact().map_err(|err| match err {
+        EVMError::Database(err) => database_error(err),
+        err => ProcessorError::SystemCall {
+            reason: format!("{}: {:?}", name, err),
+        },
+    })?;
+
+    // The system call must not touch the system address or reward the beneficiary.
+    res.state.remove(&SYSTEM_ADDRESS);
+    res.state.remove(&previous_block_env.coinbase);
+    evm.context.evm.db.commit(res.state);
+
+    evm.env_mut().block = previous_block_env;
+    Ok(res.result)
+}
+
+/// Validates the nonce, fee caps and maximum cost of a transaction against its sender and the
+/// block it is included in.
+///
+/// Reference: https://github.com/bluealloy/revm/blob/main/crates/revm/src/handler/mainnet/pre_execution.rs
+pub fn validate_tx_env(
+    tx_env: &TxEnv,
+    block_env: &BlockEnv,
+    sender: &AccountInfo,
+    spec_id: SpecId,
+    max_blobs_per_block: u64,
+) -> Result<(), TransactionError> {
+    let base_fee = block_env.basefee;
+    let intrinsic_gas = intrinsic_gas(tx_env, spec_id);
+    if tx_env.gas_limit < intrinsic_gas {
+        return Err(TransactionError::IntrinsicGasTooLow {
+            gas_limit: tx_env.gas_limit,
+            intrinsic_gas,
+        });
+    }
+
+    if spec_id >= SpecId::SHANGHAI
+        && matches!(tx_env.transact_to, TransactTo::Create(_))
+        && tx_env.data.len() > MAX_INITCODE_SIZE
+    {
+        return Err(TransactionError::InitCodeTooLarge {
+            size: tx_env.data.len(),
+            limit: MAX_INITCODE_SIZE,
+        });
+    }
+
+    if sender.code_hash != KECCAK_EMPTY {
+        return Err(TransactionError::SenderHasCode {
+            code_hash: sender.code_hash,
+        });
+    }
+
+    // Only the versioned hashes of blobs are needed to execute the block, so blob transactions
+    // are validated without their blobs being available.
+    if let Some(max_fee_per_blob_gas) = tx_env.max_fee_per_blob_gas {
+        if tx_env.blob_hashes.is_empty() {
+            return Err(TransactionError::EmptyBlobHashes);
+        }
+        if tx_env.blob_hashes.len() as u64 > max_blobs_per_block {
+            return Err(TransactionError::TooManyBlobs {
+                count: tx_env.blob_hashes.len(),
+            });
+        }
+        if matches!(tx_env.transact_to, TransactTo::Create(_)) {
+            return Err(TransactionError::BlobContractCreation);
+        }
+        let blob_gas_price = U256::from(block_env.get_blob_gasprice().unwrap_or_default());
+        if max_fee_per_blob_gas < blob_gas_price {
+            return Err(TransactionError::BlobFeeCapTooLow {
+                max_fee_per_blob_gas,
+                blob_gas_price,
+            });
+        }
+        if let Some(hash) = tx_env
+            .blob_hashes
+            .iter()
+            .find(|hash| hash[0] != VERSIONED_HASH_VERSION_KZG)
+        {
+            return Err(TransactionError::UnsupportedBlobVersion { hash: *hash });
+        }
+    }
+
+    if let Some(tx_nonce) = tx_env.nonce {
+        if tx_nonce != sender.nonce {
+            return Err(TransactionError::NonceMismatch {
+                tx_nonce,
+                sender_nonce: sender.nonce,
+            });
+        }
+    }
+
+    let max_fee_per_gas = tx_env.gas_price;
+    if max_fee_per_gas < base_fee {
+        return Err(TransactionError::FeeCapTooLow {
+            max_fee_per_gas,
+            base_fee,
+        });
+    }
+    if let Some(priority_fee) = tx_env.gas_priority_fee {
+        if priority_fee > max_fee_per_gas {
+            return Err(TransactionError::PriorityFeeTooHigh {
+                priority_fee,
+                max_fee_per_gas,
+            });
+        }
+    }
+
+    let blob_gas = U256::from(tx_env.blob_hashes.len() as u64 * GAS_PER_BLOB);
+    let max_cost = U256::from(tx_env.gas_limit)
+        .saturating_mul(max_fee_per_gas)
+        .saturating_add(tx_env.value)
+        .saturating_add(blob_gas.saturating_mul(tx_env.max_fee_per_blob_gas.unwrap_or_default()));
+    if sender.balance < max_cost {
+        return Err(TransactionError::InsufficientFunds {
+            balance: sender.balance,
+            max_cost,
+        });
+    }
+
     Ok(())
 }
+
+/// Computes the base fee of a block from its parent under the rules of EIP-1559. The first block
+/// after London, whose parent has no base fee, uses the initial base fee.
+///
+/// Fails instead of dividing by zero if the gas limit of the parent is below the elasticity
+/// multiplier.
+///
+/// Reference: https://eips.ethereum.org/EIPS/eip-1559
+pub fn calc_next_base_fee(parent: &Header, params: BaseFeeParams) -> Result<u64, BaseFeeError> {
+    let Some(parent_base_fee) = parent.base_fee_per_gas else {
+        return Ok(EIP1559_INITIAL_BASE_FEE);
+    };
+    let parent_base_fee = u128::from(parent_base_fee);
+    let gas_used = u128::from(parent.gas_used);
+    let gas_target = u128::from(parent.gas_limit) / u128::from(params.elasticity_multiplier);
+    if gas_target == 0 {
+        return Err(BaseFeeError::ZeroGasTarget {
+            gas_limit: parent.gas_limit,
+        });
+    }
+    let denominator = u128::from(params.max_change_denominator);
+
+    let base_fee = match gas_used.cmp(&gas_target) {
+        std::cmp::Ordering::Equal => parent_base_fee,
+        std::cmp::Ordering::Greater => {
+            let delta = parent_base_fee * (gas_used - gas_target) / gas_target / denominator;
+            parent_base_fee + delta.max(1)
+        }
+        std::cmp::Ordering::Less => {
+            let delta = parent_base_fee * (gas_target - gas_used) / gas_target / denominator;
+            parent_base_fee.saturating_sub(delta)
+        }
+    };
+    Ok(u64::try_from(base_fee).unwrap_or(u64::MAX))
+}
+
+/// Validates the base fee of a header against its parent under the rules of EIP-1559.
+pub fn validate_base_fee(
+    parent: &Header,
+    header: &Header,
+    params: BaseFeeParams,
+    is_london: bool,
+) -> Result<(), BaseFeeError> {
+    match (header.base_fee_per_gas, is_london) {
+        (None, false) => Ok(()),
+        (Some(base_fee), false) => Err(BaseFeeError::Unexpected { base_fee }),
+        (None, true) => Err(BaseFeeError::Missing),
+        (Some(base_fee), true) => {
+            let expected = calc_next_base_fee(parent, params)?;
+            if base_fee != expected {
+                return Err(BaseFeeError::Mismatch { base_fee, expected });
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Computes the intrinsic gas of a transaction, charged before any of its code is executed: the
+/// base cost, and the cost of its calldata, contract creation and access list.
+///
+/// Reference: https://github.com/bluealloy/revm/blob/main/crates/interpreter/src/gas/calc.rs
+pub fn intrinsic_gas(tx_env: &TxEnv, spec_id: SpecId) -> u64 {
+    let zero_bytes = tx_env.data.iter().filter(|byte| **byte == 0).count() as u64;
+    let non_zero_bytes = tx_env.data.len() as u64 - zero_bytes;
+    let non_zero_byte_cost = if spec_id >= SpecId::ISTANBUL { 16 } else { 68 };
+    let mut gas = 21_000 + zero_bytes * 4 + non_zero_bytes * non_zero_byte_cost;
+
+    if matches!(tx_env.transact_to, TransactTo::Create(_)) {
+        if spec_id >= SpecId::HOMESTEAD {
+            gas += 32_000;
+        }
+        if spec_id >= SpecId::SHANGHAI {
+            gas += 2 * (tx_env.data.len() as u64).div_ceil(32);
+        }
+    }
+
+    for (_, storage_keys) in &tx_env.access_list {
+        gas += 2_400 + storage_keys.len() as u64 * 1_900;
+    }
+    gas
+}
+
+pub fn increase_account_balance<D>(
+    db: &mut D,
+    address: Address,
+    amount_wei: U256,
+) -> Result<(), ProcessorError>
+where
+    D: Database + DatabaseCommit,
+    <D as Database>::Error: core::fmt::Debug,
+{
+    // Read account from database
+    let mut account: Account = db
+        .basic(address)
+        .map_err(database_error)?
+        .unwrap_or_default()
+        .into();
+    // Credit withdrawal amount
+    account.info.balance = account.info.balance.checked_add(amount_wei).unwrap();
+    account.mark_touch();
+    // Commit changes to database
+    db.commit([(address, account)].into());
+
+    Ok(())
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::chain::{ChainConfig, ForkCondition};
+    use crate::db::InMemoryDBHelper;
+    #[cfg(feature = "strict")]
+    use crate::mpt::MptNode;
+    use crate::mpt::MptNodeData;
+    use k256::ecdsa::SigningKey;
+    use reth_primitives::constants::EMPTY_OMMER_ROOT_HASH;
+    use reth_primitives::revm_primitives::Bytecode;
+    use reth_primitives::{Signature, TxEip1559};
+    #[cfg(feature = "strict")]
+    use revm::db::DbAccount;
+    use revm::primitives::MAX_BLOB_NUMBER_PER_BLOCK as MAX_BLOBS;
+
+    fn parent(gas_limit: u64, gas_used: u64, base_fee: Option<u64>) -> Header {
+        Header {
+            gas_limit,
+            gas_used,
+            base_fee_per_gas: base_fee,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_next_base_fee() {
+        let params = BaseFeeParams::ethereum();
+
+        // The first London block uses the initial base fee.
+        assert_eq!(
+            calc_next_base_fee(&parent(30_000_000, 0, None), params),
+            Ok(EIP1559_INITIAL_BASE_FEE)
+        );
+
+        // The base fee is unchanged when the parent hits the gas target.
+        assert_eq!(
+            calc_next_base_fee(&parent(30_000_000, 15_000_000, Some(1_000_000_000)), params),
+            Ok(1_000_000_000)
+        );
+
+        // A full parent raises the base fee by 12.5%, an empty one lowers it by 12.5%.
+        assert_eq!(
+            calc_next_base_fee(&parent(30_000_000, 30_000_000, Some(1_000_000_000)), params),
+            Ok(1_125_000_000)
+        );
+        assert_eq!(
+            calc_next_base_fee(&parent(30_000_000, 0, Some(1_000_000_000)), params),
+            Ok(875_000_000)
+        );
+
+        // The base fee always increases by at least one above the target.
+        assert_eq!(
+            calc_next_base_fee(&parent(30_000_000, 15_000_001, Some(7)), params),
+            Ok(8)
+        );
+
+        // The decrease rounds down, so small base fees stay put and zero never underflows.
+        assert_eq!(
+            calc_next_base_fee(&parent(30_000_000, 0, Some(7)), params),
+            Ok(7)
+        );
+        assert_eq!(
+            calc_next_base_fee(&parent(30_000_000, 0, Some(0)), params),
+            Ok(0)
+        );
+
+        // A parent with no gas target is rejected instead of dividing by zero.
+        assert_eq!(
+            calc_next_base_fee(&parent(1, 0, Some(7)), params),
+            Err(BaseFeeError::ZeroGasTarget { gas_limit: 1 })
+        );
+    }
+
+    #[test]
+    fn test_validate_base_fee() {
+        let params = BaseFeeParams::ethereum();
+        let parent = parent(30_000_000, 30_000_000, Some(1_000_000_000));
+        let header = |base_fee| Header {
+            base_fee_per_gas: base_fee,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            validate_base_fee(&parent, &header(Some(1_125_000_000)), params, true),
+            Ok(())
+        );
+        assert_eq!(
+            validate_base_fee(&parent, &header(Some(1_000_000_000)), params, true),
+            Err(BaseFeeError::Mismatch {
+                base_fee: 1_000_000_000,
+                expected: 1_125_000_000
+            })
+        );
+        assert_eq!(
+            validate_base_fee(&parent, &header(None), params, true),
+            Err(BaseFeeError::Missing)
+        );
+        assert_eq!(
+            validate_base_fee(&parent, &header(None), params, false),
+            Ok(())
+        );
+        assert_eq!(
+            validate_base_fee(&parent, &header(Some(7)), params, false),
+            Err(BaseFeeError::Unexpected { base_fee: 7 })
+        );
+    }
+
+    #[test]
+    fn test_sender_has_code() {
+        let tx_env = TxEnv {
+            gas_limit: 21_000,
+            gas_price: U256::from(1),
+            nonce: Some(0),
+            ..Default::default()
+        };
+        let block_env = BlockEnv {
+            basefee: U256::from(1),
+            ..Default::default()
+        };
+        let eoa = AccountInfo {
+            balance: U256::from(21_000),
+            ..Default::default()
+        };
+        assert!(validate_tx_env(&tx_env, &block_env, &eoa, SpecId::CANCUN, MAX_BLOBS).is_ok());
+
+        let code_hash = B256::from(keccak([0x60, 0x00]));
+        let contract = AccountInfo { code_hash, ..eoa };
+        assert!(matches!(
+            validate_tx_env(&tx_env, &block_env, &contract, SpecId::CANCUN, MAX_BLOBS),
+            Err(TransactionError::SenderHasCode { code_hash: hash }) if hash == code_hash
+        ));
+    }
+
+    #[test]
+    fn test_clear_destroyed_storage() {
+        let account = |destroyed: bool| {
+            let mut account = Account::default();
+            account.mark_touch();
+            if destroyed {
+                account.mark_selfdestruct();
+            }
+            account
+        };
+        let address = Address::with_last_byte(1);
+        let mut destroyed_accounts = HashSet::new();
+
+        // Destroying the account records it, without marking it as created.
+        let mut state = State::from_iter([(address, account(true))]);
+        clear_destroyed_storage(&mut state, &mut destroyed_accounts);
+        assert!(!state[&address].is_created());
+        assert!(destroyed_accounts.contains(&address));
+
+        // Touching it again, e.g. with a transfer, recreates it with empty storage.
+        let mut state = State::from_iter([(address, account(false))]);
+        clear_destroyed_storage(&mut state, &mut destroyed_accounts);
+        assert!(state[&address].is_created());
+        assert!(destroyed_accounts.is_empty());
+
+        // Later touches keep the storage written since it was recreated.
+        let mut state = State::from_iter([(address, account(false))]);
+        clear_destroyed_storage(&mut state, &mut destroyed_accounts);
+        assert!(!state[&address].is_created());
+    }
+
+    #[test]
+    fn test_intrinsic_gas() {
+        let transfer = TxEnv::default();
+        assert_eq!(intrinsic_gas(&transfer, SpecId::CANCUN), 21_000);
+
+        // Calldata is cheaper after Istanbul, zero bytes cost the same.
+        let call = TxEnv {
+            data: vec![0, 1, 2].into(),
+            ..Default::default()
+        };
+        assert_eq!(
+            intrinsic_gas(&call, SpecId::PETERSBURG),
+            21_000 + 4 + 2 * 68
+        );
+        assert_eq!(intrinsic_gas(&call, SpecId::ISTANBUL), 21_000 + 4 + 2 * 16);
+
+        // Contract creation costs extra after Homestead, and per initcode word after Shanghai.
+        let create = TxEnv {
+            transact_to: TransactTo::create(),
+            data: vec![1; 33].into(),
+            ..Default::default()
+        };
+        assert_eq!(intrinsic_gas(&create, SpecId::FRONTIER), 21_000 + 33 * 68);
+        assert_eq!(
+            intrinsic_gas(&create, SpecId::SHANGHAI),
+            53_000 + 33 * 16 + 2 * 2
+        );
+
+        let access_list = TxEnv {
+            access_list: vec![(Address::ZERO, vec![U256::ZERO, U256::from(1)])],
+            ..Default::default()
+        };
+        assert_eq!(
+            intrinsic_gas(&access_list, SpecId::BERLIN),
+            21_000 + 2_400 + 2 * 1_900
+        );
+
+        let tx_env = TxEnv {
+            gas_limit: 20_000,
+            ..Default::default()
+        };
+        assert!(matches!(
+            validate_tx_env(
+                &tx_env,
+                &BlockEnv::default(),
+                &AccountInfo::default(),
+                SpecId::CANCUN,
+                MAX_BLOBS
+            ),
+            Err(TransactionError::IntrinsicGasTooLow {
+                gas_limit: 20_000,
+                intrinsic_gas: 21_000
+            })
+        ));
+    }
+
+    #[test]
+    fn test_init_code_size() {
+        let create = |size| TxEnv {
+            transact_to: TransactTo::create(),
+            data: vec![0; size].into(),
+            gas_limit: 1_000_000,
+            ..Default::default()
+        };
+        let validate = |tx_env: &TxEnv, spec_id| {
+            validate_tx_env(
+                tx_env,
+                &BlockEnv::default(),
+                &AccountInfo::default(),
+                spec_id,
+                MAX_BLOBS,
+            )
+        };
+
+        // Initcode up to the limit is accepted, larger initcode only before Shanghai.
+        assert!(validate(&create(MAX_INITCODE_SIZE), SpecId::SHANGHAI).is_ok());
+        assert!(validate(&create(MAX_INITCODE_SIZE + 1), SpecId::MERGE).is_ok());
+        assert!(matches!(
+            validate(&create(MAX_INITCODE_SIZE + 1), SpecId::SHANGHAI),
+            Err(TransactionError::InitCodeTooLarge {
+                size,
+                limit: MAX_INITCODE_SIZE
+            }) if size == MAX_INITCODE_SIZE + 1
+        ));
+
+        // The limit does not apply to the calldata of calls.
+        let call = TxEnv {
+            transact_to: TransactTo::Call(Address::ZERO),
+            ..create(MAX_INITCODE_SIZE + 1)
+        };
+        assert!(validate(&call, SpecId::SHANGHAI).is_ok());
+    }
+
+    /// Returns a blob hash of the given version.
+    fn versioned_hash(version: u8) -> B256 {
+        let mut hash = B256::repeat_byte(2);
+        hash[0] = version;
+        hash
+    }
+
+    /// Returns a blob transaction referencing the given blobs, paying at most 1 wei per blob gas.
+    fn blob_tx_env(blob_hashes: Vec<B256>) -> TxEnv {
+        TxEnv {
+            transact_to: TransactTo::Call(Address::ZERO),
+            gas_limit: 21_000,
+            max_fee_per_blob_gas: Some(U256::from(1)),
+            blob_hashes,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_blob_versioned_hashes() {
+        let funded = AccountInfo {
+            balance: U256::MAX,
+            ..Default::default()
+        };
+        let validate = |tx_env: &TxEnv| {
+            validate_tx_env(
+                tx_env,
+                &BlockEnv::default(),
+                &funded,
+                SpecId::CANCUN,
+                MAX_BLOBS,
+            )
+        };
+
+        // The blobs themselves are not needed, only their KZG versioned hashes.
+        let kzg_hash = versioned_hash(VERSIONED_HASH_VERSION_KZG);
+        assert!(validate(&blob_tx_env(vec![kzg_hash])).is_ok());
+
+        assert!(matches!(
+            validate(&blob_tx_env(Vec::new())),
+            Err(TransactionError::EmptyBlobHashes)
+        ));
+        let unknown_hash = versioned_hash(VERSIONED_HASH_VERSION_KZG + 1);
+        assert!(matches!(
+            validate(&blob_tx_env(vec![kzg_hash, unknown_hash])),
+            Err(TransactionError::UnsupportedBlobVersion { hash }) if hash == unknown_hash
+        ));
+    }
+
+    #[test]
+    fn test_blob_fee_market() {
+        let funded = AccountInfo {
+            balance: U256::MAX,
+            ..Default::default()
+        };
+        let validate = |tx_env: &TxEnv, block_env: &BlockEnv| {
+            validate_tx_env(tx_env, block_env, &funded, SpecId::CANCUN, MAX_BLOBS)
+        };
+        let kzg_hash = versioned_hash(VERSIONED_HASH_VERSION_KZG);
+
+        // A transaction can fill a block with blobs, but not exceed it.
+        let full = vec![kzg_hash; MAX_BLOBS as usize];
+        assert!(validate(&blob_tx_env(full.clone()), &BlockEnv::default()).is_ok());
+        let overfull = blob_tx_env([full, vec![kzg_hash]].concat());
+        assert!(matches!(
+            validate(&overfull, &BlockEnv::default()),
+            Err(TransactionError::TooManyBlobs { count }) if count == overfull.blob_hashes.len()
+        ));
+
+        // Gnosis allows fewer blobs per block than Ethereum.
+        let gnosis = ChainConfig::gnosis().blob_params.max_blobs_per_block;
+        let tx_env = blob_tx_env(vec![kzg_hash; gnosis as usize + 1]);
+        assert!(validate(&tx_env, &BlockEnv::default()).is_ok());
+        assert!(matches!(
+            validate_tx_env(
+                &tx_env,
+                &BlockEnv::default(),
+                &funded,
+                SpecId::CANCUN,
+                gnosis
+            ),
+            Err(TransactionError::TooManyBlobs { .. })
+        ));
+
+        let create = TxEnv {
+            transact_to: TransactTo::create(),
+            gas_limit: 53_000,
+            ..blob_tx_env(vec![kzg_hash])
+        };
+        assert!(matches!(
+            validate(&create, &BlockEnv::default()),
+            Err(TransactionError::BlobContractCreation)
+        ));
+
+        // The blob gas price rises with the excess blob gas of the block.
+        let mut block_env = BlockEnv::default();
+        block_env.set_blob_excess_gas_and_price(10_000_000);
+        let blob_gas_price = U256::from(block_env.get_blob_gasprice().unwrap());
+        assert!(blob_gas_price > U256::from(1));
+        assert!(matches!(
+            validate(&blob_tx_env(vec![kzg_hash]), &block_env),
+            Err(TransactionError::BlobFeeCapTooLow {
+                max_fee_per_blob_gas,
+                blob_gas_price: price,
+            }) if max_fee_per_blob_gas == U256::from(1) && price == blob_gas_price
+        ));
+        let tx_env = TxEnv {
+            max_fee_per_blob_gas: Some(blob_gas_price),
+            ..blob_tx_env(vec![kzg_hash])
+        };
+        assert!(validate(&tx_env, &block_env).is_ok());
+    }
+
+    /// Returns the address of the sender signing with the given key.
+    fn sender(key: &SigningKey) -> Address {
+        let public_key = key.verifying_key().to_encoded_point(false);
+        Address::from_slice(&keccak(&public_key.as_bytes()[1..])[12..])
+    }
+
+    /// Returns a signed transaction deploying zeroed code of the given size, returned by
+    /// `PUSH2 size PUSH1 0 RETURN`.
+    fn deploy(key: &SigningKey, nonce: u64, size: u16) -> TransactionSignedNoHash {
+        let [hi, lo] = size.to_be_bytes();
+        let transaction = Transaction::Eip1559(TxEip1559 {
+            chain_id: 1,
+            nonce,
+            gas_limit: 6_000_000,
+            max_fee_per_gas: 2_000_000_000,
+            max_priority_fee_per_gas: 0,
+            to: TransactionKind::Create,
+            value: U256::ZERO,
+            input: vec![0x61, hi, lo, 0x60, 0x00, 0xf3].into(),
+            access_list: Default::default(),
+        });
+        let (signature, recovery_id) = key
+            .sign_prehash_recoverable(transaction.signature_hash().as_slice())
+            .unwrap();
+        TransactionSignedNoHash {
+            signature: Signature {
+                r: U256::from_be_slice(&signature.r().to_bytes()),
+                s: U256::from_be_slice(&signature.s().to_bytes()),
+                odd_y_parity: recovery_id.is_y_odd(),
+            },
+            transaction,
+        }
+    }
+
+    /// Initializes the block of the input and executes its transactions, with the sender of the
+    /// given key funded with one ether.
+    fn execute_funded(
+        key: &SigningKey,
+        input: SP1RethInput,
+    ) -> Result<EvmProcessor<InMemoryDB>, ProcessorError> {
+        let mut db = InMemoryDB::default();
+        db.insert_account_info(
+            sender(key),
+            AccountInfo {
+                balance: U256::from(10).pow(U256::from(18)),
+                ..Default::default()
+            },
+        );
+        let mut executor = EvmProcessor::new(input, db);
+        executor.initialize()?;
+        executor.execute()?;
+        Ok(executor)
+    }
+
+    #[test]
+    fn test_code_size() {
+        let key = SigningKey::from_slice(&[1; 32]).unwrap();
+        let input = SP1RethInput {
+            transactions: vec![
+                deploy(&key, 0, MAX_CODE_SIZE as u16),
+                deploy(&key, 1, MAX_CODE_SIZE as u16 + 1),
+            ],
+            expected_gas_used: None,
+            expected_transactions_root: None,
+            ..empty_block_input(17_100_000, 1_682_000_000)
+        };
+        let executor = execute_funded(&key, input).unwrap();
+
+        // Code up to the limit is deployed, while larger code halts and consumes all the gas.
+        let receipts = executor.compact_receipts.as_ref().unwrap();
+        assert_eq!(receipts.success(0), Some(true));
+        assert_eq!(receipts.success(1), Some(false));
+        assert_eq!(receipts.gas_used[1], 6_000_000);
+        assert_eq!(executor.created_contracts.len(), 1);
+        assert_eq!(
+            executor.created_contracts[0].address,
+            sender(&key).create(0)
+        );
+        assert_eq!(executor.created_contracts[0].transaction_index, 0);
+    }
+
+    #[test]
+    fn test_transaction_limit() {
+        let key = SigningKey::from_slice(&[1; 32]).unwrap();
+        let transactions = vec![deploy(&key, 0, 1), deploy(&key, 1, 1)];
+        let input = SP1RethInput {
+            transactions: transactions.clone(),
+            transaction_limit: Some(1),
+            expected_gas_used: None,
+            expected_transactions_root: None,
+            ..empty_block_input(17_100_000, 1_682_000_000)
+        };
+
+        // Only the prefix is executed, but the transactions root covers the whole block.
+        let executor = execute_funded(&key, input.clone()).unwrap();
+        assert_eq!(executor.compact_receipts.as_ref().unwrap().len(), 1);
+        assert_eq!(
+            executor.header.as_ref().unwrap().transactions_root,
+            transactions_root(&transactions)
+        );
+
+        // A limit beyond the transactions of the block, or a range starting after its end, is
+        // rejected.
+        let beyond_block = SP1RethInput {
+            transaction_limit: Some(3),
+            ..input.clone()
+        };
+        assert!(matches!(
+            execute_funded(&key, beyond_block),
+            Err(ProcessorError::InvalidInput { .. })
+        ));
+        let after_end = SP1RethInput {
+            transaction_start: Some(2),
+            ..input
+        };
+        assert!(matches!(
+            execute_funded(&key, after_end),
+            Err(ProcessorError::InvalidInput { .. })
+        ));
+    }
+
+    #[test]
+    fn test_consensus_violation() {
+        // A transaction whose nonce does not follow its sender violates the rules of the block.
+        let key = SigningKey::from_slice(&[1; 32]).unwrap();
+        let input = SP1RethInput {
+            transactions: vec![deploy(&key, 0, 1), deploy(&key, 2, 1)],
+            expected_gas_used: None,
+            expected_transactions_root: None,
+            ..empty_block_input(17_100_000, 1_682_000_000)
+        };
+        let Err(err) = execute_funded(&key, input) else {
+            panic!("the transaction with a nonce gap was executed");
+        };
+        assert!(matches!(
+            err,
+            ProcessorError::InvalidTransaction {
+                reason: TransactionError::NonceMismatch { .. },
+                ..
+            }
+        ));
+        assert!(err.is_consensus_violation());
+        assert_eq!(err.transaction_index(), Some(1));
+
+        // Deployed code the EVM should have halted on is a fault of the program, not the block.
+        let err = ProcessorError::InvalidTransaction {
+            index: 0,
+            reason: TransactionError::CodeTooLarge {
+                address: Address::ZERO,
+                size: MAX_CODE_SIZE + 1,
+                limit: MAX_CODE_SIZE,
+            },
+        };
+        assert!(!err.is_consensus_violation());
+        assert_eq!(err.transaction_index(), Some(0));
+
+        // Header violations are not attributed to a transaction.
+        let input = SP1RethInput {
+            expected_gas_used: Some(21_000),
+            ..empty_block_input(16_000_000, 1_668_000_000)
+        };
+        let err = process(input).unwrap_err();
+        assert!(err.is_consensus_violation());
+        assert_eq!(err.transaction_index(), None);
+
+        // Inconsistent inputs do not prove anything about the block.
+        let err = ProcessorError::InvalidInput { reason: "test" };
+        assert!(!err.is_consensus_violation());
+        assert_eq!(err.transaction_index(), None);
+    }
+
+    #[test]
+    #[cfg(feature = "strict")]
+    fn test_audit_account_states() {
+        let address = Address::repeat_byte(4);
+        let mut input = empty_block_input(16_000_000, 1_668_000_000);
+        let mut state_trie = MptNode::default();
+        let parent = StateAccount {
+            nonce: 1,
+            balance: U256::from(100),
+            ..Default::default()
+        };
+        state_trie.insert_rlp(&keccak(address), parent).unwrap();
+        input.parent_state_trie = state_trie;
+        let executor = EvmProcessor::new(input, InMemoryDB::default());
+        let audit = |account: DbAccount| {
+            let mut db = InMemoryDB::default();
+            db.accounts.insert(address, account);
+            executor.audit_account_states(&db)
+        };
+        let untouched = DbAccount {
+            info: AccountInfo {
+                nonce: 1,
+                balance: U256::from(100),
+                ..Default::default()
+            },
+            account_state: AccountState::None,
+            storage: Default::default(),
+        };
+        assert!(audit(untouched.clone()).is_ok());
+
+        // An untouched account must match the parent state.
+        let mut changed = untouched.clone();
+        changed.info.balance = U256::from(101);
+        assert!(matches!(
+            audit(changed.clone()),
+            Err(ProcessorError::InconsistentAccountState { .. })
+        ));
+
+        // Touched accounts are checked against the state root instead.
+        changed.account_state = AccountState::Touched;
+        assert!(audit(changed).is_ok());
+
+        // A deleted account must be empty, including its storage.
+        let mut deleted = DbAccount {
+            info: AccountInfo::default(),
+            account_state: AccountState::NotExisting,
+            storage: Default::default(),
+        };
+        assert!(audit(deleted.clone()).is_ok());
+        deleted.storage.insert(U256::from(1), U256::from(1));
+        assert!(matches!(
+            audit(deleted),
+            Err(ProcessorError::InconsistentAccountState { .. })
+        ));
+        let deleted = DbAccount {
+            account_state: AccountState::NotExisting,
+            ..untouched
+        };
+        assert!(matches!(
+            audit(deleted),
+            Err(ProcessorError::InconsistentAccountState { .. })
+        ));
+    }
+
+    #[test]
+    fn test_invalid_base_fee_params() {
+        // A chain needs base fee parameters from its genesis.
+        let mut input = empty_block_input(16_000_000, 1_668_000_000);
+        input.chain_config.base_fee_params[0].0 = ForkCondition::Block(1);
+        assert!(matches!(
+            process(input.clone()).unwrap_err(),
+            ProcessorError::InvalidChainConfig { .. }
+        ));
+
+        // None of the parameters of the schedule may divide by zero.
+        input.chain_config.base_fee_params = vec![
+            (ForkCondition::Block(0), BaseFeeParams::ethereum()),
+            (
+                ForkCondition::Block(20_000_000),
+                BaseFeeParams {
+                    max_change_denominator: 0,
+                    elasticity_multiplier: 2,
+                },
+            ),
+        ];
+        assert!(matches!(
+            process(input).unwrap_err(),
+            ProcessorError::InvalidChainConfig { .. }
+        ));
+    }
+
+    #[test]
+    fn test_unsupported_block() {
+        // Receipts before Byzantium commit to intermediate state roots.
+        let input = SP1RethInput {
+            difficulty: U256::from(pow::MINIMUM_DIFFICULTY),
+            base_fee_per_gas: None,
+            ..empty_block_input(4_000_000, 1_500_000_000)
+        };
+        let err = process(input).unwrap_err();
+        assert!(matches!(err, ProcessorError::UnsupportedBlock { .. }));
+        assert!(!err.is_consensus_violation());
+
+        // The ancestry of the ommers of proof-of-work blocks is not validated.
+        let ommer = Header {
+            number: 14_999_999,
+            ..Default::default()
+        };
+        let input = SP1RethInput {
+            ommers: vec![ommer.clone()],
+            ..empty_block_input(15_000_000, 1_655_000_000)
+        };
+        let err = process(input).unwrap_err();
+        assert!(matches!(err, ProcessorError::UnsupportedBlock { .. }));
+
+        // Ommers after the merge violate the rules of the block instead.
+        let input = SP1RethInput {
+            ommers: vec![ommer],
+            ..empty_block_input(16_000_000, 1_668_000_000)
+        };
+        assert!(process(input).unwrap_err().is_consensus_violation());
+
+        // Blocks after Prague would otherwise be executed under the rules of Cancun.
+        let err = process(empty_block_input(22_500_000, 1_750_000_000)).unwrap_err();
+        assert!(matches!(err, ProcessorError::UnsupportedBlock { .. }));
+    }
+
+    #[test]
+    fn test_gnosis_withdrawals() {
+        // Gnosis blocks before the merge were sealed by AuRa.
+        let input = SP1RethInput {
+            chain_config: ChainConfig::gnosis(),
+            ..empty_block_input(25_000_000, 1_665_000_000)
+        };
+        assert!(matches!(
+            process(input).unwrap_err(),
+            ProcessorError::UnsupportedBlock { .. }
+        ));
+
+        // After Shanghai, the withdrawals contract is called even without withdrawals, here with
+        // a contract that reverts.
+        let contract = ChainConfig::gnosis().system_contracts.withdrawals.unwrap();
+        let mut db = InMemoryDB::default();
+        db.insert_account_info(
+            contract,
+            AccountInfo {
+                code: Some(Bytecode::new_raw(Bytes::from_static(&[
+                    0x60, 0x00, 0x80, 0xfd,
+                ]))),
+                ..Default::default()
+            },
+        );
+        let mut executor = EvmProcessor::new(
+            SP1RethInput {
+                chain_config: ChainConfig::gnosis(),
+                ..empty_block_input(29_000_000, 1_695_000_000)
+            },
+            db,
+        );
+        executor.initialize().unwrap();
+        assert!(matches!(
+            executor.execute(),
+            Err(ProcessorError::SystemCall { .. })
+        ));
+    }
+
+    #[test]
+    fn test_claimed_header() {
+        let input = empty_block_input(17_100_000, 1_682_000_000);
+        let header = process(input.clone()).unwrap();
+        let prove_invalid = |claimed_header| SP1RethInput {
+            prove_invalid: true,
+            claimed_header,
+            ..input.clone()
+        };
+
+        // Proving a block invalid requires a claimed header matching the input.
+        assert!(process(prove_invalid(Some(header.clone()))).is_ok());
+        assert!(matches!(
+            process(prove_invalid(None)),
+            Err(ProcessorError::InvalidInput { .. })
+        ));
+        let modifications: [fn(&mut Header); 4] = [
+            |header| header.beneficiary = Address::ZERO,
+            |header| header.transactions_root = B256::ZERO,
+            |header| header.withdrawals_root = None,
+            |header| header.gas_used = 1,
+        ];
+        for modify in modifications {
+            let mut claimed_header = header.clone();
+            modify(&mut claimed_header);
+            assert!(matches!(
+                process(prove_invalid(Some(claimed_header))),
+                Err(ProcessorError::InvalidInput { .. })
+            ));
+        }
+
+        // The fields only known after execution are not checked against the input.
+        let claimed_header = Header {
+            state_root: B256::ZERO,
+            receipts_root: B256::ZERO,
+            ..header
+        };
+        assert!(process(prove_invalid(Some(claimed_header))).is_ok());
+    }
+
+    /// Returns the input of an empty block of mainnet whose parent used half of its gas.
+    pub(crate) fn empty_block_input(number: u64, timestamp: u64) -> SP1RethInput {
+        let parent_header = Header {
+            number: number - 1,
+            timestamp: timestamp - 12,
+            gas_limit: 30_000_000,
+            gas_used: 15_000_000,
+            base_fee_per_gas: Some(1_000_000_000),
+            state_root: B256::repeat_byte(1),
+            ..Default::default()
+        };
+        SP1RethInput {
+            chain_config: ChainConfig::mainnet(),
+            parent_state_trie: MptNodeData::Digest(parent_header.state_root).into(),
+            parent_header,
+            beneficiary: Address::repeat_byte(2),
+            gas_limit: 30_000_000,
+            timestamp,
+            extra_data: Bytes::new(),
+            mix_hash: B256::repeat_byte(3),
+            difficulty: U256::ZERO,
+            nonce: 0,
+            ommers: Vec::new(),
+            parent_beacon_block_root: None,
+            base_fee_per_gas: Some(1_000_000_000),
+            excess_blob_gas: None,
+            parent_storage: Default::default(),
+            contracts: Vec::new(),
+            ancestor_headers: Vec::new(),
+            transactions: Vec::new(),
+            sender_hints: Default::default(),
+            withdrawals: Default::default(),
+            commit_receipts: false,
+            commit_block_oracle: false,
+            prove_invalid: false,
+            execution_filter: None,
+            block_overrides: None,
+            transaction_limit: None,
+            transaction_start: None,
+            state_root_interval: None,
+            expected_block_hash: None,
+            expected_gas_used: Some(0),
+            expected_blob_gas_used: None,
+            expected_transactions_root: Some(EMPTY_ROOT_HASH),
+            claimed_header: None,
+        }
+    }
+
+    fn process(input: SP1RethInput) -> Result<Header, ProcessorError> {
+        let mut executor = EvmProcessor::new(input, InMemoryDB::default());
+        executor.initialize()?;
+        executor.execute()?;
+        assert_eq!(executor.compact_receipts, Some(CompactReceipts::default()));
+        assert!(executor.receipts.as_ref().unwrap().is_empty());
+        executor.finalize()?;
+        Ok(executor.header.unwrap())
+    }
+
+    #[test]
+    fn test_empty_block() {
+        // The roots of the fast path are the roots of empty lists.
+        assert_eq!(transactions_root(&[]), EMPTY_ROOT_HASH);
+        assert_eq!(
+            ordered_trie_root_with_encoder(&[] as &[ReceiptWithBloom], |receipt, buf| {
+                receipt.encode_inner(buf, false);
+            }),
+            EMPTY_ROOT_HASH
+        );
+
+        // An empty block after the merge keeps the state root of its parent.
+        let header = process(empty_block_input(16_000_000, 1_668_000_000)).unwrap();
+        assert_eq!(header.state_root, B256::repeat_byte(1));
+        assert_eq!(header.transactions_root, EMPTY_ROOT_HASH);
+        assert_eq!(header.receipts_root, EMPTY_ROOT_HASH);
+        assert_eq!(header.logs_bloom, Bloom::default());
+        assert_eq!(header.gas_used, 0);
+        assert_eq!(header.withdrawals_root, None);
+
+        // After Shanghai, the header commits to the empty list of withdrawals.
+        let header = process(empty_block_input(17_100_000, 1_682_000_000)).unwrap();
+        assert_eq!(header.withdrawals_root, Some(EMPTY_ROOT_HASH));
+        assert_eq!(header.state_root, B256::repeat_byte(1));
+
+        // A state root interval of zero is rejected instead of dividing by zero.
+        let input = SP1RethInput {
+            state_root_interval: Some(0),
+            ..empty_block_input(16_000_000, 1_668_000_000)
+        };
+        assert!(matches!(
+            process(input),
+            Err(ProcessorError::InvalidInput { .. })
+        ));
+
+        // So is a state root interval without a way to compute the intermediate state roots.
+        let input = SP1RethInput {
+            state_root_interval: Some(1),
+            ..empty_block_input(16_000_000, 1_668_000_000)
+        };
+        assert!(matches!(
+            process(input),
+            Err(ProcessorError::InvalidInput { .. })
+        ));
+
+        // The base fee of the input must follow from the parent.
+        let input = SP1RethInput {
+            base_fee_per_gas: Some(1_000_000_001),
+            ..empty_block_input(16_000_000, 1_668_000_000)
+        };
+        assert!(matches!(
+            process(input),
+            Err(ProcessorError::InvalidBaseFee(
+                BaseFeeError::Mismatch { .. }
+            ))
+        ));
+
+        // An empty block must still match the expected gas used.
+        let input = SP1RethInput {
+            expected_gas_used: Some(21_000),
+            ..empty_block_input(16_000_000, 1_668_000_000)
+        };
+        assert!(matches!(
+            process(input),
+            Err(ProcessorError::InvalidHeader { .. })
+        ));
+    }
+
+    #[test]
+    fn test_empty_block_pipeline() {
+        // An empty block after Shanghai goes through the database, initialize, execute and
+        // finalize like every block, and finalize pins the header through its hash.
+        let mut input = empty_block_input(17_100_000, 1_682_000_000);
+        let expected = Header {
+            parent_hash: input.parent_header.hash_slow(),
+            ommers_hash: EMPTY_OMMER_ROOT_HASH,
+            beneficiary: input.beneficiary,
+            state_root: input.parent_header.state_root,
+            transactions_root: EMPTY_ROOT_HASH,
+            receipts_root: EMPTY_ROOT_HASH,
+            withdrawals_root: Some(EMPTY_ROOT_HASH),
+            number: 17_100_000,
+            gas_limit: input.gas_limit,
+            gas_used: 0,
+            timestamp: input.timestamp,
+            mix_hash: input.mix_hash,
+            base_fee_per_gas: input.base_fee_per_gas,
+            ..Default::default()
+        };
+        input.expected_block_hash = Some(expected.hash_slow());
+        let db = InMemoryDB::initialize(&mut input).unwrap();
+        let mut executor = EvmProcessor::new(input.clone(), db);
+        executor.initialize().unwrap();
+        executor.execute().unwrap();
+        executor.finalize().unwrap();
+        assert_eq!(executor.header.unwrap(), expected);
+
+        // A block whose header differs from the expected one is rejected.
+        input.extra_data = Bytes::from_static(b"other");
+        let db = InMemoryDB::initialize(&mut input).unwrap();
+        let mut executor = EvmProcessor::new(input, db);
+        executor.initialize().unwrap();
+        executor.execute().unwrap();
+        assert!(matches!(
+            executor.finalize(),
+            Err(ProcessorError::BlockHashMismatch { .. })
+        ));
+    }
+}
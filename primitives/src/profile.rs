@@ -0,0 +1,115 @@
+//! Chain profiles, which let crates outside of SP1 Reth describe how blocks of their chain are
+//! executed.
+//!
+//! A profile only has to produce a [ChainConfig]: the configuration is part of the input, so the
+//! program executes blocks of any registered chain without being rebuilt.
+
+use crate::chain::ChainConfig;
+use std::fmt;
+
+/// A chain whose blocks can be proven.
+pub trait ChainProfile: Send + Sync {
+    /// Returns the name the profile is registered under, e.g. `mainnet`.
+    fn name(&self) -> &str;
+
+    /// Returns the configuration of the chain: its fork schedule, base fee parameters, system
+    /// contracts and accepted transaction types.
+    fn config(&self) -> ChainConfig;
+}
+
+/// A built-in profile backed by one of the [ChainConfig] constructors.
+struct BuiltinProfile {
+    name: &'static str,
+    config: fn() -> ChainConfig,
+}
+
+impl ChainProfile for BuiltinProfile {
+    fn name(&self) -> &str {
+        self.name
+    }
+
+    fn config(&self) -> ChainConfig {
+        (self.config)()
+    }
+}
+
+/// A registry of the chain profiles known to the host.
+pub struct ChainRegistry {
+    profiles: Vec<Box<dyn ChainProfile>>,
+}
+
+impl ChainRegistry {
+    /// Creates an empty registry.
+    pub fn new() -> Self {
+        Self {
+            profiles: Vec::new(),
+        }
+    }
+
+    /// Registers a profile, replacing any profile previously registered under the same name.
+    pub fn register(&mut self, profile: impl ChainProfile + 'static) {
+        self.profiles.retain(|p| p.name() != profile.name());
+        self.profiles.push(Box::new(profile));
+    }
+
+    /// Returns the profile registered under the given name.
+    pub fn get(&self, name: &str) -> Option<&dyn ChainProfile> {
+        self.profiles
+            .iter()
+            .find(|p| p.name() == name)
+            .map(|p| p.as_ref())
+    }
+
+    /// Returns the profile of the chain with the given chain id.
+    pub fn by_chain_id(&self, chain_id: u64) -> Option<&dyn ChainProfile> {
+        self.profiles
+            .iter()
+            .find(|p| p.config().chain_id == chain_id)
+            .map(|p| p.as_ref())
+    }
+
+    /// Returns the names of the registered profiles.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.profiles.iter().map(|p| p.name())
+    }
+}
+
+impl fmt::Debug for ChainRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.names()).finish()
+    }
+}
+
+impl Default for ChainRegistry {
+    /// Creates a registry containing the chains supported by SP1 Reth.
+    fn default() -> Self {
+        let mut registry = Self::new();
+        registry.register(BuiltinProfile {
+            name: "mainnet",
+            config: ChainConfig::mainnet,
+        });
+        registry.register(BuiltinProfile {
+            name: "sepolia",
+            config: ChainConfig::sepolia,
+        });
+        registry.register(BuiltinProfile {
+            name: "holesky",
+            config: ChainConfig::holesky,
+        });
+        registry.register(BuiltinProfile {
+            name: "gnosis",
+            config: ChainConfig::gnosis,
+        });
+        #[cfg(feature = "polygon")]
+        registry.register(BuiltinProfile {
+            name: "polygon",
+            config: ChainConfig::polygon,
+        });
+        #[cfg(feature = "optimism")]
+        registry.register(BuiltinProfile {
+            name: "optimism",
+            config: ChainConfig::optimism_mainnet,
+        });
+        registry
+    }
+}
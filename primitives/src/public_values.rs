@@ -0,0 +1,270 @@
+//! The public values committed by the program for every proven block, in a versioned and stable
+//! ABI encoding, so that on-chain verifiers and other tooling can decode proofs without knowing
+//! the layout of the optional outputs.
+
+use crate::SP1RethInput;
+use reth_primitives::{B256, U256};
+use serde::{Deserialize, Serialize};
+
+/// The version of the layout of [SP1RethPublicValues] committed by this program.
+pub const PUBLIC_VALUES_VERSION: u8 = 2;
+
+/// A marker embedded in the program, naming the version of the public values it commits, so that
+/// the script can refuse to prove with an ELF built before the layout changed.
+pub const PUBLIC_VALUES_MARKER: [u8; 32] = *b"sp1-reth public values version 2";
+
+/// Returns whether the given ELF embeds the marker of [PUBLIC_VALUES_VERSION].
+pub fn elf_commits_current_version(elf: &[u8]) -> bool {
+    elf.windows(PUBLIC_VALUES_MARKER.len())
+        .any(|window| window == PUBLIC_VALUES_MARKER)
+}
+
+/// The length of ABI encoded [SP1RethPublicValues], made of eight 32-byte words.
+pub const PUBLIC_VALUES_LEN: usize = 256;
+
+/// The flag of the [TransactionRange] committed when only part of the block is executed.
+pub const FLAG_TRANSACTION_RANGE: u32 = 1 << 0;
+
+/// The flag of the compact receipts of the executed transactions.
+pub const FLAG_RECEIPTS: u32 = 1 << 1;
+
+/// The flag of the execution filter and the transactions it skipped.
+pub const FLAG_EXECUTION_FILTER: u32 = 1 << 2;
+
+/// The flag of the state roots after every state root interval of transactions.
+pub const FLAG_INTERMEDIATE_STATE_ROOTS: u32 = 1 << 3;
+
+/// The flag of the block commitment in the ABI of block-hash oracles.
+pub const FLAG_BLOCK_ORACLE: u32 = 1 << 4;
+
+/// The flag of the hash of the sender hints used.
+pub const FLAG_SENDER_HINTS: u32 = 1 << 5;
+
+/// The flag of the overrides of the block environment.
+pub const FLAG_BLOCK_OVERRIDES: u32 = 1 << 6;
+
+/// The flags of every optional output, which follow the public values in the order of their bits.
+const KNOWN_FLAGS: u32 = (1 << 7) - 1;
+
+/// Whether the block of the public values was executed, or proven invalid.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlockStatus {
+    /// The block was executed, and the public values hold the hash and state root of the
+    /// resulting header.
+    Valid = 0,
+
+    /// The block violates a consensus rule. The public values hold the hash of the header claimed
+    /// for the block and a zero state root, and are followed by an
+    /// [crate::abort::InvalidityRecord] instead of optional outputs.
+    Invalid = 1,
+}
+
+/// The chain, number, hashes and state root of a proven block, with the status of the block and
+/// the optional outputs committed after them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SP1RethPublicValues {
+    /// The version of the layout, see [PUBLIC_VALUES_VERSION].
+    pub version: u8,
+
+    /// Whether the block was executed or proven invalid.
+    pub status: BlockStatus,
+
+    /// The optional outputs committed after the public values, as a set of `FLAG_*` bits.
+    pub flags: u32,
+
+    /// The EIP-155 chain id of the chain of the block.
+    pub chain_id: u64,
+
+    /// The number of the block.
+    pub block_number: u64,
+
+    /// The hash of the parent of the block.
+    pub parent_hash: B256,
+
+    /// The hash of the block.
+    pub block_hash: B256,
+
+    /// The state root of the block.
+    pub state_root: B256,
+}
+
+/// The transactions executed by a proof of part of a block, committed after the public values
+/// when the input limits the executed transactions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TransactionRange {
+    /// The root of every transaction of the block, including those that were not executed.
+    pub transactions_root: B256,
+
+    /// The index of the first executed transaction.
+    pub start: u32,
+
+    /// The index after the last executed transaction.
+    pub end: u32,
+
+    /// The state root before the executed transactions.
+    pub start_state_root: B256,
+
+    /// The state root after the executed transactions.
+    pub state_root: B256,
+}
+
+impl SP1RethPublicValues {
+    /// Returns the flags of the optional outputs committed for the execution of the input.
+    pub fn flags(input: &SP1RethInput) -> u32 {
+        let mut flags = 0;
+        if input.transaction_limit.is_some() || input.transaction_start.is_some() {
+            flags |= FLAG_TRANSACTION_RANGE;
+        }
+        if input.commit_receipts {
+            flags |= FLAG_RECEIPTS;
+        }
+        if input.execution_filter.is_some() {
+            flags |= FLAG_EXECUTION_FILTER;
+        }
+        if input.state_root_interval.is_some() {
+            flags |= FLAG_INTERMEDIATE_STATE_ROOTS;
+        }
+        if input.commit_block_oracle {
+            flags |= FLAG_BLOCK_ORACLE;
+        }
+        if !input.sender_hints.is_empty() {
+            flags |= FLAG_SENDER_HINTS;
+        }
+        if input.block_overrides.is_some() {
+            flags |= FLAG_BLOCK_OVERRIDES;
+        }
+        flags
+    }
+
+    /// Returns whether the optional output of the flag is committed after the public values.
+    pub fn has(&self, flag: u32) -> bool {
+        self.flags & flag != 0
+    }
+
+    /// Encodes the public values as `abi.encode(uint8 version, uint8 status, uint32 flags, uint64
+    /// chainId, uint64 blockNumber, bytes32 parentHash, bytes32 blockHash, bytes32 stateRoot)`.
+    pub fn abi_encode(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(PUBLIC_VALUES_LEN);
+        out.extend_from_slice(&U256::from(self.version).to_be_bytes::<32>());
+        out.extend_from_slice(&U256::from(self.status as u8).to_be_bytes::<32>());
+        out.extend_from_slice(&U256::from(self.flags).to_be_bytes::<32>());
+        out.extend_from_slice(&U256::from(self.chain_id).to_be_bytes::<32>());
+        out.extend_from_slice(&U256::from(self.block_number).to_be_bytes::<32>());
+        out.extend_from_slice(self.parent_hash.as_slice());
+        out.extend_from_slice(self.block_hash.as_slice());
+        out.extend_from_slice(self.state_root.as_slice());
+        out
+    }
+
+    /// Decodes public values encoded by [SP1RethPublicValues::abi_encode].
+    ///
+    /// Returns [None] if the data has the wrong length, is of another version, has an unknown
+    /// status or flag, or a value does not fit in its type.
+    pub fn abi_decode(data: &[u8]) -> Option<Self> {
+        if data.len() != PUBLIC_VALUES_LEN {
+            return None;
+        }
+        let version: u8 = U256::from_be_slice(&data[..32]).try_into().ok()?;
+        if version != PUBLIC_VALUES_VERSION {
+            return None;
+        }
+        let status = match U256::from_be_slice(&data[32..64]).try_into().ok()? {
+            0u8 => BlockStatus::Valid,
+            1u8 => BlockStatus::Invalid,
+            _ => return None,
+        };
+        let flags: u32 = U256::from_be_slice(&data[64..96]).try_into().ok()?;
+        if flags & !KNOWN_FLAGS != 0 {
+            return None;
+        }
+        Some(Self {
+            version,
+            status,
+            flags,
+            chain_id: U256::from_be_slice(&data[96..128]).try_into().ok()?,
+            block_number: U256::from_be_slice(&data[128..160]).try_into().ok()?,
+            parent_hash: B256::from_slice(&data[160..192]),
+            block_hash: B256::from_slice(&data[192..224]),
+            state_root: B256::from_slice(&data[224..]),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_abi_round_trip() {
+        let public_values = SP1RethPublicValues {
+            version: PUBLIC_VALUES_VERSION,
+            status: BlockStatus::Valid,
+            flags: FLAG_RECEIPTS | FLAG_BLOCK_ORACLE,
+            chain_id: 1,
+            block_number: 19_000_000,
+            parent_hash: B256::repeat_byte(1),
+            block_hash: B256::repeat_byte(2),
+            state_root: B256::repeat_byte(3),
+        };
+        let encoded = public_values.abi_encode();
+        assert_eq!(encoded.len(), PUBLIC_VALUES_LEN);
+        assert_eq!(encoded[31], PUBLIC_VALUES_VERSION);
+        assert_eq!(
+            SP1RethPublicValues::abi_decode(&encoded),
+            Some(public_values)
+        );
+        let invalid = SP1RethPublicValues {
+            status: BlockStatus::Invalid,
+            flags: 0,
+            state_root: B256::ZERO,
+            ..public_values
+        };
+        assert_eq!(
+            SP1RethPublicValues::abi_decode(&invalid.abi_encode()),
+            Some(invalid)
+        );
+
+        // Other lengths, versions, statuses and flags, and out of range values are rejected.
+        assert_eq!(SP1RethPublicValues::abi_decode(&encoded[1..]), None);
+        let mut other_version = encoded.clone();
+        other_version[31] += 1;
+        assert_eq!(SP1RethPublicValues::abi_decode(&other_version), None);
+        let mut unknown_status = encoded.clone();
+        unknown_status[63] = 2;
+        assert_eq!(SP1RethPublicValues::abi_decode(&unknown_status), None);
+        let mut unknown_flag = encoded.clone();
+        unknown_flag[95] |= 1 << 7;
+        assert_eq!(SP1RethPublicValues::abi_decode(&unknown_flag), None);
+        let mut large_chain_id = encoded;
+        large_chain_id[96] = 1;
+        assert_eq!(SP1RethPublicValues::abi_decode(&large_chain_id), None);
+    }
+
+    #[test]
+    fn test_marker() {
+        let marker = std::str::from_utf8(&PUBLIC_VALUES_MARKER).unwrap();
+        assert!(marker.ends_with(&format!(" {}", PUBLIC_VALUES_VERSION)));
+
+        let mut elf = vec![0u8; 64];
+        assert!(!elf_commits_current_version(&elf));
+        elf[7..39].copy_from_slice(b"sp1-reth public values version 1");
+        assert!(!elf_commits_current_version(&elf));
+        elf[7..39].copy_from_slice(&PUBLIC_VALUES_MARKER);
+        assert!(elf_commits_current_version(&elf));
+    }
+
+    #[test]
+    fn test_flags() {
+        let input = crate::processor::tests::empty_block_input(19_000_000, 1_710_000_000);
+        assert_eq!(SP1RethPublicValues::flags(&input), 0);
+        let input = SP1RethInput {
+            transaction_limit: Some(1),
+            commit_block_oracle: true,
+            ..input
+        };
+        assert_eq!(
+            SP1RethPublicValues::flags(&input),
+            FLAG_TRANSACTION_RANGE | FLAG_BLOCK_ORACLE
+        );
+    }
+}
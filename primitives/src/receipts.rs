@@ -0,0 +1,80 @@
+//! A compact representation of the receipts of a block.
+
+use serde::{Deserialize, Serialize};
+
+/// A compact encoding of the outcome of every transaction in a block.
+///
+/// Full receipts contain all emitted logs, which makes committing them as public values
+/// prohibitively large. This encoding only keeps the per-transaction status, gas usage and number
+/// of emitted logs, which is enough for consumers to learn the outcome of each transaction.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CompactReceipts {
+    /// A bitvector where bit `i` (least significant bit first) is set if transaction `i`
+    /// succeeded.
+    pub status: Vec<u8>,
+
+    /// The gas used by each transaction, i.e. the deltas of the cumulative gas used.
+    pub gas_used: Vec<u64>,
+
+    /// The number of logs emitted by each transaction.
+    pub log_counts: Vec<u32>,
+}
+
+impl CompactReceipts {
+    /// Appends the outcome of the next transaction.
+    pub fn push(&mut self, success: bool, gas_used: u64, log_count: u32) {
+        let index = self.gas_used.len();
+        if index % 8 == 0 {
+            self.status.push(0);
+        }
+        if success {
+            self.status[index / 8] |= 1 << (index % 8);
+        }
+        self.gas_used.push(gas_used);
+        self.log_counts.push(log_count);
+    }
+
+    /// Returns the number of transactions.
+    pub fn len(&self) -> usize {
+        self.gas_used.len()
+    }
+
+    /// Returns whether there are no transactions.
+    pub fn is_empty(&self) -> bool {
+        self.gas_used.is_empty()
+    }
+
+    /// Returns whether the transaction at the given index succeeded.
+    pub fn success(&self, index: usize) -> Option<bool> {
+        if index >= self.len() {
+            return None;
+        }
+        Some(self.status[index / 8] & (1 << (index % 8)) != 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_decode() {
+        // Nine transactions spill the status into a second byte.
+        let mut receipts = CompactReceipts::default();
+        for index in 0..9u32 {
+            receipts.push(index % 3 != 0, 21_000 + u64::from(index), index);
+        }
+        assert_eq!(receipts.status, vec![0b1011_0110, 0b0000_0001]);
+        assert_eq!(receipts.len(), 9);
+        assert_eq!(receipts.success(0), Some(false));
+        assert_eq!(receipts.success(8), Some(true));
+        assert_eq!(receipts.success(9), None);
+
+        // The program commits the receipts with the serialization of the zkVM.
+        let encoded = bincode::serialize(&receipts).unwrap();
+        let decoded: CompactReceipts = bincode::deserialize(&encoded).unwrap();
+        assert_eq!(decoded, receipts);
+        assert_eq!(decoded.gas_used[8], 21_008);
+        assert_eq!(decoded.log_counts[8], 8);
+    }
+}
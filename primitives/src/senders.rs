@@ -0,0 +1,136 @@
+//! Sender hints, which let the guest verify the signature of a transaction against the public key
+//! of its sender supplied by the host, instead of recovering the public key from the signature.
+//!
+//! A hint that fails verification is ignored and the sender is recovered as usual, so a wrong
+//! hint costs cycles but never changes the outcome of the block.
+
+use crate::mpt::keccak;
+use k256::ecdsa::{RecoveryId, Signature, VerifyingKey};
+use k256::elliptic_curve::group::Curve;
+use k256::elliptic_curve::ops::{Invert, Reduce};
+use k256::elliptic_curve::point::AffineCoordinates;
+use k256::elliptic_curve::scalar::IsHigh;
+use k256::{FieldBytes, ProjectivePoint, Scalar, U256};
+use reth_primitives::{Address, TransactionSignedNoHash, B256, B512};
+
+/// Returns the public key that signed the transaction, as its sender hint, or [None] if the
+/// signature is invalid.
+pub fn recover_sender_hint(tx: &TransactionSignedNoHash) -> Option<B512> {
+    let signature = signature(tx)?;
+    let recovery_id = RecoveryId::from_byte(tx.signature.odd_y_parity as u8)?;
+    let key = VerifyingKey::recover_from_prehash(
+        tx.transaction.signature_hash().as_slice(),
+        &signature,
+        recovery_id,
+    )
+    .ok()?;
+    Some(B512::from_slice(
+        &key.to_encoded_point(false).as_bytes()[1..],
+    ))
+}
+
+/// Verifies the signature of the transaction against the hinted public key, and returns the
+/// address of the key if it is the key recovered from the signature.
+///
+/// An ECDSA signature is valid for both keys recoverable from it, so besides verifying it as
+/// usual, the point `R` computed while verifying must have the y-parity of the signature and an
+/// unreduced x-coordinate, as recovery assumes.
+pub fn verify_sender_hint(tx: &TransactionSignedNoHash, hint: &B512) -> Option<Address> {
+    let mut encoded = [0u8; 65];
+    encoded[0] = 0x04;
+    encoded[1..].copy_from_slice(hint.as_slice());
+    let key = VerifyingKey::from_sec1_bytes(&encoded).ok()?;
+    let (r, s) = signature(tx)?.split_scalars();
+    if s.is_high().into() {
+        return None;
+    }
+
+    // Reference: https://github.com/RustCrypto/signatures/blob/master/ecdsa/src/hazmat.rs
+    let prehash = tx.transaction.signature_hash();
+    let z = <Scalar as Reduce<U256>>::reduce_bytes(FieldBytes::from_slice(prehash.as_slice()));
+    let s_inv = *s.invert_vartime();
+    let point = (ProjectivePoint::GENERATOR * (z * s_inv)
+        + ProjectivePoint::from(*key.as_affine()) * (*r * s_inv))
+        .to_affine();
+    if point.x() != r.to_bytes() || bool::from(point.y_is_odd()) != tx.signature.odd_y_parity {
+        return None;
+    }
+    Some(Address::from_slice(&keccak(hint.as_slice())[12..]))
+}
+
+/// Hashes the transaction hashes and senders of the hints used by the guest, so that auditors
+/// can check which hints a proof relied on.
+pub fn sender_hints_hash(used: &[(B256, Address)]) -> B256 {
+    let mut data = Vec::with_capacity(used.len() * 52);
+    for (tx_hash, sender) in used {
+        data.extend_from_slice(tx_hash.as_slice());
+        data.extend_from_slice(sender.as_slice());
+    }
+    keccak(data).into()
+}
+
+/// Returns the ECDSA signature of the transaction. High `s` values are rejected when verifying,
+/// as required since Homestead.
+fn signature(tx: &TransactionSignedNoHash) -> Option<Signature> {
+    Signature::from_scalars(
+        tx.signature.r.to_be_bytes::<32>(),
+        tx.signature.s.to_be_bytes::<32>(),
+    )
+    .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use k256::ecdsa::SigningKey;
+    use reth_primitives::{Transaction, TransactionKind, TxEip1559};
+
+    /// Returns a transfer signed by the given key.
+    fn signed_transfer(key: &SigningKey) -> TransactionSignedNoHash {
+        let transaction = Transaction::Eip1559(TxEip1559 {
+            chain_id: 1,
+            gas_limit: 21_000,
+            max_fee_per_gas: 1_000_000_000,
+            to: TransactionKind::Call(Address::repeat_byte(1)),
+            ..Default::default()
+        });
+        let (signature, recovery_id) = key
+            .sign_prehash_recoverable(transaction.signature_hash().as_slice())
+            .unwrap();
+        TransactionSignedNoHash {
+            signature: reth_primitives::Signature {
+                r: reth_primitives::U256::from_be_slice(&signature.r().to_bytes()),
+                s: reth_primitives::U256::from_be_slice(&signature.s().to_bytes()),
+                odd_y_parity: recovery_id.is_y_odd(),
+            },
+            transaction,
+        }
+    }
+
+    #[test]
+    fn test_verify_sender_hint() {
+        let key = SigningKey::from_slice(&[1; 32]).unwrap();
+        let tx = signed_transfer(&key);
+        let hint = recover_sender_hint(&tx).unwrap();
+        let public_key = key.verifying_key().to_encoded_point(false);
+        assert_eq!(hint.as_slice(), &public_key.as_bytes()[1..]);
+        assert_eq!(
+            verify_sender_hint(&tx, &hint),
+            Some(tx.recover_signer().unwrap())
+        );
+
+        // The key recovered with the flipped y-parity also verifies the signature, but is not the
+        // sender.
+        let mut flipped = tx.clone();
+        flipped.signature.odd_y_parity = !flipped.signature.odd_y_parity;
+        let flipped_hint = recover_sender_hint(&flipped).unwrap();
+        assert_ne!(flipped_hint, hint);
+        assert_eq!(verify_sender_hint(&tx, &flipped_hint), None);
+        assert!(verify_sender_hint(&flipped, &flipped_hint).is_some());
+
+        // A key that did not sign the transaction is rejected.
+        let other = SigningKey::from_slice(&[2; 32]).unwrap();
+        let other_hint = recover_sender_hint(&signed_transfer(&other)).unwrap();
+        assert_eq!(verify_sender_hint(&tx, &other_hint), None);
+    }
+}
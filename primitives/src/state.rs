@@ -0,0 +1,255 @@
+//! Folding the state changes of revm into the state and storage tries to compute state roots.
+
+use crate::mpt::keccak;
+use crate::mpt::MptNode;
+use crate::mpt::StateAccount;
+use crate::mpt::StorageEntry;
+use crate::processor::ProcessorError;
+use crate::SP1RethInput;
+
+use hashbrown::hash_map::Entry;
+use reth_primitives::{Address, B256, U256};
+use revm::db::AccountState;
+use revm::db::InMemoryDB;
+use revm::primitives::HashMap;
+
+/// Computes state roots by applying the state changes of a database to the tries of a witness.
+///
+/// The parent tries are left untouched: changes are applied to copy-on-write clones, and only the
+/// storage tries of the changed accounts are cloned. Changes of several databases can be applied
+/// one after another, e.g. to compute the state root after a range of blocks.
+pub struct StateRootComputer<'a> {
+    /// The state trie with the changes applied so far.
+    state_trie: MptNode,
+
+    /// The storage tries of the witness.
+    parent_storage: &'a HashMap<Address, StorageEntry>,
+
+    /// The storage tries of the accounts changed so far.
+    storage_tries: HashMap<Address, MptNode>,
+
+    /// Scratch space for the nibbles of the key being updated.
+    nibs: Vec<u8>,
+
+    /// Scratch space for the hashed storage changes of the account being updated.
+    slots: Vec<([u8; 32], U256)>,
+}
+
+impl<'a> StateRootComputer<'a> {
+    /// Creates a computer starting from the given state trie and storage tries.
+    pub fn new(state_trie: &MptNode, parent_storage: &'a HashMap<Address, StorageEntry>) -> Self {
+        Self {
+            state_trie: state_trie.clone(),
+            parent_storage,
+            storage_tries: HashMap::new(),
+            nibs: Vec::with_capacity(64),
+            slots: Vec::new(),
+        }
+    }
+
+    /// Creates a computer starting from the parent tries of the input.
+    pub fn from_input(input: &'a SP1RethInput) -> Self {
+        Self::new(&input.parent_state_trie, &input.parent_storage)
+    }
+
+    /// Applies the changes of every touched account of the database to the tries.
+    ///
+    /// Accounts and storage slots are applied in the order of their keys in the tries, so that
+    /// consecutive updates walk and copy the shared nodes of their paths while they are hot, and
+    /// the nibbles of all keys are written into the same scratch buffer.
+    pub fn apply(&mut self, db: &InMemoryDB) -> Result<(), ProcessorError> {
+        // Ignore untouched accounts.
+        let mut accounts = db
+            .accounts
+            .iter()
+            .filter(|(_, account)| account.account_state != AccountState::None)
+            .map(|(address, account)| (keccak(address), address, account))
+            .collect::<Vec<_>>();
+        accounts.sort_unstable_by_key(|(state_trie_index, _, _)| *state_trie_index);
+
+        for (state_trie_index, address, account) in accounts {
+            let storage_trie = match account.account_state {
+                // Remove from state trie if it has been deleted.
+                AccountState::NotExisting => {
+                    self.state_trie
+                        .delete_with(&state_trie_index, &mut self.nibs)?;
+                    self.storage_tries.insert(*address, MptNode::default());
+                    continue;
+                }
+                // The storage of an account created in the block, including one recreated after
+                // being destroyed, starts empty regardless of the parent storage.
+                AccountState::StorageCleared => {
+                    let storage_trie = self.storage_tries.entry(*address).or_default();
+                    storage_trie.clear();
+                    storage_trie
+                }
+                // Otherwise update the storage trie of the account.
+                _ => match self.storage_tries.entry(*address) {
+                    Entry::Occupied(entry) => entry.into_mut(),
+                    Entry::Vacant(entry) => {
+                        let (parent_trie, _) = self
+                            .parent_storage
+                            .get(address)
+                            .ok_or(ProcessorError::MissingWitness { address: *address })?;
+                        entry.insert(parent_trie.clone())
+                    }
+                },
+            };
+
+            // Apply all storage changes to the storage trie.
+            self.slots.clear();
+            self.slots.extend(
+                account
+                    .storage
+                    .iter()
+                    .map(|(key, value)| (keccak(key.to_be_bytes::<32>()), *value)),
+            );
+            self.slots
+                .sort_unstable_by_key(|(storage_trie_index, _)| *storage_trie_index);
+            for (storage_trie_index, value) in &self.slots {
+                if value == &U256::ZERO {
+                    storage_trie.delete_with(storage_trie_index, &mut self.nibs)?;
+                } else {
+                    storage_trie.insert_rlp_with(storage_trie_index, *value, &mut self.nibs)?;
+                }
+            }
+
+            let state_account = StateAccount {
+                nonce: account.info.nonce,
+                balance: account.info.balance,
+                storage_root: storage_trie.hash(),
+                code_hash: account.info.code_hash,
+            };
+            self.state_trie
+                .insert_rlp_with(&state_trie_index, state_account, &mut self.nibs)?;
+        }
+        Ok(())
+    }
+
+    /// Returns the state root with the changes applied so far.
+    pub fn root(&self) -> B256 {
+        self.state_trie.hash()
+    }
+
+    /// Returns the state trie and the storage of the witness with the changes applied so far,
+    /// keeping the proven slots of every account.
+    pub fn into_tries(self) -> (MptNode, HashMap<Address, StorageEntry>) {
+        let mut storage = self.parent_storage.clone();
+        for (address, storage_trie) in self.storage_tries {
+            storage.entry(address).or_default().0 = storage_trie;
+        }
+        (self.state_trie, storage)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use revm::db::DbAccount;
+    use revm::primitives::AccountInfo;
+
+    fn slot_key(index: u64) -> [u8; 32] {
+        keccak(U256::from(index).to_be_bytes::<32>())
+    }
+
+    fn storage_trie(slots: &[(u64, u64)]) -> MptNode {
+        let mut trie = MptNode::default();
+        for (index, value) in slots {
+            trie.insert_rlp(&slot_key(*index), U256::from(*value))
+                .unwrap();
+        }
+        trie
+    }
+
+    fn state_trie(accounts: &[(Address, u64, &MptNode)]) -> MptNode {
+        let mut trie = MptNode::default();
+        for (address, balance, storage_trie) in accounts {
+            let account = StateAccount {
+                balance: U256::from(*balance),
+                storage_root: storage_trie.hash(),
+                ..Default::default()
+            };
+            trie.insert_rlp(&keccak(address), account).unwrap();
+        }
+        trie
+    }
+
+    fn db(accounts: &[(Address, AccountState, u64, &[(u64, u64)])]) -> InMemoryDB {
+        let mut db = InMemoryDB::default();
+        for (address, account_state, balance, slots) in accounts {
+            let account = DbAccount {
+                info: AccountInfo {
+                    balance: U256::from(*balance),
+                    ..Default::default()
+                },
+                account_state: account_state.clone(),
+                storage: slots
+                    .iter()
+                    .map(|(index, value)| (U256::from(*index), U256::from(*value)))
+                    .collect(),
+            };
+            db.accounts.insert(*address, account);
+        }
+        db
+    }
+
+    #[test]
+    fn test_selfdestruct() {
+        let a = Address::with_last_byte(1);
+        let b = Address::with_last_byte(2);
+        let a_storage = storage_trie(&[(1, 1), (2, 2)]);
+        let b_storage = storage_trie(&[(1, 1)]);
+        let parent_state_trie = state_trie(&[(a, 1, &a_storage), (b, 1, &b_storage)]);
+        let parent_storage = HashMap::from_iter([
+            (a, (a_storage.clone(), vec![U256::from(1), U256::from(2)])),
+            (b, (b_storage.clone(), vec![U256::from(1)])),
+        ]);
+
+        // A destroyed account is removed from the state trie.
+        let mut computer = StateRootComputer::new(&parent_state_trie, &parent_storage);
+        computer
+            .apply(&db(&[(a, AccountState::NotExisting, 0, &[])]))
+            .unwrap();
+        assert_eq!(computer.root(), state_trie(&[(b, 1, &b_storage)]).hash());
+
+        // A recreated account starts from empty storage, keeping none of its parent slots.
+        let mut computer = StateRootComputer::new(&parent_state_trie, &parent_storage);
+        computer
+            .apply(&db(&[(a, AccountState::StorageCleared, 5, &[(3, 3)])]))
+            .unwrap();
+        let expected = state_trie(&[(a, 5, &storage_trie(&[(3, 3)])), (b, 1, &b_storage)]);
+        assert_eq!(computer.root(), expected.hash());
+
+        // An account touched after being destroyed by an earlier database keeps empty storage.
+        let mut computer = StateRootComputer::new(&parent_state_trie, &parent_storage);
+        computer
+            .apply(&db(&[(a, AccountState::NotExisting, 0, &[])]))
+            .unwrap();
+        computer
+            .apply(&db(&[(a, AccountState::Touched, 7, &[])]))
+            .unwrap();
+        let expected = state_trie(&[(a, 7, &MptNode::default()), (b, 1, &b_storage)]);
+        assert_eq!(computer.root(), expected.hash());
+    }
+
+    #[test]
+    fn test_created_without_witness() {
+        let a = Address::with_last_byte(1);
+        let parent_storage = HashMap::new();
+        let mut computer = StateRootComputer::new(&MptNode::default(), &parent_storage);
+
+        // The storage of a created account does not depend on the witness.
+        computer
+            .apply(&db(&[(a, AccountState::StorageCleared, 1, &[(1, 1)])]))
+            .unwrap();
+        let expected = state_trie(&[(a, 1, &storage_trie(&[(1, 1)]))]);
+        assert_eq!(computer.root(), expected.hash());
+
+        // Updating an existing account requires its storage witness.
+        let b = Address::with_last_byte(2);
+        assert!(matches!(
+            computer.apply(&db(&[(b, AccountState::Touched, 1, &[])])),
+            Err(ProcessorError::MissingWitness { address }) if address == b
+        ));
+    }
+}
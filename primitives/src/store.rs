@@ -0,0 +1,486 @@
+//! Serialization of tries as a store of distinct nodes, which the roots reference by index.
+//!
+//! The storage tries of different accounts often share subtries, e.g. the storage of clones of
+//! the same contract, which a plain serialization writes once per trie. Serializing the tries
+//! through a store writes every distinct node once, children before their parents, and
+//! deserializing it shares the node between the tries again. Since a node caches its reference,
+//! the guest also hashes every distinct node of the witness only once, wherever it appears.
+//!
+//! Nodes are referenced by their index in the store rather than by their hash, which is smaller
+//! and spares the guest a lookup. The guest hashes the deserialized tries when validating them
+//! against the parent state root, so a store that does not match the witness is rejected there.
+//!
+//! The nodes are written with a compact binary codec rather than the derived serde encoding, whose
+//! fixed-size lengths and child indices dominate the size of the witness. Every node starts with
+//! a tag byte, followed by:
+//!
+//! - nothing for an empty node,
+//! - a 16-bit bitmap of the present children of a branch, and the distance to each of them,
+//! - the length and bytes of the path and of the value of a leaf,
+//! - the length and bytes of the path of an extension, and the distance to its child,
+//! - the 32 bytes of a digest.
+//!
+//! Lengths and distances are LEB128 varints. A child is referenced by the distance from its parent
+//! back to it in the store, which is small for the nodes written just before their parent.
+
+use crate::mpt::{MptNode, MptNodeData, MptNodeReference, StorageEntry};
+use reth_primitives::{Address, B256, U256};
+use revm::primitives::HashMap;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::sync::Arc;
+
+/// A node of the store, referencing its children by their index.
+#[cfg_attr(test, derive(Debug, PartialEq))]
+enum StoredNode {
+    Null,
+    Branch([Option<u32>; 16]),
+    Leaf(Vec<u8>, Vec<u8>),
+    Extension(Vec<u8>, u32),
+    Digest(B256),
+}
+
+/// The distinct nodes of a set of tries, and the roots of the tries.
+#[derive(Serialize, Deserialize)]
+struct NodeStore<R> {
+    nodes: CompactNodes,
+    roots: R,
+}
+
+const NULL: u8 = 0;
+const BRANCH: u8 = 1;
+const LEAF: u8 = 2;
+const EXTENSION: u8 = 3;
+const DIGEST: u8 = 4;
+
+/// The nodes of a store, serialized as bytes with the compact codec.
+#[cfg_attr(test, derive(Debug, PartialEq))]
+struct CompactNodes(Vec<StoredNode>);
+
+impl Serialize for CompactNodes {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut out = Vec::new();
+        for (index, node) in self.0.iter().enumerate() {
+            // Children are stored before their parents, so the distance back to them is positive.
+            let distance = |child: u32| (index as u32 - child) as u64;
+            match node {
+                StoredNode::Null => out.push(NULL),
+                StoredNode::Branch(children) => {
+                    out.push(BRANCH);
+                    let bitmap = children
+                        .iter()
+                        .enumerate()
+                        .filter(|(_, child)| child.is_some())
+                        .fold(0u16, |bitmap, (nib, _)| bitmap | (1 << nib));
+                    out.extend_from_slice(&bitmap.to_le_bytes());
+                    for child in children.iter().flatten() {
+                        write_varint(&mut out, distance(*child));
+                    }
+                }
+                StoredNode::Leaf(prefix, value) => {
+                    out.push(LEAF);
+                    write_bytes(&mut out, prefix);
+                    write_bytes(&mut out, value);
+                }
+                StoredNode::Extension(prefix, child) => {
+                    out.push(EXTENSION);
+                    write_bytes(&mut out, prefix);
+                    write_varint(&mut out, distance(*child));
+                }
+                StoredNode::Digest(digest) => {
+                    out.push(DIGEST);
+                    out.extend_from_slice(digest.as_slice());
+                }
+            }
+        }
+        serializer.serialize_bytes(&out)
+    }
+}
+
+impl<'de> Deserialize<'de> for CompactNodes {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct Visitor;
+
+        impl<'de> serde::de::Visitor<'de> for Visitor {
+            type Value = CompactNodes;
+
+            fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+                formatter.write_str("compactly encoded trie nodes")
+            }
+
+            fn visit_bytes<E: serde::de::Error>(self, bytes: &[u8]) -> Result<Self::Value, E> {
+                decode_nodes(bytes).map(CompactNodes).map_err(E::custom)
+            }
+
+            fn visit_seq<A: serde::de::SeqAccess<'de>>(
+                self,
+                mut seq: A,
+            ) -> Result<Self::Value, A::Error> {
+                let mut bytes = Vec::with_capacity(seq.size_hint().unwrap_or_default());
+                while let Some(byte) = seq.next_element()? {
+                    bytes.push(byte);
+                }
+                self.visit_bytes(&bytes)
+            }
+        }
+
+        deserializer.deserialize_byte_buf(Visitor)
+    }
+}
+
+fn write_varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+fn write_bytes(out: &mut Vec<u8>, bytes: &[u8]) {
+    write_varint(out, bytes.len() as u64);
+    out.extend_from_slice(bytes);
+}
+
+/// Reads the compact encoding of nodes, see [CompactNodes].
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8], &'static str> {
+        if self.0.len() < len {
+            return Err("unexpected end of nodes");
+        }
+        let (bytes, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Ok(bytes)
+    }
+
+    fn varint(&mut self) -> Result<u64, &'static str> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.bytes(1)?[0];
+            value |= ((byte & 0x7f) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err("varint overflow")
+    }
+
+    fn vec(&mut self) -> Result<Vec<u8>, &'static str> {
+        let len = usize::try_from(self.varint()?).map_err(|_| "length overflow")?;
+        Ok(self.bytes(len)?.to_vec())
+    }
+
+    /// Reads the distance back to a child of the node at `index`, and returns its index.
+    fn child(&mut self, index: u32) -> Result<u32, &'static str> {
+        u32::try_from(self.varint()?)
+            .ok()
+            .and_then(|distance| index.checked_sub(distance))
+            .ok_or("node references unknown node")
+    }
+}
+
+fn decode_nodes(bytes: &[u8]) -> Result<Vec<StoredNode>, &'static str> {
+    let mut reader = Reader(bytes);
+    let mut nodes = Vec::new();
+    while !reader.0.is_empty() {
+        let index = u32::try_from(nodes.len()).map_err(|_| "too many nodes")?;
+        let node = match reader.bytes(1)?[0] {
+            NULL => StoredNode::Null,
+            BRANCH => {
+                let bitmap = u16::from_le_bytes(reader.bytes(2)?.try_into().unwrap());
+                let mut children = [None; 16];
+                for (nib, child) in children.iter_mut().enumerate() {
+                    if bitmap & (1 << nib) != 0 {
+                        *child = Some(reader.child(index)?);
+                    }
+                }
+                StoredNode::Branch(children)
+            }
+            LEAF => StoredNode::Leaf(reader.vec()?, reader.vec()?),
+            EXTENSION => StoredNode::Extension(reader.vec()?, reader.child(index)?),
+            DIGEST => StoredNode::Digest(B256::from_slice(reader.bytes(32)?)),
+            _ => return Err("unknown node tag"),
+        };
+        nodes.push(node);
+    }
+    Ok(nodes)
+}
+
+/// Collects the distinct nodes of tries into a store.
+#[derive(Default)]
+struct StoreBuilder {
+    nodes: Vec<StoredNode>,
+
+    /// The index of every stored node. Digests are kept apart from the resolved nodes with the
+    /// same reference, so that a subtrie is never swapped for its digest.
+    indices: std::collections::HashMap<(bool, MptNodeReference), u32>,
+}
+
+impl StoreBuilder {
+    /// Stores the node and its descendants, and returns the index of the node.
+    fn insert(&mut self, node: &MptNode) -> u32 {
+        let key = (
+            matches!(node.as_data(), MptNodeData::Digest(_)),
+            node.reference(),
+        );
+        if let Some(index) = self.indices.get(&key) {
+            return *index;
+        }
+        let stored = match node.as_data() {
+            MptNodeData::Null => StoredNode::Null,
+            MptNodeData::Branch(children) => {
+                let mut indices = [None; 16];
+                for (index, child) in indices.iter_mut().zip(children) {
+                    *index = child.as_ref().map(|child| self.insert(child));
+                }
+                StoredNode::Branch(indices)
+            }
+            MptNodeData::Leaf(prefix, value) => StoredNode::Leaf(prefix.clone(), value.clone()),
+            MptNodeData::Extension(prefix, child) => {
+                StoredNode::Extension(prefix.clone(), self.insert(child))
+            }
+            MptNodeData::Digest(digest) => StoredNode::Digest(*digest),
+        };
+        let index = u32::try_from(self.nodes.len()).unwrap();
+        self.nodes.push(stored);
+        self.indices.insert(key, index);
+        index
+    }
+}
+
+/// Rebuilds the nodes of a store, sharing every node between its parents.
+fn build_nodes<E: serde::de::Error>(stored: Vec<StoredNode>) -> Result<Vec<Arc<MptNode>>, E> {
+    let mut nodes: Vec<Arc<MptNode>> = Vec::with_capacity(stored.len());
+    for node in stored {
+        // Children are stored before their parents, which also rules out cycles.
+        let child = |index: u32| {
+            nodes
+                .get(index as usize)
+                .cloned()
+                .ok_or_else(|| E::custom(format!("node references unknown node {}", index)))
+        };
+        let data = match node {
+            StoredNode::Null => MptNodeData::Null,
+            StoredNode::Branch(indices) => {
+                let mut children: [Option<Arc<MptNode>>; 16] = Default::default();
+                for (child_node, index) in children.iter_mut().zip(indices) {
+                    *child_node = index.map(&child).transpose()?;
+                }
+                MptNodeData::Branch(children)
+            }
+            StoredNode::Leaf(prefix, value) => MptNodeData::Leaf(prefix, value),
+            StoredNode::Extension(prefix, index) => MptNodeData::Extension(prefix, child(index)?),
+            StoredNode::Digest(digest) => MptNodeData::Digest(digest),
+        };
+        nodes.push(Arc::new(data.into()));
+    }
+    Ok(nodes)
+}
+
+/// Returns the root at the given index of the rebuilt nodes.
+fn root<E: serde::de::Error>(nodes: &[Arc<MptNode>], index: u32) -> Result<MptNode, E> {
+    nodes
+        .get(index as usize)
+        .map(|node| node.as_ref().clone())
+        .ok_or_else(|| E::custom(format!("root references unknown node {}", index)))
+}
+
+/// Serializes a single trie through a store, for use with `#[serde(with = "...")]`.
+pub mod trie {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(trie: &MptNode, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut builder = StoreBuilder::default();
+        let roots = builder.insert(trie);
+        NodeStore {
+            nodes: CompactNodes(builder.nodes),
+            roots,
+        }
+        .serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<MptNode, D::Error> {
+        let store = NodeStore::<u32>::deserialize(deserializer)?;
+        let nodes = build_nodes(store.nodes.0)?;
+        root(&nodes, store.roots)
+    }
+}
+
+/// Serializes the storage tries of a set of accounts through a shared store, for use with
+/// `#[serde(with = "...")]`.
+pub mod storage {
+    use super::*;
+    use serde::de::Error as _;
+
+    pub fn serialize<S: Serializer>(
+        storage: &HashMap<Address, StorageEntry>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        // Accounts are sorted, so that the same witness always serializes to the same bytes.
+        let mut accounts = storage.iter().collect::<Vec<_>>();
+        accounts.sort_unstable_by_key(|(address, _)| **address);
+
+        let mut builder = StoreBuilder::default();
+        let roots = accounts
+            .into_iter()
+            .map(|(address, (trie, slots))| (*address, builder.insert(trie), slots))
+            .collect::<Vec<_>>();
+        NodeStore {
+            nodes: CompactNodes(builder.nodes),
+            roots,
+        }
+        .serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<HashMap<Address, StorageEntry>, D::Error> {
+        let store = NodeStore::<Vec<(Address, u32, Vec<U256>)>>::deserialize(deserializer)?;
+        let nodes = build_nodes(store.nodes.0)?;
+        let mut storage = HashMap::with_capacity(store.roots.len());
+        for (address, index, slots) in store.roots {
+            if storage
+                .insert(address, (root(&nodes, index)?, slots))
+                .is_some()
+            {
+                return Err(D::Error::custom(format!(
+                    "duplicate storage of account {}",
+                    address
+                )));
+            }
+        }
+        Ok(storage)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mpt::keccak;
+
+    #[derive(Serialize, Deserialize)]
+    struct Tries {
+        #[serde(with = "trie")]
+        trie: MptNode,
+        #[serde(with = "storage")]
+        storage: HashMap<Address, StorageEntry>,
+    }
+
+    #[derive(Deserialize)]
+    struct Trie(#[serde(with = "trie")] MptNode);
+
+    #[test]
+    fn test_shared_storage() {
+        let mut shared = MptNode::default();
+        for i in 0..64u64 {
+            shared.insert_rlp(&keccak(i.to_be_bytes()), i + 1).unwrap();
+        }
+        let mut modified = shared.clone();
+        modified
+            .insert_rlp(&keccak(64u64.to_be_bytes()), 65u64)
+            .unwrap();
+        let digest = MptNode::from(MptNodeData::Digest(shared.hash()));
+        let storage = HashMap::from_iter([
+            (
+                Address::with_last_byte(1),
+                (shared.clone(), vec![U256::from(1)]),
+            ),
+            (Address::with_last_byte(2), (shared.clone(), vec![])),
+            (Address::with_last_byte(3), (modified.clone(), vec![])),
+            (Address::with_last_byte(4), (digest.clone(), vec![])),
+        ]);
+        let tries = Tries {
+            trie: shared.clone(),
+            storage: storage.clone(),
+        };
+
+        // Shared tries are only written once.
+        let encoded = bincode::serialize(&tries).unwrap();
+        let plain = bincode::serialize(&(&tries.trie, &storage)).unwrap();
+        assert!(encoded.len() < plain.len());
+
+        let decoded: Tries = bincode::deserialize(&encoded).unwrap();
+        assert_eq!(decoded.trie, shared);
+        assert_eq!(decoded.storage, storage);
+        for (address, (trie, _)) in &storage {
+            assert_eq!(decoded.storage[address].0.hash(), trie.hash());
+        }
+
+        // The digest of a trie is not replaced by the trie itself.
+        assert_eq!(decoded.storage[&Address::with_last_byte(4)].0, digest);
+    }
+
+    #[test]
+    fn test_identical_nodes() {
+        // Two storage tries with the same leaf at the same depth, but different siblings.
+        let value = vec![0x42; 40];
+        let mut first = MptNode::default();
+        first.insert(&[0x10; 32], value.clone()).unwrap();
+        first.insert(&[0x20; 32], value.clone()).unwrap();
+        let mut second = MptNode::default();
+        second.insert(&[0x10; 32], value.clone()).unwrap();
+        second.insert(&[0x30; 32], vec![0x43; 40]).unwrap();
+        let storage = HashMap::from_iter([
+            (Address::with_last_byte(1), (first, vec![])),
+            (Address::with_last_byte(2), (second, vec![])),
+        ]);
+        let tries = Tries {
+            trie: MptNode::default(),
+            storage,
+        };
+
+        // The identical leaves are deserialized as a single node, hashed at most once.
+        let encoded = bincode::serialize(&tries).unwrap();
+        let decoded: Tries = bincode::deserialize(&encoded).unwrap();
+        let leaves = [1, 2].map(|byte| {
+            let (trie, _) = &decoded.storage[&Address::with_last_byte(byte)];
+            let MptNodeData::Branch(children) = trie.as_data() else {
+                panic!("root is not a branch");
+            };
+            children[1].clone().unwrap()
+        });
+        assert!(Arc::ptr_eq(&leaves[0], &leaves[1]));
+    }
+
+    #[test]
+    fn test_invalid_reference() {
+        // A branch referencing itself.
+        let mut children = [None; 16];
+        children[0] = Some(0);
+        let store = NodeStore {
+            nodes: CompactNodes(vec![StoredNode::Branch(children)]),
+            roots: 0u32,
+        };
+        let encoded = bincode::serialize(&store).unwrap();
+        assert!(bincode::deserialize::<Trie>(&encoded).is_err());
+    }
+
+    #[test]
+    fn test_compact_nodes() {
+        let mut children = [None; 16];
+        children[3] = Some(1);
+        children[15] = Some(2);
+        let nodes = CompactNodes(vec![
+            StoredNode::Null,
+            StoredNode::Leaf(vec![0x20, 0xab], vec![0x42; 200]),
+            StoredNode::Digest(B256::repeat_byte(7)),
+            StoredNode::Branch(children),
+            StoredNode::Extension(vec![0x1c], 3),
+        ]);
+        let encoded = bincode::serialize(&nodes).unwrap();
+        assert_eq!(
+            bincode::deserialize::<CompactNodes>(&encoded).unwrap(),
+            nodes
+        );
+        // Also through formats without a native byte string.
+        let json = serde_json::to_string(&nodes).unwrap();
+        assert_eq!(serde_json::from_str::<CompactNodes>(&json).unwrap(), nodes);
+
+        // Truncated nodes and unknown tags are rejected.
+        let bytes: Vec<u8> = bincode::deserialize(&encoded).unwrap();
+        assert!(decode_nodes(&bytes[..bytes.len() - 1]).is_err());
+        assert!(decode_nodes(&[DIGEST + 1]).is_err());
+        // A child after its parent is rejected.
+        assert!(decode_nodes(&[EXTENSION, 1, 0x1c, 1]).is_err());
+    }
+}
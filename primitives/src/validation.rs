@@ -0,0 +1,447 @@
+//! Validation of the header of a block against its parent and the consensus rules of its chain.
+//!
+//! The rules are a [BlockValidator] set on the [crate::processor::EvmProcessor], so that rollups
+//! and test harnesses can swap them without patching the processor.
+
+use crate::chain::Consensus;
+use crate::chain::Hardfork;
+use crate::pow;
+use crate::processor::{invalid_header, validate_base_fee, ProcessorError};
+#[cfg(feature = "polygon")]
+use crate::processor::{BOR_EXTRA_DATA_SEAL, BOR_EXTRA_DATA_VANITY};
+use crate::processor::{MAXIMUM_EXTRA_DATA_SIZE, MINIMUM_GAS_LIMIT};
+use crate::SP1RethInput;
+
+use reth_primitives::constants::EMPTY_OMMER_ROOT_HASH;
+use reth_primitives::{Header, U256};
+
+/// Validates the header of a block against its parent and the rules of its chain.
+pub trait BlockValidator: Send + Sync {
+    /// Validates the header derived from the input before the transactions are executed.
+    fn validate_header(&self, input: &SP1RethInput, header: &Header) -> Result<(), ProcessorError>;
+
+    /// Validates the header once the executed transactions filled in its gas fields.
+    fn validate_executed_header(
+        &self,
+        input: &SP1RethInput,
+        header: &Header,
+    ) -> Result<(), ProcessorError>;
+}
+
+/// The rules of Ethereum under beacon consensus, including the proof-of-work rules before the
+/// merge and the rules of Bor blocks when the `polygon` feature is enabled.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct EthereumBeaconValidator;
+
+impl BlockValidator for EthereumBeaconValidator {
+    fn validate_header(&self, input: &SP1RethInput, header: &Header) -> Result<(), ProcessorError> {
+        Self::validate_against_parent(input, header)?;
+        Self::validate_gas_limit(input, header)?;
+        Self::validate_header_extradata(input, header)?;
+        Self::validate_header_base_fee(input, header)?;
+        Self::validate_parent_beacon_block_root(input, header)?;
+        Self::validate_pow_fields(input, header)
+    }
+
+    fn validate_executed_header(
+        &self,
+        input: &SP1RethInput,
+        header: &Header,
+    ) -> Result<(), ProcessorError> {
+        Self::validate_blob_gas(input, header)?;
+        Self::validate_header_standalone(input, header)
+    }
+}
+
+impl EthereumBeaconValidator {
+    /// Validate the header standalone, once the gas used by the executed transactions is known.
+    ///
+    /// Reference: https://github.com/paradigmxyz/reth/blob/main/crates/consensus/common/src/validation.rs#L14
+    pub fn validate_header_standalone(
+        input: &SP1RethInput,
+        header: &Header,
+    ) -> Result<(), ProcessorError> {
+        // Gas used needs to be less then gas limit.
+        if header.gas_used > header.gas_limit {
+            return invalid_header("gas used exceeds gas limit");
+        }
+
+        // Gas used must match the gas used declared by the block, if any.
+        if let Some(expected_gas_used) = input.expected_gas_used {
+            if header.gas_used != expected_gas_used {
+                return invalid_header(format!(
+                    "gas used {} does not match the expected gas used {}",
+                    header.gas_used, expected_gas_used
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Validates the integrity and consistency of a block header in relation to it's parent.
+    ///
+    /// Reference: https://github.com/paradigmxyz/reth/blob/main/crates/primitives/src/header.rs#L800
+    pub fn validate_against_parent(
+        input: &SP1RethInput,
+        header: &Header,
+    ) -> Result<(), ProcessorError> {
+        let parent_header = &input.parent_header;
+
+        // Parent number is consistent.
+        if parent_header.number + 1 != header.number {
+            return invalid_header("parent number is inconsistent with header number");
+        }
+
+        // Parent hash is consistent.
+        if parent_header.hash_slow() != header.parent_hash {
+            return invalid_header("parent hash is inconsistent with header parent hash");
+        }
+
+        // Timestamp in past check.
+        if header.timestamp <= parent_header.timestamp {
+            return invalid_header("timestamp is not after the parent timestamp");
+        }
+        Ok(())
+    }
+
+    /// Checks the gas limit for consistency between parent and self headers.
+    ///
+    /// Reference: https://github.com/paradigmxyz/reth/blob/main/crates/primitives/src/header.rs#L738
+    pub fn validate_gas_limit(input: &SP1RethInput, header: &Header) -> Result<(), ProcessorError> {
+        // The gas limit of OP Stack blocks is set by the system config on L1 instead.
+        #[cfg(feature = "optimism")]
+        if input
+            .chain_config
+            .is_active(Hardfork::Bedrock, header.number, header.timestamp)
+        {
+            return Ok(());
+        }
+
+        // The London block doubles the gas limit of its parent, keeping the gas target the same.
+        let parent_header = &input.parent_header;
+        let is_london = |number, timestamp| {
+            input
+                .chain_config
+                .is_active(Hardfork::London, number, timestamp)
+        };
+        let mut parent_gas_limit = u128::from(parent_header.gas_limit);
+        if is_london(header.number, header.timestamp)
+            && !is_london(parent_header.number, parent_header.timestamp)
+        {
+            let params = input
+                .chain_config
+                .base_fee_params_at(header.number, header.timestamp);
+            parent_gas_limit *= u128::from(params.elasticity_multiplier);
+        }
+        let gas_limit = u128::from(header.gas_limit);
+
+        // Check for an increase in gas limit beyond the allowed threshold.
+        if gas_limit > parent_gas_limit {
+            if gas_limit - parent_gas_limit >= parent_gas_limit / 1024 {
+                return invalid_header("gas limit invalid increase");
+            }
+        }
+        // Check for a decrease in gas limit beyond the allowed threshold.
+        else if parent_gas_limit - gas_limit >= parent_gas_limit / 1024 {
+            return invalid_header("gas limit invalid decrease");
+        }
+        // Check if the gas limit is below the minimum required limit.
+        else if header.gas_limit < MINIMUM_GAS_LIMIT {
+            return invalid_header("gas limit below minimum");
+        }
+        Ok(())
+    }
+
+    /// Validates that the header has a parent beacon block root exactly when the hardfork
+    /// introducing it is active.
+    ///
+    /// Reference: https://eips.ethereum.org/EIPS/eip-4788
+    pub fn validate_parent_beacon_block_root(
+        input: &SP1RethInput,
+        header: &Header,
+    ) -> Result<(), ProcessorError> {
+        let is_cancun =
+            input
+                .chain_config
+                .has_header_fields(Hardfork::Cancun, header.number, header.timestamp);
+        match (is_cancun, header.parent_beacon_block_root) {
+            (true, None) => invalid_header("parent beacon block root is missing after Cancun"),
+            (false, Some(_)) => invalid_header("parent beacon block root is set before Cancun"),
+            _ => Ok(()),
+        }
+    }
+
+    /// Validates the proof-of-work fields and the ommers of the header. Blocks with ommers are
+    /// rejected before, by [crate::processor::EvmProcessor::validate_supported_block].
+    ///
+    /// Reference: https://github.com/paradigmxyz/reth/blob/main/crates/consensus/common/src/validation.rs
+    pub fn validate_pow_fields(
+        input: &SP1RethInput,
+        header: &Header,
+    ) -> Result<(), ProcessorError> {
+        // The difficulty of Bor blocks depends on the validator set, which is not part of the
+        // input, so only the fields unused by Bor are checked.
+        #[cfg(feature = "polygon")]
+        if input.chain_config.consensus == Consensus::Bor {
+            if header.nonce != 0 {
+                return invalid_header("nonce must be zero for Bor blocks");
+            }
+            if !input.ommers.is_empty() {
+                return invalid_header("ommers are not allowed for Bor blocks");
+            }
+            return Ok(());
+        }
+
+        let is_merged =
+            input
+                .chain_config
+                .is_active(Hardfork::Paris, header.number, header.timestamp);
+
+        if is_merged {
+            if header.difficulty != U256::ZERO {
+                return invalid_header("difficulty must be zero after the merge");
+            }
+            if header.nonce != 0 {
+                return invalid_header("nonce must be zero after the merge");
+            }
+            if !input.ommers.is_empty() {
+                return invalid_header("ommers are not allowed after the merge");
+            }
+            // The header hooks may override the ommers hash, so it is checked on its own.
+            if header.ommers_hash != EMPTY_OMMER_ROOT_HASH {
+                return invalid_header("ommers hash must be the empty list hash after the merge");
+            }
+            return Ok(());
+        }
+
+        let difficulty = pow::calc_difficulty(
+            &input.chain_config,
+            &input.parent_header,
+            header.number,
+            header.timestamp,
+        );
+        if header.difficulty != difficulty {
+            return invalid_header("difficulty is inconsistent with parent difficulty");
+        }
+        Ok(())
+    }
+
+    /// Validates the blob gas fields of the header against the parent and the blob gas the block
+    /// is expected to use.
+    ///
+    /// Reference: https://eips.ethereum.org/EIPS/eip-4844#header-extension
+    pub fn validate_blob_gas(input: &SP1RethInput, header: &Header) -> Result<(), ProcessorError> {
+        let parent_header = &input.parent_header;
+        let is_cancun =
+            input
+                .chain_config
+                .has_header_fields(Hardfork::Cancun, header.number, header.timestamp);
+        if !is_cancun {
+            if header.blob_gas_used.is_some() || header.excess_blob_gas.is_some() {
+                return invalid_header("blob gas fields are set before Cancun");
+            }
+            return Ok(());
+        }
+
+        let (Some(blob_gas_used), Some(excess_blob_gas)) =
+            (header.blob_gas_used, header.excess_blob_gas)
+        else {
+            return invalid_header("blob gas fields are missing after Cancun");
+        };
+        if let Some(expected_blob_gas_used) = input.expected_blob_gas_used {
+            if blob_gas_used != expected_blob_gas_used {
+                return invalid_header(format!(
+                    "blob gas used {} does not match the expected blob gas used {}",
+                    blob_gas_used, expected_blob_gas_used
+                ));
+            }
+        }
+        let blob_params = &input.chain_config.blob_params;
+        if blob_gas_used > blob_params.max_blob_gas_per_block() {
+            return invalid_header("blob gas used exceeds the block limit");
+        }
+        let expected_excess_blob_gas = blob_params.excess_blob_gas(
+            parent_header.excess_blob_gas.unwrap_or_default(),
+            parent_header.blob_gas_used.unwrap_or_default(),
+        );
+        if excess_blob_gas != expected_excess_blob_gas {
+            return invalid_header("excess blob gas is inconsistent with the parent header");
+        }
+        Ok(())
+    }
+
+    /// Validates the header's extradata according to the beacon consensus rules.
+    ///
+    /// Reference: https://github.com/paradigmxyz/reth/blob/main/crates/consensus/beacon-core/src/lib.rs#L118
+    pub fn validate_header_extradata(
+        input: &SP1RethInput,
+        header: &Header,
+    ) -> Result<(), ProcessorError> {
+        match input.chain_config.consensus {
+            Consensus::Ethereum => {
+                if header.extra_data.len() > MAXIMUM_EXTRA_DATA_SIZE {
+                    return invalid_header("extra data too large");
+                }
+            }
+            #[cfg(feature = "polygon")]
+            Consensus::Bor => {
+                if header.extra_data.len() < BOR_EXTRA_DATA_VANITY + BOR_EXTRA_DATA_SEAL {
+                    return invalid_header("extra data too short for the Bor seal");
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Validates the base fee of the header against the gas usage of the parent.
+    pub fn validate_header_base_fee(
+        input: &SP1RethInput,
+        header: &Header,
+    ) -> Result<(), ProcessorError> {
+        let is_london =
+            input
+                .chain_config
+                .is_active(Hardfork::London, header.number, header.timestamp);
+        validate_base_fee(
+            &input.parent_header,
+            header,
+            input
+                .chain_config
+                .base_fee_params_at(header.number, header.timestamp),
+            is_london,
+        )?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::processor::tests::empty_block_input;
+    use reth_primitives::{Bytes, B256};
+    use revm::primitives::GAS_PER_BLOB;
+
+    /// Returns the valid header of the block of the input, before execution.
+    fn header(input: &SP1RethInput) -> Header {
+        Header {
+            parent_hash: input.parent_header.hash_slow(),
+            number: input.parent_header.number + 1,
+            timestamp: input.timestamp,
+            gas_limit: input.gas_limit,
+            base_fee_per_gas: input.base_fee_per_gas,
+            excess_blob_gas: input.excess_blob_gas,
+            ommers_hash: EMPTY_OMMER_ROOT_HASH,
+            ..Default::default()
+        }
+    }
+
+    /// Returns whether the validator rejects the valid header of the input once modified.
+    fn rejects(input: &SP1RethInput, modify: impl FnOnce(&mut Header)) -> bool {
+        let mut header = header(input);
+        modify(&mut header);
+        matches!(
+            EthereumBeaconValidator.validate_header(input, &header),
+            Err(ProcessorError::InvalidHeader { .. } | ProcessorError::InvalidBaseFee(_))
+        )
+    }
+
+    #[test]
+    fn test_validate_header() {
+        let input = empty_block_input(16_000_000, 1_668_000_000);
+        assert!(!rejects(&input, |_| ()));
+
+        // The header must follow its parent.
+        assert!(rejects(&input, |header| header.number += 1));
+        assert!(rejects(&input, |header| header.parent_hash = B256::ZERO));
+        assert!(rejects(&input, |header| {
+            header.timestamp = input.parent_header.timestamp
+        }));
+
+        // The extra data of Ethereum blocks is at most 32 bytes.
+        assert!(rejects(&input, |header| {
+            header.extra_data = Bytes::from(vec![0; MAXIMUM_EXTRA_DATA_SIZE + 1])
+        }));
+
+        // The base fee follows from the parent.
+        assert!(rejects(&input, |header| {
+            header.base_fee_per_gas = header.base_fee_per_gas.map(|base_fee| base_fee + 1)
+        }));
+
+        // Blocks after the merge have no proof-of-work fields.
+        assert!(rejects(&input, |header| header.difficulty = U256::from(1)));
+        assert!(rejects(&input, |header| header.nonce = 1));
+        assert!(rejects(&input, |header| header.ommers_hash = B256::ZERO));
+
+        // The parent beacon block root only exists after Cancun.
+        assert!(rejects(&input, |header| {
+            header.parent_beacon_block_root = Some(B256::ZERO)
+        }));
+    }
+
+    #[test]
+    fn test_validate_gas_limit() {
+        let accepts = |input: &SP1RethInput, gas_limit| {
+            let header = Header {
+                gas_limit,
+                ..header(input)
+            };
+            EthereumBeaconValidator::validate_gas_limit(input, &header).is_ok()
+        };
+
+        // The gas limit changes by less than 1/1024 of the parent gas limit.
+        let input = empty_block_input(16_000_000, 1_668_000_000);
+        let parent_gas_limit = input.parent_header.gas_limit;
+        let max_delta = parent_gas_limit / 1024 - 1;
+        assert!(accepts(&input, parent_gas_limit + max_delta));
+        assert!(accepts(&input, parent_gas_limit - max_delta));
+        assert!(!accepts(&input, parent_gas_limit + max_delta + 1));
+        assert!(!accepts(&input, parent_gas_limit - max_delta - 1));
+
+        // The London block doubles the gas limit of its parent.
+        let input = empty_block_input(12_965_000, 1_628_166_822);
+        let parent_gas_limit = input.parent_header.gas_limit;
+        assert!(accepts(&input, parent_gas_limit * 2));
+        assert!(!accepts(&input, parent_gas_limit));
+    }
+
+    #[test]
+    fn test_validate_executed_header() {
+        let rejects = |input: &SP1RethInput, modify: fn(&mut Header)| {
+            let mut header = header(input);
+            modify(&mut header);
+            EthereumBeaconValidator
+                .validate_executed_header(input, &header)
+                .is_err()
+        };
+        let input = empty_block_input(16_000_000, 1_668_000_000);
+        assert!(!rejects(&input, |_| ()));
+
+        // The gas used must match the expected gas used and stay within the gas limit.
+        assert!(rejects(&input, |header| header.gas_used = 1));
+        let input = SP1RethInput {
+            expected_gas_used: None,
+            ..input
+        };
+        assert!(rejects(&input, |header| header.gas_used = header.gas_limit + 1));
+
+        // Blob gas fields do not exist before Cancun.
+        assert!(rejects(&input, |header| header.blob_gas_used = Some(0)));
+
+        // After Cancun, the blob gas used must match the expected blob gas used, and the excess
+        // blob gas must follow from the parent.
+        let input = SP1RethInput {
+            excess_blob_gas: Some(0),
+            expected_blob_gas_used: Some(GAS_PER_BLOB),
+            ..empty_block_input(19_500_000, 1_712_000_000)
+        };
+        assert!(!rejects(&input, |header| {
+            header.blob_gas_used = Some(GAS_PER_BLOB)
+        }));
+        assert!(rejects(&input, |header| header.blob_gas_used = Some(0)));
+        assert!(rejects(&input, |header| header.blob_gas_used = None));
+        assert!(rejects(&input, |header| {
+            header.blob_gas_used = Some(GAS_PER_BLOB);
+            header.excess_blob_gas = Some(GAS_PER_BLOB);
+        }));
+    }
+}
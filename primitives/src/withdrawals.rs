@@ -0,0 +1,257 @@
+//! A validated list of the consensus layer withdrawals of a block.
+
+use reth_primitives::{Bytes, Withdrawal, U256};
+use serde::{Deserialize, Serialize};
+use std::ops::Deref;
+use thiserror::Error as ThisError;
+
+/// The maximum number of withdrawals in an execution payload.
+///
+/// Reference: https://github.com/ethereum/consensus-specs/blob/dev/specs/capella/beacon-chain.md
+pub const MAX_WITHDRAWALS_PER_PAYLOAD: usize = 16;
+
+/// The maximum number of validators in the beacon state, which bounds the validator indices.
+///
+/// Reference: https://github.com/ethereum/consensus-specs/blob/dev/specs/phase0/beacon-chain.md
+pub const VALIDATOR_REGISTRY_LIMIT: u64 = 1 << 40;
+
+/// The number of withdrawals that failed in earlier blocks the withdrawals contract of Gnosis retries
+/// in every block.
+///
+/// Reference: https://github.com/gnosischain/specs/blob/master/execution/withdrawals.md
+pub const MAX_FAILED_WITHDRAWALS_TO_PROCESS: u64 = 4;
+
+/// The selector of `executeSystemWithdrawals(uint256,uint64[],address[])`.
+pub const EXECUTE_SYSTEM_WITHDRAWALS_SELECTOR: [u8; 4] = [0x79, 0xd0, 0xc0, 0xbc];
+
+/// Represents the reasons a list of withdrawals can be malformed.
+#[derive(Debug, PartialEq, Eq, ThisError)]
+pub enum WithdrawalsError {
+    /// Triggered when the list contains more withdrawals than a payload can hold.
+    #[error("too many withdrawals: {0}")]
+    TooMany(usize),
+
+    /// Triggered when the global index of a withdrawal does not follow the previous one.
+    #[error("withdrawal index {index} does not follow {previous}")]
+    NonConsecutiveIndex { previous: u64, index: u64 },
+
+    /// Triggered when a withdrawal refers to a validator outside of the registry limit.
+    #[error("validator index {0} is out of range")]
+    ValidatorIndexOutOfRange(u64),
+
+    /// Triggered when a withdrawal has a zero amount, which the consensus layer never withdraws.
+    #[error("withdrawal {0} has a zero amount")]
+    ZeroAmount(u64),
+
+    /// Triggered when the amounts of the withdrawals sum to more gwei than a balance of the
+    /// consensus layer can hold.
+    #[error("the total amount of the withdrawals overflows")]
+    TotalAmountOverflow,
+}
+
+/// The consensus layer withdrawals of a block.
+///
+/// The list is validated when it is constructed on the host and again when it is deserialized in
+/// the guest, so a malformed list can never silently produce a wrong withdrawals root.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "Vec<Withdrawal>", into = "Vec<Withdrawal>")]
+pub struct Withdrawals(Vec<Withdrawal>);
+
+impl Withdrawals {
+    /// Validates the given withdrawals.
+    pub fn new(withdrawals: Vec<Withdrawal>) -> Result<Self, WithdrawalsError> {
+        if withdrawals.len() > MAX_WITHDRAWALS_PER_PAYLOAD {
+            return Err(WithdrawalsError::TooMany(withdrawals.len()));
+        }
+        for pair in withdrawals.windows(2) {
+            if pair[0].index.checked_add(1) != Some(pair[1].index) {
+                return Err(WithdrawalsError::NonConsecutiveIndex {
+                    previous: pair[0].index,
+                    index: pair[1].index,
+                });
+            }
+        }
+        if let Some(withdrawal) = withdrawals
+            .iter()
+            .find(|w| w.validator_index >= VALIDATOR_REGISTRY_LIMIT)
+        {
+            return Err(WithdrawalsError::ValidatorIndexOutOfRange(
+                withdrawal.validator_index,
+            ));
+        }
+        if let Some(withdrawal) = withdrawals.iter().find(|w| w.amount == 0) {
+            return Err(WithdrawalsError::ZeroAmount(withdrawal.index));
+        }
+        // The consensus layer holds balances as 64-bit amounts of gwei.
+        withdrawals
+            .iter()
+            .try_fold(0u64, |total, w| total.checked_add(w.amount))
+            .ok_or(WithdrawalsError::TotalAmountOverflow)?;
+        Ok(Self(withdrawals))
+    }
+
+    /// Returns the withdrawals as a slice.
+    pub fn as_slice(&self) -> &[Withdrawal] {
+        &self.0
+    }
+}
+
+impl Deref for Withdrawals {
+    type Target = [Withdrawal];
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl TryFrom<Vec<Withdrawal>> for Withdrawals {
+    type Error = WithdrawalsError;
+
+    fn try_from(withdrawals: Vec<Withdrawal>) -> Result<Self, Self::Error> {
+        Self::new(withdrawals)
+    }
+}
+
+impl From<Withdrawals> for Vec<Withdrawal> {
+    fn from(withdrawals: Withdrawals) -> Self {
+        withdrawals.0
+    }
+}
+
+/// Encodes the call of `executeSystemWithdrawals(uint256 maxFailedWithdrawalsToProcess, uint64[]
+/// amounts, address[] addresses)` through which the withdrawals contract of Gnosis credits the
+/// withdrawals, with their amounts in gwei.
+pub fn system_withdrawals_call(withdrawals: &[Withdrawal]) -> Bytes {
+    let word = |value: u64| U256::from(value).to_be_bytes::<32>();
+    let len = withdrawals.len() as u64;
+    let mut out = Vec::with_capacity(4 + 32 * (5 + 2 * withdrawals.len()));
+    out.extend_from_slice(&EXECUTE_SYSTEM_WITHDRAWALS_SELECTOR);
+    out.extend_from_slice(&word(MAX_FAILED_WITHDRAWALS_TO_PROCESS));
+    // The offsets of the two arrays, which follow the three head words.
+    out.extend_from_slice(&word(3 * 32));
+    out.extend_from_slice(&word((4 + len) * 32));
+    out.extend_from_slice(&word(len));
+    for withdrawal in withdrawals {
+        out.extend_from_slice(&word(withdrawal.amount));
+    }
+    out.extend_from_slice(&word(len));
+    for withdrawal in withdrawals {
+        out.extend_from_slice(withdrawal.address.into_word().as_slice());
+    }
+    out.into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mpt::keccak;
+    use reth_primitives::Address;
+
+    /// Returns consecutive withdrawals starting at the given index.
+    fn withdrawals(start: u64, count: u64) -> Vec<Withdrawal> {
+        (start..start + count)
+            .map(|index| Withdrawal {
+                index,
+                validator_index: index,
+                address: Address::repeat_byte(1),
+                amount: 1_000_000_000,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_new() {
+        assert!(Withdrawals::new(Vec::new()).is_ok());
+        let valid = Withdrawals::new(withdrawals(100, 16)).unwrap();
+        assert_eq!(valid.len(), 16);
+
+        // At most 16 withdrawals fit in a payload.
+        assert_eq!(
+            Withdrawals::new(withdrawals(100, 17)),
+            Err(WithdrawalsError::TooMany(17))
+        );
+
+        // The indices are consecutive.
+        let mut gap = withdrawals(100, 2);
+        gap[1].index = 102;
+        assert_eq!(
+            Withdrawals::new(gap),
+            Err(WithdrawalsError::NonConsecutiveIndex {
+                previous: 100,
+                index: 102
+            })
+        );
+
+        // Validator indices stay within the registry limit.
+        let mut out_of_range = withdrawals(100, 1);
+        out_of_range[0].validator_index = VALIDATOR_REGISTRY_LIMIT;
+        assert_eq!(
+            Withdrawals::new(out_of_range),
+            Err(WithdrawalsError::ValidatorIndexOutOfRange(
+                VALIDATOR_REGISTRY_LIMIT
+            ))
+        );
+
+        // Amounts are positive and sum to at most the largest balance in gwei.
+        let mut zero = withdrawals(100, 2);
+        zero[1].amount = 0;
+        assert_eq!(
+            Withdrawals::new(zero),
+            Err(WithdrawalsError::ZeroAmount(101))
+        );
+        let mut overflow = withdrawals(100, 2);
+        overflow[0].amount = u64::MAX;
+        assert_eq!(
+            Withdrawals::new(overflow),
+            Err(WithdrawalsError::TotalAmountOverflow)
+        );
+    }
+
+    #[test]
+    fn test_deserialize() {
+        // The guest validates the withdrawals again when deserializing them.
+        let valid = Withdrawals::new(withdrawals(100, 2)).unwrap();
+        let encoded = bincode::serialize(&valid).unwrap();
+        assert_eq!(
+            bincode::deserialize::<Withdrawals>(&encoded).unwrap(),
+            valid
+        );
+        let mut gap = withdrawals(100, 2);
+        gap[1].index = 102;
+        let encoded = bincode::serialize(&gap).unwrap();
+        assert!(bincode::deserialize::<Withdrawals>(&encoded).is_err());
+    }
+
+    #[test]
+    fn test_system_withdrawals_call() {
+        let signature = "executeSystemWithdrawals(uint256,uint64[],address[])";
+        assert_eq!(
+            EXECUTE_SYSTEM_WITHDRAWALS_SELECTOR,
+            keccak(signature.as_bytes())[..4]
+        );
+
+        let withdrawals = [1, 2].map(|i| Withdrawal {
+            index: i,
+            validator_index: i,
+            address: Address::repeat_byte(i as u8),
+            amount: 1_000 * i,
+        });
+        let data = system_withdrawals_call(&withdrawals);
+        let words: Vec<_> = data[4..].chunks(32).map(U256::from_be_slice).collect();
+        let addresses = withdrawals.map(|w| U256::from_be_slice(w.address.as_slice()));
+        assert_eq!(
+            words,
+            [
+                U256::from(MAX_FAILED_WITHDRAWALS_TO_PROCESS),
+                U256::from(96),
+                U256::from(192),
+                U256::from(2),
+                U256::from(1_000),
+                U256::from(2_000),
+                U256::from(2),
+                addresses[0],
+                addresses[1],
+            ]
+        );
+    }
+}
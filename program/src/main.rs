@@ -9,29 +9,170 @@ sp1_zkvm::entrypoint!(main);
 
 use reth_primitives::B256;
 use revm::InMemoryDB;
-use sp1_reth_primitives::db::InMemoryDBHelper;
+use sp1_reth_primitives::abort::{AbortPhase, AbortRecord, InvalidityRecord};
+use sp1_reth_primitives::db::{DbError, InMemoryDBHelper, WitnessDb};
 use sp1_reth_primitives::mpt::keccak;
-use sp1_reth_primitives::processor::EvmProcessor;
+use sp1_reth_primitives::oracle::BlockCommitment;
+use sp1_reth_primitives::processor::{state_root, transactions_root, EvmProcessor, ProcessorError};
+use sp1_reth_primitives::public_values::{
+    BlockStatus, SP1RethPublicValues, TransactionRange, FLAG_BLOCK_ORACLE,
+    FLAG_INTERMEDIATE_STATE_ROOTS, FLAG_RECEIPTS, FLAG_SENDER_HINTS, FLAG_TRANSACTION_RANGE,
+    PUBLIC_VALUES_MARKER, PUBLIC_VALUES_VERSION,
+};
+use sp1_reth_primitives::senders::sender_hints_hash;
 use sp1_reth_primitives::SP1RethInput;
 
+/// The marker the script looks for to check that this ELF commits the current public values.
+#[used]
+static MARKER: [u8; 32] = PUBLIC_VALUES_MARKER;
+
 fn main() {
+    // Keep the marker in the ELF.
+    core::hint::black_box(&MARKER);
+
     // Read the input.
     let mut input = sp1_zkvm::io::read::<SP1RethInput>();
 
     // Initialize the database.
-    let db = InMemoryDB::initialize(&mut input).unwrap();
+    let db = match InMemoryDB::initialize(&mut input) {
+        Ok(db) => db,
+        Err(err) => {
+            let code = err.downcast_ref::<DbError>().map_or(0, DbError::code);
+            abort(AbortRecord::new(
+                &input,
+                AbortPhase::Initialize,
+                code,
+                &err.to_string(),
+            ));
+        }
+    };
 
-    // Execute the block.
-    let mut executor = EvmProcessor::<InMemoryDB> {
-        input,
-        db: Some(db),
-        header: None,
+    // Execute the block, failing on every read the witness does not cover.
+    let mut executor = EvmProcessor::new(input, WitnessDb(db))
+        .with_checkpoint_state_root(|input, db| state_root(input, &db.0));
+    if let Err(err) = executor.initialize() {
+        return fail(&executor.input, AbortPhase::Initialize, err);
+    }
+    if let Err(err) = executor.execute() {
+        return fail(&executor.input, AbortPhase::Execute, err);
+    }
+    if let Err(err) = executor.finalize() {
+        return fail(&executor.input, AbortPhase::Finalize, err);
+    }
+
+    // Commit the versioned public values of the block as raw ABI bytes, without the length
+    // prefix of the serialization of the zkVM, so that verifiers can decode them directly. Their
+    // flags announce the optional outputs committed after them, in the order of their bits.
+    let header = executor.header.as_ref().unwrap();
+    let hash = B256::from(keccak(alloy_rlp::encode(header)));
+    let public_values = SP1RethPublicValues {
+        version: PUBLIC_VALUES_VERSION,
+        status: BlockStatus::Valid,
+        flags: SP1RethPublicValues::flags(&executor.input),
+        chain_id: executor.input.chain_config.chain_id,
+        block_number: header.number,
+        parent_hash: header.parent_hash,
+        block_hash: hash,
+        state_root: header.state_root,
     };
-    executor.initialize();
-    executor.execute();
-    executor.finalize();
+    sp1_zkvm::io::write_slice(&public_values.abi_encode());
+
+    // Commit the executed transactions and the state roots before and after them if only part of
+    // the block was executed.
+    if public_values.has(FLAG_TRANSACTION_RANGE) {
+        let transactions_root = if executor.input.execution_filter.is_none() {
+            header.transactions_root
+        } else {
+            transactions_root(&executor.input.transactions)
+        };
+        let tx_count = executor.input.transactions.len() as u32;
+        sp1_zkvm::io::write(&TransactionRange {
+            transactions_root,
+            start: executor.input.transaction_start.unwrap_or_default(),
+            end: executor.input.transaction_limit.unwrap_or(tx_count),
+            start_state_root: executor.input.parent_state_trie.hash(),
+            state_root: header.state_root,
+        });
+    }
+
+    // Commit the compact receipts if they were requested.
+    if public_values.has(FLAG_RECEIPTS) {
+        sp1_zkvm::io::write(executor.compact_receipts.as_ref().unwrap());
+    }
+
+    // Commit the filter and the transactions it skipped if the block was executed with a filter.
+    if let Some(filter) = &executor.input.execution_filter {
+        sp1_zkvm::io::write(filter);
+        sp1_zkvm::io::write(&executor.skipped_transactions);
+    }
+
+    // Commit the intermediate state roots if a state root interval was requested.
+    if public_values.has(FLAG_INTERMEDIATE_STATE_ROOTS) {
+        sp1_zkvm::io::write(&executor.intermediate_state_roots);
+    }
+
+    // Commit the block in the ABI of block-hash oracles if it was requested, as raw ABI bytes.
+    if public_values.has(FLAG_BLOCK_ORACLE) {
+        let commitment = BlockCommitment {
+            block_number: header.number,
+            block_hash: hash,
+            state_root: header.state_root,
+        };
+        sp1_zkvm::io::write_slice(&commitment.abi_encode());
+    }
+
+    // Commit the hash of the sender hints used if any were supplied.
+    if public_values.has(FLAG_SENDER_HINTS) {
+        sp1_zkvm::io::write(&sender_hints_hash(&executor.used_sender_hints));
+    }
+
+    // Commit the overrides of the block environment if the block was simulated with any.
+    if let Some(overrides) = &executor.input.block_overrides {
+        sp1_zkvm::io::write(overrides);
+    }
 
     // Print the resulting block hash.
-    let hash = B256::from(keccak(alloy_rlp::encode(executor.header.unwrap())));
     println!("block hash: {}", hash);
 }
+
+/// Aborts the program with the record, so that no proof exists for a block it failed to execute.
+fn abort(record: AbortRecord) -> ! {
+    panic!("aborting: {:?}", record);
+}
+
+/// Commits the public values of an invalid block followed by the consensus rule it violates if its
+/// invalidity is to be proven, and aborts otherwise.
+fn fail(input: &SP1RethInput, phase: AbortPhase, err: ProcessorError) {
+    let record = AbortRecord::new(input, phase, err.code(), &err.to_string());
+    // The claimed header was checked against the input when the block was initialized.
+    let claimed_header = input
+        .claimed_header
+        .as_ref()
+        .filter(|_| input.prove_invalid && input.proves_block() && err.is_consensus_violation());
+    let Some(claimed_header) = claimed_header else {
+        abort(record);
+    };
+    let invalidity = InvalidityRecord {
+        parent_hash: record.parent_hash,
+        block_number: record.block_number,
+        block_hash: claimed_header.hash_slow(),
+        transactions_root: claimed_header.transactions_root,
+        phase,
+        code: record.code,
+        transaction_index: err.transaction_index(),
+        detail_hash: record.detail_hash,
+    };
+    println!("invalid block: {:?}", invalidity);
+    let public_values = SP1RethPublicValues {
+        version: PUBLIC_VALUES_VERSION,
+        status: BlockStatus::Invalid,
+        flags: 0,
+        chain_id: input.chain_config.chain_id,
+        block_number: invalidity.block_number,
+        parent_hash: invalidity.parent_hash,
+        block_hash: invalidity.block_hash,
+        state_root: B256::ZERO,
+    };
+    sp1_zkvm::io::write_slice(&public_values.abi_encode());
+    sp1_zkvm::io::write(&invalidity);
+}
@@ -0,0 +1,74 @@
+use crate::cache::{self, WitnessMetadata};
+use alloy_providers::provider::{HttpProvider, TempProvider};
+use alloy_transport_http::Http;
+use anyhow::Result;
+use reth_primitives::B256;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::path::Path;
+use url::Url;
+
+/// A record, saved next to a proof, of whether the block hash committed by the proof matched the
+/// canonical chain of a provider when the proof was generated.
+///
+/// The attestation is a convenience for consumers and is not part of the proven statement: it
+/// only reflects the chain served by the provider at the time of the check.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CanonicalAttestation {
+    /// The number of the proven block.
+    pub block_number: u64,
+
+    /// The block hash committed by the proof.
+    pub committed_block_hash: B256,
+
+    /// The hash of the canonical block at that number, or [None] if the provider does not know
+    /// the block.
+    pub canonical_block_hash: Option<B256>,
+
+    /// Whether the committed block hash is the canonical one.
+    pub matches_canonical: bool,
+
+    /// The Keccak-256 hash of the RPC url the canonical block hash was fetched from.
+    pub rpc_url_hash: B256,
+
+    /// The client version reported by the provider through `web3_clientVersion`, if any.
+    pub client_version: Option<String>,
+
+    /// The unix timestamp (in seconds) at which the canonical block hash was fetched.
+    pub checked_at: u64,
+}
+
+impl CanonicalAttestation {
+    /// Fetches the canonical hash of the block from the provider and compares it to the hash
+    /// committed by the proof.
+    pub async fn fetch(
+        rpc_url: &str,
+        block_number: u64,
+        committed_block_hash: B256,
+    ) -> Result<Self> {
+        let http = Http::new(Url::parse(rpc_url)?);
+        let provider: HttpProvider = HttpProvider::new(http);
+        let block = provider
+            .get_block_by_number(block_number.into(), false)
+            .await?;
+        let canonical_block_hash = block
+            .and_then(|block| block.header.hash)
+            .map(|hash| B256::from(hash.0));
+        Ok(Self {
+            block_number,
+            committed_block_hash,
+            canonical_block_hash,
+            matches_canonical: canonical_block_hash == Some(committed_block_hash),
+            rpc_url_hash: WitnessMetadata::hash_rpc_url(rpc_url),
+            client_version: cache::fetch_client_version(rpc_url).await,
+            checked_at: cache::unix_timestamp(),
+        })
+    }
+
+    /// Saves the attestation as JSON to the given path.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let file = File::create(path)?;
+        serde_json::to_writer_pretty(file, self)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,125 @@
+use crate::init::SP1RethInputInitializer;
+use crate::SP1RethArgs;
+use alloy_providers::provider::{HttpProvider, TempProvider};
+use alloy_transport_http::Http;
+use anyhow::{anyhow, bail, Result};
+use clap::Args;
+use reth_primitives::B256;
+use revm::InMemoryDB;
+use sp1_reth_primitives::db::InMemoryDBHelper;
+use sp1_reth_primitives::processor::{EvmProcessor, ProcessorError};
+use sp1_reth_primitives::SP1RethInput;
+use url::Url;
+
+/// The CLI arguments for bisecting a range of blocks.
+#[derive(Args, Debug, Clone)]
+pub struct BisectArgs {
+    #[arg(short, long)]
+    rpc_url: String,
+
+    /// The name of the chain profile the blocks belong to.
+    #[arg(long, default_value = "mainnet")]
+    chain: String,
+
+    /// The first block of the range, which is expected to execute correctly.
+    #[arg(long)]
+    from: u64,
+
+    /// The last block of the range, which is expected to diverge from the chain.
+    #[arg(long)]
+    to: u64,
+}
+
+/// Finds the earliest block in the range whose natively executed block hash diverges from the
+/// chain, assuming that every block after a diverging block diverges as well.
+///
+/// Returns [None] if the last block of the range does not diverge.
+pub async fn bisect(args: &BisectArgs) -> Result<Option<u64>> {
+    if args.from > args.to {
+        bail!("invalid range: {} is after {}", args.from, args.to);
+    }
+    if !diverges(args, args.to).await? {
+        return Ok(None);
+    }
+
+    // Invariant: `high` diverges and every block before `low` executes correctly.
+    let (mut low, mut high) = (args.from, args.to);
+    while low < high {
+        let mid = low + (high - low) / 2;
+        if diverges(args, mid).await? {
+            high = mid;
+        } else {
+            low = mid + 1;
+        }
+    }
+    Ok(Some(high))
+}
+
+/// Executes the block natively, the same way the program does, and returns whether the resulting
+/// block hash differs from the one of the chain.
+///
+/// Errors fetching the block or its state are returned rather than counted as a divergence, since
+/// they say nothing about the block and would misdirect the search.
+async fn diverges(bisect_args: &BisectArgs, block_number: u64) -> Result<bool> {
+    let http = Http::new(Url::parse(&bisect_args.rpc_url)?);
+    let provider: HttpProvider = HttpProvider::new(http);
+    let expected: B256 = provider
+        .get_block_by_number(block_number.into(), false)
+        .await?
+        .ok_or_else(|| anyhow!("block {} not found", block_number))?
+        .header
+        .hash
+        .ok_or_else(|| anyhow!("block {} has no hash", block_number))?;
+
+    // Failing to execute the block while building the witness means the host already diverged.
+    let args = SP1RethArgs::new(
+        bisect_args.rpc_url.clone(),
+        bisect_args.chain.clone(),
+        block_number,
+    );
+    let input = match SP1RethInput::initialize(&args).await {
+        Ok(input) => input,
+        Err(err) if is_execution_error(&err) => {
+            println!("block {}: failed to build witness: {}", block_number, err);
+            return Ok(true);
+        }
+        Err(err) => return Err(err),
+    };
+
+    let diverges = match tokio::task::spawn_blocking(move || execute_natively(input)).await? {
+        Ok(hash) if hash == expected => false,
+        Ok(hash) => {
+            println!(
+                "block {}: computed hash {} but expected {}",
+                block_number, hash, expected
+            );
+            true
+        }
+        Err(err) if is_execution_error(&err) => {
+            println!("block {}: execution failed: {}", block_number, err);
+            true
+        }
+        Err(err) => return Err(err),
+    };
+    if !diverges {
+        println!("block {}: ok", block_number);
+    }
+    Ok(diverges)
+}
+
+/// Returns whether the error was raised while executing the block, rather than while reading
+/// the state it executes on.
+fn is_execution_error(err: &anyhow::Error) -> bool {
+    err.downcast_ref::<ProcessorError>()
+        .is_some_and(|err| !matches!(err, ProcessorError::Database { .. }))
+}
+
+/// Executes the block against the witness and returns the hash of the resulting header.
+fn execute_natively(mut input: SP1RethInput) -> Result<B256> {
+    let db = InMemoryDB::initialize(&mut input)?;
+    let mut executor = EvmProcessor::new(input, db);
+    executor.initialize()?;
+    executor.execute()?;
+    executor.finalize()?;
+    Ok(executor.header.unwrap().hash_slow())
+}
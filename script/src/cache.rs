@@ -0,0 +1,230 @@
+use alloy_providers::provider::{HttpProvider, TempProvider};
+use alloy_rpc_types::EIP1186AccountProofResponse;
+use alloy_transport_http::Http;
+use anyhow::{Context, Result};
+use reth_primitives::{Address, B256};
+use serde::{Deserialize, Serialize};
+use sp1_reth_primitives::mpt::keccak;
+use sp1_reth_primitives::SP1RethInput;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+use url::Url;
+
+/// Provenance information describing where a cached input was fetched from.
+///
+/// The metadata is only ever stored alongside the input on disk and is never passed to the
+/// guest program, so it does not influence anything committed by the proof.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WitnessMetadata {
+    /// The Keccak-256 hash of the RPC url the witness was fetched from. The url itself is not
+    /// stored, since it commonly embeds an API key.
+    pub rpc_url_hash: B256,
+
+    /// The unix timestamp (in seconds) at which fetching the witness started.
+    pub fetch_started_at: u64,
+
+    /// The unix timestamp (in seconds) at which fetching the witness finished.
+    pub fetch_finished_at: u64,
+
+    /// The client version reported by the provider through `web3_clientVersion`, if any.
+    pub client_version: Option<String>,
+}
+
+impl WitnessMetadata {
+    /// Hashes the given RPC url so that it can be recorded without leaking secrets.
+    pub fn hash_rpc_url(rpc_url: &str) -> B256 {
+        keccak(rpc_url.as_bytes()).into()
+    }
+}
+
+/// Returns the current unix timestamp in seconds.
+pub fn unix_timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("system time is before the unix epoch")
+        .as_secs()
+}
+
+/// Queries the client version of the provider behind the given RPC url.
+///
+/// Providers are not required to support `web3_clientVersion`, so failures are mapped to [None].
+pub async fn fetch_client_version(rpc_url: &str) -> Option<String> {
+    let http = Http::new(Url::parse(rpc_url).ok()?);
+    let provider: HttpProvider = HttpProvider::new(http);
+    provider
+        .raw_request::<(), String>("web3_clientVersion", ())
+        .await
+        .ok()
+}
+
+/// Writes the input to the cache file, followed by the optional metadata section.
+pub fn save_input(
+    path: impl AsRef<Path>,
+    input: &SP1RethInput,
+    metadata: Option<&WitnessMetadata>,
+) -> Result<()> {
+    let mut file = File::create(path)?;
+    bincode::serialize_into(&mut file, input)?;
+    bincode::serialize_into(&mut file, &metadata)?;
+    Ok(())
+}
+
+/// Reads an input and its metadata section from the cache file.
+///
+/// Cache files written before the metadata section was introduced are still accepted, in which
+/// case no metadata is returned. Inputs written by versions with a different layout of
+/// [SP1RethInput] cannot be decoded, and must be fetched again.
+pub fn load_input(path: impl AsRef<Path>) -> Result<(SP1RethInput, Option<WitnessMetadata>)> {
+    let mut file = File::open(path)?;
+    let input: SP1RethInput = bincode::deserialize_from(&mut file)
+        .context("unable to decode the cached input, fetch it again without --use-cache")?;
+    let mut section = Vec::new();
+    file.read_to_end(&mut section)?;
+    if section.is_empty() {
+        return Ok((input, None));
+    }
+    Ok((input, bincode::deserialize(&section)?))
+}
+
+/// A proof of an account and some of its storage slots, fetched while building a witness.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CheckpointEntry {
+    /// The number of the block whose state was proven.
+    block_number: u64,
+
+    /// The address of the account.
+    address: Address,
+
+    /// The storage keys that were requested.
+    keys: Vec<B256>,
+
+    /// The proof returned by the provider.
+    proof: EIP1186AccountProofResponse,
+}
+
+/// A file of the storage proofs fetched while building a witness, so that fetching can resume
+/// where it stopped when the provider fails partway, for example because of rate limits.
+///
+/// Every proof is appended as one JSON object per line as soon as it is fetched. An incomplete
+/// last line, left by an interrupted write, is truncated when the file is opened again, so that
+/// the proofs appended afterwards start on a line of their own.
+pub struct ProofCheckpoint {
+    /// The proofs fetched so far.
+    entries: Vec<CheckpointEntry>,
+
+    /// The file the proofs are appended to.
+    file: File,
+}
+
+impl ProofCheckpoint {
+    /// Opens the checkpoint at the given path, creating it if it does not exist yet.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let file = OpenOptions::new()
+            .create(true)
+            .read(true)
+            .append(true)
+            .open(path)?;
+        let mut entries = Vec::new();
+        let mut complete_len = 0;
+        let mut reader = BufReader::new(&file);
+        let mut line = String::new();
+        loop {
+            line.clear();
+            let len = reader.read_line(&mut line)?;
+            if !line.ends_with('\n') {
+                break;
+            }
+            match serde_json::from_str(&line) {
+                Ok(entry) => entries.push(entry),
+                Err(_) => break,
+            }
+            complete_len += len as u64;
+        }
+        drop(reader);
+        file.set_len(complete_len)?;
+        Ok(Self { entries, file })
+    }
+
+    /// Returns the proof of the account fetched before at the given block for exactly the given
+    /// storage keys, if any.
+    pub fn get(
+        &self,
+        block_number: u64,
+        address: Address,
+        keys: &[B256],
+    ) -> Option<EIP1186AccountProofResponse> {
+        self.entries
+            .iter()
+            .find(|entry| {
+                entry.block_number == block_number && entry.address == address && entry.keys == keys
+            })
+            .map(|entry| entry.proof.clone())
+    }
+
+    /// Appends a fetched proof to the checkpoint.
+    pub fn record(
+        &mut self,
+        block_number: u64,
+        address: Address,
+        keys: &[B256],
+        proof: &EIP1186AccountProofResponse,
+    ) -> Result<()> {
+        let entry = CheckpointEntry {
+            block_number,
+            address,
+            keys: keys.to_vec(),
+            proof: proof.clone(),
+        };
+        writeln!(self.file, "{}", serde_json::to_string(&entry)?)?;
+        self.entries.push(entry);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_checkpoint_truncated_line() {
+        let path = std::env::temp_dir().join(format!("checkpoint-{}.jsonl", std::process::id()));
+        let _ = fs::remove_file(&path);
+        let proof = EIP1186AccountProofResponse::default();
+        let mut checkpoint = ProofCheckpoint::open(&path).unwrap();
+        checkpoint
+            .record(1, Address::repeat_byte(1), &[], &proof)
+            .unwrap();
+        drop(checkpoint);
+
+        // Interrupt the write of a second proof halfway.
+        let entry = CheckpointEntry {
+            block_number: 1,
+            address: Address::repeat_byte(2),
+            keys: Vec::new(),
+            proof: proof.clone(),
+        };
+        let line = serde_json::to_string(&entry).unwrap();
+        OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .unwrap()
+            .write_all(&line.as_bytes()[..line.len() / 2])
+            .unwrap();
+
+        // The incomplete line is dropped, and the proofs recorded afterwards are kept.
+        let mut checkpoint = ProofCheckpoint::open(&path).unwrap();
+        assert_eq!(checkpoint.entries.len(), 1);
+        checkpoint
+            .record(1, Address::repeat_byte(3), &[], &proof)
+            .unwrap();
+        drop(checkpoint);
+        let checkpoint = ProofCheckpoint::open(&path).unwrap();
+        assert!(checkpoint.get(1, Address::repeat_byte(1), &[]).is_some());
+        assert!(checkpoint.get(1, Address::repeat_byte(2), &[]).is_none());
+        assert!(checkpoint.get(1, Address::repeat_byte(3), &[]).is_some());
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,162 @@
+use crate::init::SP1RethInputInitializer;
+use crate::{check_elf, shard, SP1RethArgs, SP1_RETH_ELF};
+use alloy_providers::provider::{HttpProvider, TempProvider};
+use alloy_transport_http::Http;
+use anyhow::{anyhow, bail, Result};
+use clap::Args;
+use sp1_core::{utils::BabyBearPoseidon2, SP1Prover, SP1Stdin, SP1Verifier};
+use sp1_reth_primitives::public_values::{BlockStatus, SP1RethPublicValues, PUBLIC_VALUES_LEN};
+use sp1_reth_primitives::SP1RethInput;
+use std::process::Command;
+use std::time::Duration;
+use url::Url;
+
+/// The number of blocks behind the head the canary proves, so that reorgs do not affect it.
+const CONFIRMATIONS: u64 = 2;
+
+/// The delay before retrying to fetch the head block number the first time, doubled on every
+/// further failure up to the interval of the canary.
+const INITIAL_RETRY_DELAY: Duration = Duration::from_secs(1);
+
+/// The CLI arguments for the canary, which periodically proves a recent block.
+#[derive(Args, Debug, Clone)]
+pub struct CanaryArgs {
+    #[arg(short, long)]
+    rpc_url: String,
+
+    /// The name of the chain profile the blocks belong to.
+    #[arg(long, default_value = "mainnet")]
+    chain: String,
+
+    /// The number of seconds between two proofs.
+    #[arg(long, default_value_t = 86400)]
+    interval: u64,
+
+    /// A shell command run on every failure, with the block number, if any, in `CANARY_BLOCK` and
+    /// the reason in `CANARY_REASON`.
+    #[arg(long)]
+    alert_command: Option<String>,
+
+    /// The factor by which the cycles per gas of a block may exceed the average of the previous
+    /// blocks before an alert is fired.
+    #[arg(long, default_value_t = 2.0)]
+    cycle_anomaly_factor: f64,
+}
+
+/// Proves a recent block every interval, and fires the alert command when building the witness,
+/// proving or verifying fails, when the program aborts, or when the cycle count is anomalous.
+///
+/// This catches a hardfork or dependency change that breaks proving before production pipelines
+/// hit it. Runs until the process is killed.
+pub async fn run(args: &CanaryArgs) -> Result<()> {
+    check_elf()?;
+    let http = Http::new(Url::parse(&args.rpc_url)?);
+    let provider: HttpProvider = HttpProvider::new(http);
+    let mut cycles_per_gas = Vec::new();
+    loop {
+        let head = head_block_number(args, &provider).await;
+        let block_number = head.saturating_sub(CONFIRMATIONS);
+        match prove_block(args, block_number).await {
+            Ok((cycles, gas_used)) => {
+                let ratio = cycles as f64 / gas_used.max(1) as f64;
+                println!(
+                    "canary: block {} proven in {} cycles ({:.2} cycles per gas)",
+                    block_number, cycles, ratio
+                );
+                if !cycles_per_gas.is_empty() {
+                    let average = cycles_per_gas.iter().sum::<f64>() / cycles_per_gas.len() as f64;
+                    if ratio > average * args.cycle_anomaly_factor {
+                        let reason = format!(
+                            "{:.2} cycles per gas exceeds the average of {:.2}",
+                            ratio, average
+                        );
+                        alert(args, Some(block_number), &reason);
+                    }
+                }
+                cycles_per_gas.push(ratio);
+            }
+            Err(err) => alert(args, Some(block_number), &err.to_string()),
+        }
+        tokio::time::sleep(Duration::from_secs(args.interval)).await;
+    }
+}
+
+/// Fetches the number of the head block, retrying with exponential backoff until the provider
+/// answers. The first failure of a streak fires the alert command, and the following ones are
+/// only logged.
+async fn head_block_number(args: &CanaryArgs, provider: &HttpProvider) -> u64 {
+    let max_delay = Duration::from_secs(args.interval).max(INITIAL_RETRY_DELAY);
+    let mut delay = INITIAL_RETRY_DELAY;
+    let mut alerted = false;
+    loop {
+        match provider.get_block_number().await {
+            Ok(head) => return head.to::<u64>(),
+            Err(err) => {
+                let reason = format!("fetching the head block number failed: {}", err);
+                if alerted {
+                    println!("canary: {}", reason);
+                } else {
+                    alert(args, None, &reason);
+                    alerted = true;
+                }
+                println!("canary: retrying in {:?}", delay);
+                tokio::time::sleep(delay).await;
+                delay = (delay * 2).min(max_delay);
+            }
+        }
+    }
+}
+
+/// Builds the witness of the block, proves and verifies it, and returns the number of cycles of
+/// the program and the gas used by the block.
+async fn prove_block(canary_args: &CanaryArgs, block_number: u64) -> Result<(u64, u64)> {
+    let args = SP1RethArgs::new(
+        canary_args.rpc_url.clone(),
+        canary_args.chain.clone(),
+        block_number,
+    );
+    let input = SP1RethInput::initialize(&args).await?;
+    let gas_used = input.expected_gas_used.unwrap_or_default();
+
+    let mut stdin = SP1Stdin::new();
+    stdin.write(&input);
+    let cycles = shard::measure_cycles(SP1_RETH_ELF, &stdin);
+    let config = BabyBearPoseidon2::new();
+    let mut proof = SP1Prover::prove_with_config(SP1_RETH_ELF, stdin, config)
+        .map_err(|err| anyhow!("proving failed: {:?}", err))?;
+    let config = BabyBearPoseidon2::new();
+    SP1Verifier::verify_with_config(SP1_RETH_ELF, &proof, config)
+        .map_err(|err| anyhow!("verification failed: {:?}", err))?;
+    let mut encoded = vec![0; PUBLIC_VALUES_LEN];
+    proof.stdout.read_slice(&mut encoded);
+    let public_values = SP1RethPublicValues::abi_decode(&encoded)
+        .ok_or_else(|| anyhow!("malformed public values"))?;
+    if public_values.status != BlockStatus::Valid {
+        bail!("block proven invalid: {:?}", public_values);
+    }
+    Ok((cycles, gas_used))
+}
+
+/// Reports a failure of the canary, for the given block if any, running the alert command if one
+/// is configured.
+fn alert(args: &CanaryArgs, block_number: Option<u64>, reason: &str) {
+    match block_number {
+        Some(block_number) => println!("canary: block {} failed: {}", block_number, reason),
+        None => println!("canary: {}", reason),
+    }
+    let Some(alert_command) = &args.alert_command else {
+        return;
+    };
+    let mut command = Command::new("sh");
+    command
+        .arg("-c")
+        .arg(alert_command)
+        .env("CANARY_REASON", reason);
+    if let Some(block_number) = block_number {
+        command.env("CANARY_BLOCK", block_number.to_string());
+    }
+    let status = command.status();
+    if let Err(err) = status {
+        println!("canary: failed to run the alert command: {}", err);
+    }
+}
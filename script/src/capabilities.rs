@@ -0,0 +1,78 @@
+use serde::Serialize;
+use sp1_reth_primitives::chain::Hardfork;
+use sp1_reth_primitives::profile::ChainRegistry;
+
+/// The capabilities the binary was compiled with, used by orchestration to check deployments.
+#[derive(Debug, Serialize)]
+pub struct Capabilities {
+    /// The version of the binary.
+    pub version: &'static str,
+
+    /// The chains whose blocks can be proven.
+    pub chains: Vec<ChainCapabilities>,
+
+    /// The modes in which proofs can be generated.
+    pub proof_modes: Vec<&'static str>,
+
+    /// The sources the witness of a block can be built from.
+    pub witness_backends: Vec<&'static str>,
+
+    /// The optional features enabled at compile time.
+    pub features: Vec<&'static str>,
+}
+
+/// The capabilities of the binary for a single chain.
+#[derive(Debug, Serialize)]
+pub struct ChainCapabilities {
+    /// The name of the chain profile.
+    pub name: String,
+
+    /// The EIP-155 chain id of the chain.
+    pub chain_id: u64,
+
+    /// The hardforks of the chain known to the binary.
+    pub hardforks: Vec<Hardfork>,
+}
+
+impl Capabilities {
+    /// Collects the capabilities of the running binary, with the chains of the given registry.
+    pub fn collect(version: &'static str, registry: &ChainRegistry) -> Self {
+        let chains = registry
+            .names()
+            .filter_map(|name| registry.get(name))
+            .map(|profile| {
+                let config = profile.config();
+                ChainCapabilities {
+                    name: profile.name().to_string(),
+                    chain_id: config.chain_id,
+                    hardforks: config.hardforks.iter().map(|(fork, _)| *fork).collect(),
+                }
+            })
+            .collect();
+
+        let mut features = Vec::new();
+        if cfg!(feature = "optimism") {
+            features.push("optimism");
+        }
+        if cfg!(feature = "polygon") {
+            features.push("polygon");
+        }
+        if cfg!(feature = "kzg") {
+            features.push("kzg");
+        }
+        if cfg!(feature = "strict") {
+            features.push("strict");
+        }
+        if cfg!(feature = "neon") {
+            features.push("neon");
+        }
+
+        Self {
+            version,
+            chains,
+            proof_modes: vec!["core"],
+            witness_backends: vec!["rpc", "cache"],
+            features,
+        }
+    }
+}
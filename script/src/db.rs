@@ -16,6 +16,8 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use crate::cache::ProofCheckpoint;
+use crate::rpclog::RpcRecorder;
 use alloy_providers::provider::{HttpProvider, TempProvider};
 use alloy_rpc_types::{BlockId, EIP1186AccountProofResponse};
 use anyhow::Result;
@@ -25,9 +27,17 @@ use revm::db::InMemoryDB;
 use revm::primitives::db::Database;
 use revm::primitives::HashMap;
 use revm::DatabaseCommit;
+use sp1_reth_primitives::alloy2reth::IntoReth;
 use sp1_reth_primitives::db::InMemoryDBHelper;
+use std::collections::BTreeSet;
+use std::fmt::Display;
+use std::sync::Arc;
 use tokio::runtime::Handle;
 
+/// The maximum number of storage keys requested in a single `eth_getProof` call, since providers
+/// commonly reject larger requests.
+const MAX_PROOF_KEYS_PER_REQUEST: usize = 1024;
+
 /// A database that fetches data from a [HttpProvider].
 pub struct RemoteDb {
     /// The provider to fetch data from.
@@ -42,6 +52,16 @@ pub struct RemoteDb {
     /// The latest database state.
     pub current_db: InMemoryDB,
 
+    /// State known ahead of time, consulted before the provider. Entries are only moved into the
+    /// initial database, and thus into the witness, once execution touches them.
+    pub primed_db: InMemoryDB,
+
+    /// An optional recorder of the RPC requests made by the database.
+    pub recorder: Option<Arc<RpcRecorder>>,
+
+    /// An optional checkpoint of the storage proofs, reused instead of fetching them again.
+    pub proof_checkpoint: Option<ProofCheckpoint>,
+
     /// An executor for asynchronous tasks, facilitating non-blocking operations.
     async_executor: Handle,
 }
@@ -54,11 +74,41 @@ impl RemoteDb {
             block_number,
             initial_db: InMemoryDB::default(),
             current_db: InMemoryDB::default(),
+            primed_db: InMemoryDB::default(),
+            recorder: None,
+            proof_checkpoint: None,
             async_executor: tokio::runtime::Handle::current(),
         }
     }
 
-    /// Gets all storage proofs for a given block number and a set of storage keys.
+    /// Primes the database with a snapshot of the state at the block we are executing from, such
+    /// as the post-state of the previous block, so that the state it contains is not fetched from
+    /// the provider. Accounts and block hashes already primed are kept.
+    pub fn prime(&mut self, snapshot: InMemoryDB) {
+        for (address, account) in snapshot.accounts {
+            self.primed_db.accounts.entry(address).or_insert(account);
+        }
+        for (number, hash) in snapshot.block_hashes {
+            self.primed_db.block_hashes.entry(number).or_insert(hash);
+        }
+    }
+
+    /// Records a request and its response if a recorder is configured.
+    fn record<P, R, E>(&self, method: &str, params: &P, response: &Result<R, E>)
+    where
+        P: serde::Serialize,
+        R: serde::Serialize,
+        E: Display,
+    {
+        if let Some(recorder) = &self.recorder {
+            recorder.record(method, params, response);
+        }
+    }
+
+    /// Gets all storage proofs for a given block number and a set of storage keys. Duplicate keys
+    /// are requested once, and the keys of an account are split into requests of at most
+    /// [MAX_PROOF_KEYS_PER_REQUEST] keys. Proofs found in the checkpoint are not requested again,
+    /// and fetched proofs are added to it.
     fn fetch_storage_proofs(
         &mut self,
         block_number: u64,
@@ -66,13 +116,35 @@ impl RemoteDb {
     ) -> Result<HashMap<Address, EIP1186AccountProofResponse>> {
         let mut storage_proofs = HashMap::new();
         for (address, keys) in storage_keys {
-            let indices = keys.into_iter().map(|x| x.to_be_bytes().into()).collect();
-            let proof = self.async_executor.block_on(async {
-                self.provider
-                    .get_proof(address, indices, Some(BlockId::from(block_number)))
-                    .await
-            })?;
-            storage_proofs.insert(address, proof);
+            let keys = keys.into_iter().collect::<BTreeSet<_>>();
+            let indices: Vec<B256> = keys.into_iter().map(|x| x.to_be_bytes().into()).collect();
+            let checkpointed = self
+                .proof_checkpoint
+                .as_ref()
+                .and_then(|checkpoint| checkpoint.get(block_number, address, &indices));
+            if let Some(proof) = checkpointed {
+                storage_proofs.insert(address, proof);
+                continue;
+            }
+            let mut account_proof: Option<EIP1186AccountProofResponse> = None;
+            for chunk in chunk_keys(&indices) {
+                let proof = self.async_executor.block_on(async {
+                    self.provider
+                        .get_proof(address, chunk.to_vec(), Some(BlockId::from(block_number)))
+                        .await
+                });
+                self.record("eth_getProof", &(address, chunk, block_number), &proof);
+                let proof = proof?;
+                match &mut account_proof {
+                    Some(account_proof) => account_proof.storage_proof.extend(proof.storage_proof),
+                    None => account_proof = Some(proof),
+                }
+            }
+            let account_proof = account_proof.unwrap();
+            if let Some(checkpoint) = &mut self.proof_checkpoint {
+                checkpoint.record(block_number, address, &indices, &account_proof)?;
+            }
+            storage_proofs.insert(address, account_proof);
         }
         Ok(storage_proofs)
     }
@@ -89,39 +161,11 @@ impl RemoteDb {
         let headers = (earliest_block.as_limbs()[0]..self.block_number)
             .rev()
             .map(|block_number| {
-                self.async_executor.block_on(async {
-                    let header = self
-                        .provider
-                        .get_block(block_number.into(), false)
-                        .await
-                        .unwrap()
-                        .unwrap()
-                        .header;
-                    Header {
-                        parent_hash: header.parent_hash.0.into(),
-                        ommers_hash: header.uncles_hash.0.into(),
-                        beneficiary: header.miner.0.into(),
-                        state_root: header.state_root.0.into(),
-                        transactions_root: header.transactions_root.0.into(),
-                        receipts_root: header.receipts_root.0.into(),
-                        withdrawals_root: header.withdrawals_root,
-                        logs_bloom: header.logs_bloom.0.into(),
-                        difficulty: header.difficulty,
-                        number: header.number.unwrap().try_into().unwrap(),
-                        gas_limit: header.gas_limit.try_into().unwrap(),
-                        gas_used: header.gas_used.try_into().unwrap(),
-                        timestamp: header.timestamp.try_into().unwrap(),
-                        extra_data: header.extra_data.0.into(),
-                        mix_hash: header.mix_hash.unwrap(),
-                        nonce: u64::from_be_bytes(header.nonce.unwrap().0),
-                        base_fee_per_gas: Some(
-                            header.base_fee_per_gas.unwrap().try_into().unwrap(),
-                        ),
-                        blob_gas_used: Some(header.blob_gas_used.unwrap().try_into().unwrap()),
-                        excess_blob_gas: Some(header.excess_blob_gas.unwrap().try_into().unwrap()),
-                        parent_beacon_block_root: header.parent_beacon_block_root,
-                    }
-                })
+                let block = self
+                    .async_executor
+                    .block_on(async { self.provider.get_block(block_number.into(), false).await });
+                self.record("eth_getBlockByNumber", &(block_number, false), &block);
+                block.unwrap().unwrap().header.into_reth()
             })
             .collect();
         Ok(headers)
@@ -151,6 +195,15 @@ impl RemoteDb {
     }
 }
 
+/// Splits storage keys into chunks of at most [MAX_PROOF_KEYS_PER_REQUEST] keys. An account
+/// without keys still gets a single empty chunk, so that its account proof is fetched.
+fn chunk_keys(keys: &[B256]) -> Vec<&[B256]> {
+    if keys.is_empty() {
+        return vec![keys];
+    }
+    keys.chunks(MAX_PROOF_KEYS_PER_REQUEST).collect()
+}
+
 impl Database for RemoteDb {
     type Error = anyhow::Error;
 
@@ -162,23 +215,33 @@ impl Database for RemoteDb {
         if let Ok(db_result) = self.initial_db.get_account_info(address) {
             return Ok(db_result);
         }
+        if let Ok(Some(account_info)) = self.primed_db.get_account_info(address) {
+            self.initial_db
+                .insert_account_info(address, account_info.clone());
+            return Ok(Some(account_info));
+        }
 
         // Get the nonce, balance, and code to reconstruct the account.
+        let params = (address, self.block_number);
         let nonce = self.async_executor.block_on(async {
             self.provider
                 .get_transaction_count(address, Some(BlockId::from(self.block_number)))
                 .await
-        })?;
+        });
+        self.record("eth_getTransactionCount", &params, &nonce);
         let balance = self.async_executor.block_on(async {
             self.provider
                 .get_balance(address, Some(BlockId::from(self.block_number)))
                 .await
-        })?;
+        });
+        self.record("eth_getBalance", &params, &balance);
         let code = self.async_executor.block_on(async {
             self.provider
                 .get_code_at(address, Some(BlockId::from(self.block_number)))
                 .await
-        })?;
+        });
+        self.record("eth_getCode", &params, &code);
+        let (nonce, balance, code) = (nonce?, balance?, code?);
 
         // Insert the account into the initial database.
         let account_info = AccountInfo::new(
@@ -200,9 +263,19 @@ impl Database for RemoteDb {
         if let Ok(db_result) = self.initial_db.get_storage_slot(address, index) {
             return Ok(db_result);
         }
+        self.initial_db.basic(address)?;
+        let primed = self
+            .primed_db
+            .accounts
+            .get(&address)
+            .and_then(|account| account.storage.get(&index));
+        if let Some(storage) = primed.copied() {
+            self.initial_db
+                .insert_account_storage(address, index, storage)?;
+            return Ok(storage);
+        }
 
         // Get the storage slot from the provider.
-        self.initial_db.basic(address)?;
         let storage = self.async_executor.block_on(async {
             self.provider
                 .get_storage_at(
@@ -211,7 +284,13 @@ impl Database for RemoteDb {
                     Some(BlockId::from(self.block_number)),
                 )
                 .await
-        })?;
+        });
+        self.record(
+            "eth_getStorageAt",
+            &(address, index, self.block_number),
+            &storage,
+        );
+        let storage = storage?;
         self.initial_db
             .insert_account_storage(address, index, storage)?;
         Ok(storage)
@@ -223,20 +302,20 @@ impl Database for RemoteDb {
             return Ok(block_hash);
         }
 
+        if let Some(block_hash) = self.primed_db.block_hashes.get(&number).copied() {
+            self.initial_db.insert_block_hash(number, block_hash);
+            return Ok(block_hash);
+        }
+
         // Get the block hash from the provider.
         let block_number = u64::try_from(number).unwrap();
-        let block_hash = self.async_executor.block_on(async {
+        let block = self.async_executor.block_on(async {
             self.provider
                 .get_block_by_number(block_number.into(), false)
                 .await
-                .unwrap()
-                .unwrap()
-                .header
-                .hash
-                .unwrap()
-                .0
-                .into()
         });
+        self.record("eth_getBlockByNumber", &(block_number, false), &block);
+        let block_hash = block.unwrap().unwrap().header.hash.unwrap().0.into();
         self.initial_db
             .insert_block_hash(U256::from(block_number), block_hash);
         Ok(block_hash)
@@ -16,20 +16,31 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use crate::cache::ProofCheckpoint;
 use crate::db::RemoteDb;
+use crate::prefetch::Prefetcher;
+use crate::rpclog::RpcRecorder;
+use crate::trace::{self, CallTracer};
 use crate::SP1RethArgs;
+use alloy_primitives::U64;
 use alloy_providers::provider::HttpProvider;
 use alloy_providers::provider::TempProvider;
-use alloy_rpc_types::BlockTransactions;
+use alloy_rpc_types::{Block, BlockId, BlockNumberOrTag, BlockTransactions};
 use alloy_transport_http::Http;
-use anyhow::Result;
+use anyhow::{bail, Result};
 use async_trait::async_trait;
-use reth_primitives::Bytes;
-use sp1_reth_primitives::alloy2reth::IntoReth;
+use reth_primitives::{Address, Bytes, B256};
+use revm::db::{AccountState, InMemoryDB};
+use sp1_reth_primitives::alloy2reth::{IntoReth, TryIntoReth};
+use sp1_reth_primitives::db::InMemoryDBHelper;
 use sp1_reth_primitives::mpt::proofs_to_tries;
-use sp1_reth_primitives::processor::EvmProcessor;
+use sp1_reth_primitives::processor::{EvmProcessor, ProcessorError};
+use sp1_reth_primitives::senders::recover_sender_hint;
+use sp1_reth_primitives::state::StateRootComputer;
+use sp1_reth_primitives::withdrawals::Withdrawals;
 use sp1_reth_primitives::SP1RethInput;
 use std::collections::HashSet;
+use std::sync::Arc;
 use url::Url;
 
 #[async_trait]
@@ -47,70 +58,260 @@ impl SP1RethInputInitializer for SP1RethInput {
         let http = Http::new(Url::parse(&args.rpc_url).expect("invalid rpc url"));
         let provider: HttpProvider = HttpProvider::new(http);
 
+        // Optionally record the RPC requests made while building the witness.
+        let recorder = match &args.debug_rpc {
+            Some(path) => Some(Arc::new(RpcRecorder::create(path, &args.rpc_url)?)),
+            None => None,
+        };
+
+        // Optionally wait for the block while pre-warming the state of pending transactions.
+        let prefetched_db = if args.prefetch {
+            let mut prefetcher = Prefetcher::new(&args.rpc_url, args.block_number - 1);
+            prefetcher.run_until_produced().await?;
+            Some(prefetcher.into_db())
+        } else {
+            None
+        };
+
+        // Make sure the provider serves the chain the input is built for.
+        let Some(profile) = args.registry.get(&args.chain) else {
+            bail!(
+                "unknown chain {}, expected one of: {}",
+                args.chain,
+                args.registry.names().collect::<Vec<_>>().join(", ")
+            );
+        };
+        let chain_config = profile.config();
+        let chain_id = provider.get_chain_id().await?.to::<u64>();
+        if chain_id != chain_config.chain_id {
+            bail!(
+                "provider serves chain {}, but {} has chain id {}",
+                chain_id,
+                args.chain,
+                chain_config.chain_id
+            );
+        }
+
+        // Make sure the provider still serves the state the block is executed on.
+        check_state_available(&provider, args.block_number - 1).await?;
+
         // Get the block.
         let parent_block = provider
             .get_block_by_number((args.block_number - 1).into(), false)
-            .await?;
-        let parent_header = parent_block.unwrap().header;
+            .await;
         let block = provider
             .get_block_by_number(args.block_number.into(), true)
-            .await?
-            .unwrap();
+            .await;
+        if let Some(recorder) = &recorder {
+            recorder.record(
+                "eth_getBlockByNumber",
+                &(args.block_number - 1, false),
+                &parent_block,
+            );
+            recorder.record("eth_getBlockByNumber", &(args.block_number, true), &block);
+        }
+        let parent_header = parent_block?.unwrap().header;
+        let block = block?.unwrap();
+
+        // Get the ommers.
+        let mut ommers = Vec::with_capacity(block.uncles.len());
+        for index in 0..block.uncles.len() {
+            let ommer: Block = provider
+                .raw_request(
+                    "eth_getUncleByBlockNumberAndIndex",
+                    (BlockNumberOrTag::from(args.block_number), U64::from(index)),
+                )
+                .await?;
+            ommers.push(ommer.header.into_reth());
+        }
 
         // Intiialize the db.
-        let provider_db = RemoteDb::new(provider, parent_header.number.unwrap().as_limbs()[0]);
+        let mut provider_db = RemoteDb::new(provider, parent_header.number.unwrap().as_limbs()[0]);
+        provider_db.recorder = recorder;
+        if let Some(path) = &args.resume_witness {
+            provider_db.proof_checkpoint = Some(ProofCheckpoint::open(path)?);
+        }
+        if let Some(prefetched_db) = prefetched_db {
+            provider_db.prime(prefetched_db);
+        }
 
         // Create the input.
         let txs = match block.transactions {
-            BlockTransactions::Full(txs) => txs.into_iter().map(|tx| tx.into_reth()).collect(),
+            BlockTransactions::Full(txs) => txs,
             _ => unreachable!(),
         };
+        if let Some(limit) = args.tx_limit {
+            if limit as usize > txs.len() {
+                bail!(
+                    "transaction limit {} exceeds the {} transactions of block {}",
+                    limit,
+                    txs.len(),
+                    args.block_number
+                );
+            }
+        }
+        if let Some(start) = args.tx_start {
+            let end = args.tx_limit.unwrap_or(txs.len() as u32);
+            if start > end {
+                bail!(
+                    "transaction range starts at {}, after its end {}",
+                    start,
+                    end
+                );
+            }
+        }
+        #[cfg(feature = "polygon")]
+        if let Some(tx) = txs.iter().find(|tx| crate::polygon::is_state_sync(tx)) {
+            bail!(
+                "block {} applies the Bor state syncs {}, which are not supported",
+                args.block_number,
+                tx.hash
+            );
+        }
+        let mut reth_txs = Vec::with_capacity(txs.len());
+        for tx in txs {
+            #[cfg(feature = "optimism")]
+            if crate::optimism::is_deposit(&tx) {
+                reth_txs.push(crate::optimism::fetch_deposit(&provider_db.provider, tx).await?);
+                continue;
+            }
+            reth_txs.push(tx.try_into_reth()?);
+        }
+        let sender_hints = reth_txs
+            .iter()
+            .enumerate()
+            .filter(|_| args.sender_hints)
+            .filter_map(|(index, tx)| Some((index.try_into().unwrap(), recover_sender_hint(tx)?)))
+            .collect();
         let withdrawals = block
             .withdrawals
-            .unwrap()
+            .unwrap_or_default()
             .into_iter()
             .map(|w| w.into_reth())
             .collect();
+        let withdrawals = Withdrawals::new(withdrawals)?;
+        // Only the whole block, executed without a filter or overrides, results in the header of
+        // the chain.
+        let is_canonical = args.tx_limit.is_none()
+            && args.tx_start.is_none()
+            && args.execution_filter().is_none()
+            && args.block_overrides().is_none();
+        let expected_block_hash = block
+            .header
+            .hash
+            .filter(|_| is_canonical)
+            .map(|hash| hash.0.into());
+        let expected_gas_used = is_canonical.then(|| block.header.gas_used.try_into().unwrap());
+        let expected_blob_gas_used = block
+            .header
+            .blob_gas_used
+            .filter(|_| is_canonical)
+            .map(|blob_gas_used| blob_gas_used.try_into().unwrap());
+        // The code of the contracts deployed by the protocol is not part of the parent state.
+        #[cfg(feature = "optimism")]
+        let protocol_contracts = crate::optimism::fetch_create2_deployer(
+            &provider_db.provider,
+            &chain_config,
+            args.block_number,
+            parent_header.timestamp.try_into().unwrap(),
+            block.header.timestamp.try_into().unwrap(),
+        )
+        .await?
+        .into_iter()
+        .collect();
+        #[cfg(not(feature = "optimism"))]
+        let protocol_contracts = Vec::new();
+        // The header is kept for valid blocks too, so that a cached input can be proven invalid.
+        let claimed_header = block.header.clone().into_reth();
         let input = SP1RethInput {
+            chain_config,
             beneficiary: block.header.miner,
             gas_limit: block.header.gas_limit.try_into().unwrap(),
             timestamp: block.header.timestamp.try_into().unwrap(),
             extra_data: block.header.extra_data,
             mix_hash: block.header.mix_hash.unwrap(),
-            transactions: txs,
+            difficulty: block.header.difficulty,
+            nonce: u64::from_be_bytes(block.header.nonce.unwrap_or_default().0),
+            ommers,
+            parent_beacon_block_root: block.header.parent_beacon_block_root,
+            base_fee_per_gas: block
+                .header
+                .base_fee_per_gas
+                .map(|base_fee| base_fee.try_into().unwrap()),
+            excess_blob_gas: block
+                .header
+                .excess_blob_gas
+                .map(|excess_blob_gas| excess_blob_gas.try_into().unwrap()),
+            transactions: reth_txs,
+            sender_hints,
             withdrawals,
             parent_state_trie: Default::default(),
             parent_storage: Default::default(),
-            contracts: Default::default(),
+            contracts: protocol_contracts,
             parent_header: parent_header.into_reth(),
             ancestor_headers: Default::default(),
+            commit_receipts: args.commit_receipts,
+            commit_block_oracle: args.commit_block_oracle,
+            prove_invalid: args.prove_invalid,
+            execution_filter: args.execution_filter(),
+            block_overrides: args.block_overrides(),
+            transaction_limit: args.tx_limit,
+            // The preceding transactions are executed to discover their accesses, and skipped
+            // once the witness is advanced to the start of the range.
+            transaction_start: None,
+            state_root_interval: args.state_root_interval,
+            expected_block_hash,
+            expected_gas_used,
+            expected_blob_gas_used,
+            expected_transactions_root: Some(block.header.transactions_root.0.into()),
+            claimed_header: Some(claimed_header),
         };
 
-        let mut executor = EvmProcessor::<RemoteDb> {
-            input: input.clone(),
-            db: Some(provider_db),
-            header: None,
-        };
-        executor.initialize();
-        let mut executor = tokio::task::spawn_blocking(move || {
-            executor.execute();
-            executor
+        let mut executor = EvmProcessor::new(input.clone(), provider_db)
+            .with_checkpoint_state_root(pending_state_root);
+        executor.initialize()?;
+        let trace = args.trace;
+        let (mut executor, result) = tokio::task::spawn_blocking(move || {
+            let result = if trace {
+                executor
+                    .execute_with_inspector(CallTracer::default())
+                    .map(Some)
+            } else {
+                executor.execute().map(|_| None)
+            };
+            (executor, result)
         })
         .await?;
+        if let Some(tracer) = result? {
+            let path = format!("{}.trace.json", args.block_number);
+            let traces = tracer.into_traces(&input, &executor.skipped_transactions);
+            trace::save(&path, &traces)?;
+            println!(
+                "saved the call traces of {} transactions to {}",
+                traces.len(),
+                path
+            );
+        }
+        for contract in &executor.created_contracts {
+            println!(
+                "tx {}: {} deployed {} with code hash {}",
+                contract.transaction_index, contract.deployer, contract.address, contract.code_hash
+            );
+        }
 
         // Get the proofs and ancestor headers.
         let mut provider_db = executor.db.take().unwrap();
         let (parent_proofs, proofs, ancestor_headers, provider_db) =
-            tokio::task::spawn_blocking(move || {
-                let parent_proofs = provider_db.fetch_initial_storage_proofs().unwrap();
-                let proofs = provider_db.fetch_latest_storage_proofs().unwrap();
-                let ancestor_headers = provider_db.fetch_ancestor_headers().unwrap();
-                (parent_proofs, proofs, ancestor_headers, provider_db)
+            tokio::task::spawn_blocking(move || -> Result<_> {
+                let parent_proofs = provider_db.fetch_initial_storage_proofs()?;
+                let proofs = provider_db.fetch_latest_storage_proofs()?;
+                let ancestor_headers = provider_db.fetch_ancestor_headers()?;
+                Ok((parent_proofs, proofs, ancestor_headers, provider_db))
             })
-            .await?;
+            .await??;
 
-        // Get the contracts from the initial db.
-        let mut contracts = HashSet::new();
+        // Get the contracts from the initial db, and those deployed by the protocol.
+        let mut contracts: HashSet<_> = input.contracts.iter().map(|code| code.0.clone()).collect();
         let initial_db = provider_db.initial_db;
         for account in initial_db.accounts.values() {
             let code = &account.info.code;
@@ -132,8 +333,115 @@ impl SP1RethInputInitializer for SP1RethInput {
             ..input
         };
 
+        // Make sure the guest will be able to finalize the block with this witness.
+        check_witness_coverage(&provider_db.current_db, &input)?;
+
+        // Make sure the witness yields the state root of the block before proving it.
+        if is_canonical {
+            let mut computer = StateRootComputer::from_input(&input);
+            computer.apply(&provider_db.current_db)?;
+            let expected = B256::from(block.header.state_root.0);
+            if computer.root() != expected {
+                bail!(
+                    "witness of block {} yields state root {}, but the block has {}",
+                    args.block_number,
+                    computer.root(),
+                    expected
+                );
+            }
+        }
+
+        // Advance the witness to the state the transaction range starts from.
+        let input = match args.tx_start {
+            Some(start) => advance_witness(input, start)?,
+            None => input,
+        };
+
         // DONE!
 
         Ok(input)
     }
 }
+
+/// The state history commonly kept by providers that prune old state.
+const PRUNED_STATE_HISTORY: u64 = 128;
+
+/// Checks that the provider serves the state at the given block, which pruning providers only do
+/// for recent blocks.
+async fn check_state_available(provider: &HttpProvider, block_number: u64) -> Result<()> {
+    if let Err(err) = provider
+        .get_balance(Address::ZERO, Some(BlockId::from(block_number)))
+        .await
+    {
+        let latest = provider.get_block_number().await?.to::<u64>();
+        bail!(
+            "provider does not serve the state of block {} ({} blocks behind the head): {}. \
+             Providers that prune state usually keep only the last {} blocks, use an archive \
+             node to prove older blocks",
+            block_number,
+            latest.saturating_sub(block_number),
+            err,
+            PRUNED_STATE_HISTORY
+        );
+    }
+    Ok(())
+}
+
+/// Stands in for the intermediate state roots while the block is executed against the provider.
+/// The witness they are computed from is only built afterwards, so the program computes them.
+fn pending_state_root(_: &SP1RethInput, _: &RemoteDb) -> Result<B256, ProcessorError> {
+    Ok(B256::ZERO)
+}
+
+/// Returns the input of the transaction range starting at the given index, whose parent tries hold
+/// the state after executing the preceding transactions on the tries of the input.
+///
+/// The tries of the input must cover the accesses of both the preceding transactions and the
+/// range, so that the advanced tries are a witness of the range.
+fn advance_witness(input: SP1RethInput, start: u32) -> Result<SP1RethInput> {
+    let mut prefix = SP1RethInput {
+        transaction_limit: Some(start),
+        state_root_interval: None,
+        expected_block_hash: None,
+        expected_gas_used: None,
+        expected_blob_gas_used: None,
+        ..input.clone()
+    };
+    let db = InMemoryDB::initialize(&mut prefix)?;
+    let mut executor = EvmProcessor::new(prefix, db);
+    executor.initialize()?;
+    executor.execute()?;
+
+    let mut computer = StateRootComputer::from_input(&input);
+    computer.apply(executor.db.as_ref().unwrap())?;
+    let (parent_state_trie, parent_storage) = computer.into_tries();
+    Ok(SP1RethInput {
+        parent_state_trie,
+        parent_storage,
+        transaction_start: Some(start),
+        ..input
+    })
+}
+
+/// Checks that the witness contains the storage of every account written during execution.
+fn check_witness_coverage(db: &InMemoryDB, input: &SP1RethInput) -> Result<()> {
+    let missing = db
+        .accounts
+        .iter()
+        .filter(|(_, account)| {
+            !matches!(
+                account.account_state,
+                AccountState::None | AccountState::NotExisting
+            )
+        })
+        .map(|(address, _)| *address)
+        .filter(|address| !input.parent_storage.contains_key(address))
+        .collect::<Vec<_>>();
+    if !missing.is_empty() {
+        bail!(
+            "witness does not cover accounts written during execution: {:?}",
+            missing
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,77 @@
+//! Inspection of cached inputs, for diagnosing witnesses without proving them.
+
+use crate::cache;
+use anyhow::Result;
+use clap::{Args, ValueEnum};
+use sp1_reth_primitives::dump::{self, NodeDump};
+use sp1_reth_primitives::mpt::MptNode;
+use std::fs;
+use std::path::PathBuf;
+
+/// The CLI arguments for inspecting a cached input.
+#[derive(Args, Debug, Clone)]
+pub struct InspectArgs {
+    #[arg(short, long)]
+    block_number: u64,
+
+    /// Dump the state trie and every storage trie of the witness in this format.
+    #[arg(long, value_name = "FORMAT")]
+    dump_tries: Option<TrieFormat>,
+
+    /// The directory the tries are dumped to.
+    #[arg(long, default_value = "tries")]
+    out_dir: PathBuf,
+}
+
+/// The formats tries can be dumped in.
+#[derive(ValueEnum, Debug, Clone, Copy)]
+pub enum TrieFormat {
+    /// A Graphviz DOT graph, to be rendered with `dot -Tsvg`.
+    Dot,
+
+    /// Structured JSON with the type, hash and key fragment of every node.
+    Json,
+}
+
+/// Prints a summary of the cached input of the block, and dumps its witness tries if requested.
+pub fn inspect(args: &InspectArgs) -> Result<()> {
+    let (input, metadata) = cache::load_input(format!("{}.bin", args.block_number))?;
+    if let Some(metadata) = metadata {
+        println!("provenance: {:?}", metadata);
+    }
+    println!("parent block: {}", input.parent_header.number);
+    println!("transactions: {}", input.transactions.len());
+    println!("contracts: {}", input.contracts.len());
+    println!(
+        "state trie: {} nodes, root {}",
+        input.parent_state_trie.size(),
+        input.parent_state_trie.hash()
+    );
+    println!("storage tries: {}", input.parent_storage.len());
+
+    let Some(format) = args.dump_tries else {
+        return Ok(());
+    };
+    fs::create_dir_all(&args.out_dir)?;
+    let mut tries = vec![("state".to_string(), &input.parent_state_trie)];
+    for (address, (storage_trie, _)) in &input.parent_storage {
+        tries.push((format!("storage-{}", address), storage_trie));
+    }
+    for (name, trie) in tries {
+        let (extension, contents) = render(trie, format)?;
+        fs::write(
+            args.out_dir.join(format!("{}.{}", name, extension)),
+            contents,
+        )?;
+    }
+    println!("dumped the tries to {}", args.out_dir.display());
+    Ok(())
+}
+
+/// Renders the trie in the given format, and returns the file extension for it.
+fn render(trie: &MptNode, format: TrieFormat) -> Result<(&'static str, String)> {
+    Ok(match format {
+        TrieFormat::Dot => ("dot", dump::to_dot(trie)),
+        TrieFormat::Json => ("json", serde_json::to_string_pretty(&NodeDump::new(trie))?),
+    })
+}
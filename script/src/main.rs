@@ -1,11 +1,49 @@
+pub mod attest;
+pub mod bisect;
+pub mod cache;
+pub mod canary;
+pub mod capabilities;
 pub mod db;
 pub mod init;
+pub mod inspect;
+#[cfg(feature = "optimism")]
+pub mod optimism;
+#[cfg(feature = "polygon")]
+pub mod polygon;
+pub mod prefetch;
+pub mod rpclog;
+pub mod shard;
+pub mod size;
+pub mod trace;
+pub mod vectors;
 
+use crate::attest::CanonicalAttestation;
+use crate::bisect::BisectArgs;
+use crate::cache::WitnessMetadata;
+use crate::canary::CanaryArgs;
+use crate::capabilities::Capabilities;
 use crate::init::SP1RethInputInitializer;
-use clap::Parser;
+use crate::inspect::InspectArgs;
+use crate::vectors::VectorsArgs;
+use anyhow::{ensure, Result};
+use clap::{Args, Parser, Subcommand};
+use reth_primitives::{Address, Bytes, B256};
 use sp1_core::{utils::BabyBearPoseidon2, SP1Prover, SP1Stdin, SP1Verifier};
+use sp1_reth_primitives::abort::InvalidityRecord;
+use sp1_reth_primitives::filter::{ExecutionFilter, FilterMode, SkippedTransaction};
+use sp1_reth_primitives::oracle::{BlockCommitment, BLOCK_COMMITMENT_LEN};
+use sp1_reth_primitives::overrides::BlockOverrides;
+use sp1_reth_primitives::profile::ChainRegistry;
+use sp1_reth_primitives::public_values::{
+    self, BlockStatus, SP1RethPublicValues, TransactionRange, FLAG_BLOCK_ORACLE,
+    FLAG_BLOCK_OVERRIDES, FLAG_EXECUTION_FILTER, FLAG_INTERMEDIATE_STATE_ROOTS, FLAG_RECEIPTS,
+    FLAG_SENDER_HINTS, FLAG_TRANSACTION_RANGE, PUBLIC_VALUES_LEN, PUBLIC_VALUES_VERSION,
+};
+use sp1_reth_primitives::receipts::CompactReceipts;
 use sp1_reth_primitives::SP1RethInput;
-use std::fs::File;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Instant;
 
 /// The version message for the SP1 Reth program.
 const VERSION_MESSAGE: &str = concat!(
@@ -20,53 +58,423 @@ const VERSION_MESSAGE: &str = concat!(
 /// The ELF file for the SP1 Reth program.
 const SP1_RETH_ELF: &[u8] = include_bytes!("../../program/elf/riscv32im-succinct-zkvm-elf");
 
-/// The CLI arguments for the SP1 Reth program.
+/// Checks that the ELF was rebuilt since the layout of the public values last changed, so that the
+/// public values it commits are decoded with the layout they were written in.
+fn check_elf() -> Result<()> {
+    ensure!(
+        public_values::elf_commits_current_version(SP1_RETH_ELF),
+        "the program ELF does not commit version {} of the public values, rebuild it with `cargo prove build` in program/",
+        PUBLIC_VALUES_VERSION
+    );
+    Ok(())
+}
+
+/// The CLI of the SP1 Reth program.
 #[derive(Parser, Debug)]
 #[command(version = VERSION_MESSAGE, about, long_about = None)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+/// The commands of the SP1 Reth program.
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Generate and verify a proof of the execution of a block.
+    Prove(SP1RethArgs),
+
+    /// Find the first block in a range whose native execution diverges from the chain.
+    Bisect(BisectArgs),
+
+    /// Print the chains, proof modes and features the binary was compiled with, as JSON.
+    Capabilities,
+
+    /// Generate test vectors of inputs, public values and proofs from a block.
+    Vectors(VectorsArgs),
+
+    /// Periodically prove a recent block, alerting on failures and cycle count anomalies.
+    Canary(CanaryArgs),
+
+    /// Print a summary of the cached input of a block, optionally dumping its witness tries.
+    Inspect(InspectArgs),
+}
+
+/// The CLI arguments for proving a block.
+#[derive(Args, Debug, Clone)]
 pub struct SP1RethArgs {
     #[arg(short, long)]
     rpc_url: String,
 
+    /// The name of the chain profile the block belongs to.
+    #[arg(long, default_value = "mainnet")]
+    chain: String,
+
     #[arg(short, long)]
     block_number: u64,
 
     #[arg(short, long)]
     use_cache: bool,
+
+    /// Pass the fetched witness straight to the prover without caching it on disk.
+    #[arg(long, conflicts_with = "use_cache")]
+    no_cache: bool,
+
+    /// Wait for the block to be produced, prefetching the state touched by pending transactions.
+    #[arg(long)]
+    prefetch: bool,
+
+    /// Commit the compact receipts of the block as public values.
+    #[arg(long)]
+    commit_receipts: bool,
+
+    /// Commit the number, hash and state root of the block in the ABI of block-hash oracles.
+    #[arg(long)]
+    commit_block_oracle: bool,
+
+    /// After proving, compare the committed block hash to the canonical one served by the RPC and
+    /// save the result next to the proof.
+    #[arg(long)]
+    attest_canonical: bool,
+
+    /// Commit the consensus rule the block violates instead of aborting, to prove the block
+    /// invalid.
+    #[arg(long)]
+    prove_invalid: bool,
+
+    /// Supply the public keys of the transaction senders, so that the program verifies the
+    /// signatures against them instead of recovering the keys, and commits the hints it used.
+    #[arg(long)]
+    sender_hints: bool,
+
+    /// Simulate the block without the transactions from or to any of these addresses.
+    #[arg(long, value_delimiter = ',', conflicts_with = "allowlist")]
+    blocklist: Vec<Address>,
+
+    /// Simulate the block with only the transactions between these addresses.
+    #[arg(long, value_delimiter = ',')]
+    allowlist: Vec<Address>,
+
+    /// Simulate the block with this timestamp.
+    #[arg(long, value_name = "TIMESTAMP")]
+    override_timestamp: Option<u64>,
+
+    /// Simulate the block with this beneficiary.
+    #[arg(long, value_name = "ADDRESS")]
+    override_beneficiary: Option<Address>,
+
+    /// Simulate the block with this gas limit.
+    #[arg(long, value_name = "GAS")]
+    override_gas_limit: Option<u64>,
+
+    /// Simulate the block with this prevrandao value.
+    #[arg(long, value_name = "HASH")]
+    override_prevrandao: Option<B256>,
+
+    /// Only execute the first transactions of the block, committing the state root after them.
+    #[arg(long, value_name = "COUNT")]
+    tx_limit: Option<u32>,
+
+    /// Only execute the transactions of the block from this index on, committing the state roots
+    /// before and after them.
+    #[arg(long, value_name = "INDEX")]
+    tx_start: Option<u32>,
+
+    /// Commit the state root after every this many transactions of the block.
+    #[arg(long, value_name = "COUNT", value_parser = clap::value_parser!(u32).range(1..))]
+    state_root_interval: Option<u32>,
+
+    /// Abort before proving if the serialized input exceeds this number of bytes.
+    #[arg(long, value_name = "BYTES")]
+    max_input_bytes: Option<u64>,
+
+    /// Keep the storage proofs fetched while building the witness in this file, and reuse the
+    /// proofs it already contains, so that an interrupted run can be resumed.
+    #[arg(long, value_name = "FILE")]
+    resume_witness: Option<PathBuf>,
+
+    /// Record the RPC requests made while building the witness to this file, as
+    /// newline-delimited JSON with the RPC url redacted.
+    #[arg(long, value_name = "FILE")]
+    debug_rpc: Option<PathBuf>,
+
+    /// Record the call frames of every transaction while building the witness, and save them
+    /// next to the witness.
+    #[arg(long)]
+    trace: bool,
+
+    /// The chain profiles the chain of the block is looked up in. Binaries embedding SP1 Reth
+    /// replace it to prove blocks of their own chains.
+    #[arg(skip)]
+    registry: Arc<ChainRegistry>,
+}
+
+impl SP1RethArgs {
+    /// Returns the arguments for fetching the witness of the whole block without caching it,
+    /// committing optional outputs or simulating a variant of the block.
+    pub fn new(rpc_url: String, chain: String, block_number: u64) -> Self {
+        Self {
+            rpc_url,
+            chain,
+            block_number,
+            use_cache: false,
+            no_cache: true,
+            prefetch: false,
+            commit_receipts: false,
+            commit_block_oracle: false,
+            attest_canonical: false,
+            prove_invalid: false,
+            sender_hints: false,
+            blocklist: Vec::new(),
+            allowlist: Vec::new(),
+            override_timestamp: None,
+            override_beneficiary: None,
+            override_gas_limit: None,
+            override_prevrandao: None,
+            tx_limit: None,
+            tx_start: None,
+            state_root_interval: None,
+            max_input_bytes: None,
+            resume_witness: None,
+            debug_rpc: None,
+            trace: false,
+            registry: Default::default(),
+        }
+    }
+
+    /// Returns the execution filter described by the arguments, if any.
+    pub fn execution_filter(&self) -> Option<ExecutionFilter> {
+        let (mode, addresses) = if !self.blocklist.is_empty() {
+            (FilterMode::Blocklist, &self.blocklist)
+        } else if !self.allowlist.is_empty() {
+            (FilterMode::Allowlist, &self.allowlist)
+        } else {
+            return None;
+        };
+        Some(ExecutionFilter {
+            mode,
+            addresses: addresses.iter().copied().collect(),
+        })
+    }
+
+    /// Returns the overrides of the block environment described by the arguments, if any.
+    pub fn block_overrides(&self) -> Option<BlockOverrides> {
+        let overrides = BlockOverrides {
+            timestamp: self.override_timestamp,
+            beneficiary: self.override_beneficiary,
+            gas_limit: self.override_gas_limit,
+            prevrandao: self.override_prevrandao,
+        };
+        (!overrides.is_empty()).then_some(overrides)
+    }
 }
 
 #[tokio::main]
 async fn main() {
     // Parse arguments.
-    let args = SP1RethArgs::parse();
+    match Cli::parse().command {
+        Command::Prove(args) => prove(args).await.expect("proving the block failed"),
+        Command::Bisect(args) => match bisect::bisect(&args).await.expect("bisect failed") {
+            Some(block_number) => println!("first diverging block: {}", block_number),
+            None => println!("no diverging block found"),
+        },
+        Command::Capabilities => {
+            let capabilities = Capabilities::collect(VERSION_MESSAGE, &ChainRegistry::default());
+            println!("{}", serde_json::to_string_pretty(&capabilities).unwrap());
+        }
+        Command::Vectors(args) => vectors::generate(&args)
+            .await
+            .expect("generating vectors failed"),
+        Command::Canary(args) => canary::run(&args).await.expect("canary failed"),
+        Command::Inspect(args) => inspect::inspect(&args).expect("inspecting the input failed"),
+    }
+}
+
+/// Generates and verifies a proof of the execution of a block.
+async fn prove(args: SP1RethArgs) -> Result<()> {
+    check_elf()?;
 
     // Get input.
-    let input: SP1RethInput = if !args.use_cache {
+    let started_at = Instant::now();
+    let cache_path = format!("{}.bin", args.block_number);
+    let mut input: SP1RethInput = if args.no_cache {
+        SP1RethInput::initialize(&args).await.unwrap()
+    } else if !args.use_cache {
+        let fetch_started_at = cache::unix_timestamp();
         let input = SP1RethInput::initialize(&args).await.unwrap();
-        let mut file =
-            File::create(format!("{}.bin", args.block_number)).expect("unable to open file");
-        bincode::serialize_into(&mut file, &input).expect("unable to serialize input");
+        let metadata = WitnessMetadata {
+            rpc_url_hash: WitnessMetadata::hash_rpc_url(&args.rpc_url),
+            fetch_started_at,
+            fetch_finished_at: cache::unix_timestamp(),
+            client_version: cache::fetch_client_version(&args.rpc_url).await,
+        };
+        cache::save_input(&cache_path, &input, Some(&metadata)).expect("unable to cache input");
         input
     } else {
-        let file = File::open(format!("{}.bin", args.block_number)).expect("unable to open file");
-        bincode::deserialize_from(file).expect("unable to deserialize input")
+        let (input, metadata) = cache::load_input(&cache_path).expect("unable to load input");
+        if let Some(metadata) = metadata {
+            println!("loaded cached input with provenance {:?}", metadata);
+        }
+        input
     };
+    input.commit_receipts = args.commit_receipts;
+    input.commit_block_oracle = args.commit_block_oracle;
+    input.prove_invalid = args.prove_invalid;
+    if let Some(max_input_bytes) = args.max_input_bytes {
+        size::check_input_size(&input, max_input_bytes)?;
+    }
+    let witness_latency = started_at.elapsed();
 
     // Generate proof.
     sp1_core::utils::setup_logger();
     let mut stdin = SP1Stdin::new();
     stdin.write(&input);
 
+    // Size the shards from the cycle count of the block, unless the shard size is set explicitly.
+    if std::env::var("SHARD_SIZE").is_err() {
+        let cycles = shard::measure_cycles(SP1_RETH_ELF, &stdin);
+        let shard_size = shard::shard_size(cycles);
+        println!(
+            "block takes {} cycles, using shards of {} cycles",
+            cycles, shard_size
+        );
+        std::env::set_var("SHARD_SIZE", shard_size.to_string());
+    }
+
     let config = BabyBearPoseidon2::new();
-    let proof = SP1Prover::prove_with_config(SP1_RETH_ELF, stdin, config).expect("proving failed");
+    let mut proof =
+        SP1Prover::prove_with_config(SP1_RETH_ELF, stdin, config).expect("proving failed");
+    println!(
+        "block {}: witness ready after {:?}, proof generated after {:?}",
+        args.block_number,
+        witness_latency,
+        started_at.elapsed()
+    );
 
     // Verify proof.
     let config = BabyBearPoseidon2::new();
     SP1Verifier::verify_with_config(SP1_RETH_ELF, &proof, config).expect("verification failed");
 
+    // Read the committed versioned public values, whose status and flags describe the outputs
+    // that follow them.
+    let mut encoded = vec![0; PUBLIC_VALUES_LEN];
+    proof.stdout.read_slice(&mut encoded);
+    let public_values = SP1RethPublicValues::abi_decode(&encoded).expect("malformed public values");
+    println!(
+        "public values: {:?} (abi: {})",
+        public_values,
+        Bytes::from(encoded)
+    );
+    let is_valid = public_values.status == BlockStatus::Valid;
+    if !is_valid {
+        let record = proof.stdout.read::<InvalidityRecord>();
+        println!("block proven invalid: {:?}", record);
+    }
+
+    // Read the committed executed transactions and the state roots before and after them.
+    if public_values.has(FLAG_TRANSACTION_RANGE) {
+        let range = proof.stdout.read::<TransactionRange>();
+        println!(
+            "executed the transactions {}..{} of the block with transactions root {}",
+            range.start, range.end, range.transactions_root
+        );
+        println!(
+            "state root before the executed transactions: {}",
+            range.start_state_root
+        );
+        println!(
+            "state root after the executed transactions: {}",
+            range.state_root
+        );
+    }
+
+    // Read the committed receipts.
+    if public_values.has(FLAG_RECEIPTS) {
+        let receipts = proof.stdout.read::<CompactReceipts>();
+        for index in 0..receipts.len() {
+            println!(
+                "tx {}: success={} gas_used={} logs={}",
+                index,
+                receipts.success(index).unwrap(),
+                receipts.gas_used[index],
+                receipts.log_counts[index]
+            );
+        }
+    }
+
+    // Read the committed filter and the transactions it skipped.
+    if public_values.has(FLAG_EXECUTION_FILTER) {
+        let filter = proof.stdout.read::<ExecutionFilter>();
+        println!(
+            "executed with a {:?} of {} addresses",
+            filter.mode,
+            filter.addresses.len()
+        );
+        let skipped = proof.stdout.read::<Vec<SkippedTransaction>>();
+        for tx in skipped {
+            println!("tx {}: skipped ({:?})", tx.index, tx.reason);
+        }
+    }
+
+    // Read the committed intermediate state roots.
+    if public_values.has(FLAG_INTERMEDIATE_STATE_ROOTS) {
+        let state_roots = proof.stdout.read::<Vec<B256>>();
+        let interval = input.state_root_interval.unwrap();
+        for (index, state_root) in state_roots.iter().enumerate() {
+            println!(
+                "state root after {} transactions: {}",
+                (index as u32 + 1) * interval,
+                state_root
+            );
+        }
+    }
+
+    // Read the committed block-hash oracle commitment.
+    if public_values.has(FLAG_BLOCK_ORACLE) {
+        let mut encoded = vec![0; BLOCK_COMMITMENT_LEN];
+        proof.stdout.read_slice(&mut encoded);
+        let commitment = BlockCommitment::abi_decode(&encoded).expect("malformed commitment");
+        println!(
+            "block-hash oracle commitment: {:?} (abi: {})",
+            commitment,
+            Bytes::from(encoded)
+        );
+    }
+
+    // Read the committed hash of the sender hints used.
+    if public_values.has(FLAG_SENDER_HINTS) {
+        let hash = proof.stdout.read::<B256>();
+        println!("sender hints hash: {}", hash);
+    }
+
+    // Read the committed overrides of the block environment.
+    if public_values.has(FLAG_BLOCK_OVERRIDES) {
+        let overrides = proof.stdout.read::<BlockOverrides>();
+        println!("block environment overrides: {:?}", overrides);
+    }
+
     // Save proof.
     proof
         .save("proof-with-io.json")
         .expect("saving proof failed");
 
-    println!("succesfully generated and verified proof for the program!")
+    // Attest whether the committed block hash is canonical.
+    if is_valid && args.attest_canonical {
+        let attestation = CanonicalAttestation::fetch(
+            &args.rpc_url,
+            public_values.block_number,
+            public_values.block_hash,
+        )
+        .await
+        .expect("fetching the canonical block hash failed");
+        println!(
+            "committed block hash matches the canonical chain: {}",
+            attestation.matches_canonical
+        );
+        attestation
+            .save("proof-attestation.json")
+            .expect("saving attestation failed");
+    }
+
+    println!("succesfully generated and verified proof for the program!");
+    Ok(())
 }
@@ -0,0 +1,78 @@
+use alloy_primitives::U128;
+use alloy_providers::provider::{HttpProvider, TempProvider};
+use alloy_rpc_types::{BlockId, Transaction as AlloyTransaction};
+use anyhow::Result;
+use reth_primitives::{
+    Bytes, Signature, Transaction, TransactionKind, TransactionSignedNoHash, TxDeposit, B256,
+};
+use serde::Deserialize;
+use sp1_reth_primitives::chain::{ChainConfig, Hardfork, CREATE2_DEPLOYER_ADDRESS};
+
+/// The transaction type of OP Stack deposit transactions.
+///
+/// Reference: https://specs.optimism.io/protocol/deposits.html
+pub const DEPOSIT_TX_TYPE: u64 = 0x7e;
+
+/// The fields of a deposit transaction that are not part of the standard transaction object.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DepositFields {
+    source_hash: B256,
+    mint: Option<U128>,
+    is_system_tx: Option<bool>,
+}
+
+/// Returns whether the transaction is an OP Stack deposit transaction.
+pub fn is_deposit(tx: &AlloyTransaction) -> bool {
+    tx.transaction_type.map(|ty| ty.to::<u64>()) == Some(DEPOSIT_TX_TYPE)
+}
+
+/// Converts a deposit transaction, fetching the deposit specific fields from the provider.
+pub async fn fetch_deposit(
+    provider: &HttpProvider,
+    tx: AlloyTransaction,
+) -> Result<TransactionSignedNoHash> {
+    let fields: DepositFields = provider
+        .raw_request("eth_getTransactionByHash", (tx.hash,))
+        .await?;
+    let deposit = TxDeposit {
+        source_hash: fields.source_hash,
+        from: tx.from,
+        to: match tx.to {
+            None => TransactionKind::Create,
+            Some(to) => TransactionKind::Call(to),
+        },
+        mint: fields.mint.map(|mint| mint.to::<u128>()),
+        value: tx.value.into(),
+        gas_limit: tx.gas.try_into().unwrap(),
+        is_system_transaction: fields.is_system_tx.unwrap_or_default(),
+        input: tx.input,
+    };
+    Ok(TransactionSignedNoHash {
+        signature: Signature::default(),
+        transaction: Transaction::Deposit(deposit),
+    })
+}
+
+/// Fetches the code of the create2deployer if the block is the first block of Canyon, which
+/// deploys it with code taken from the contracts of the input.
+pub async fn fetch_create2_deployer(
+    provider: &HttpProvider,
+    chain_config: &ChainConfig,
+    block_number: u64,
+    parent_timestamp: u64,
+    timestamp: u64,
+) -> Result<Option<Bytes>> {
+    if !chain_config.is_activation_block(
+        Hardfork::Canyon,
+        block_number - 1,
+        parent_timestamp,
+        timestamp,
+    ) {
+        return Ok(None);
+    }
+    let code = provider
+        .get_code_at(CREATE2_DEPLOYER_ADDRESS, Some(BlockId::from(block_number)))
+        .await?;
+    Ok(Some(code))
+}
@@ -0,0 +1,11 @@
+use alloy_rpc_types::Transaction as AlloyTransaction;
+use reth_primitives::Address;
+
+/// Returns whether the transaction is a Bor state-sync transaction. Bor lists the state syncs of
+/// a sprint as a transaction from and to the zero address, although they are applied through a
+/// system call that is not part of the transactions of the block.
+///
+/// Reference: https://github.com/maticnetwork/bor/blob/master/core/types/bor_receipt.go
+pub fn is_state_sync(tx: &AlloyTransaction) -> bool {
+    tx.from == Address::ZERO && tx.to == Some(Address::ZERO)
+}
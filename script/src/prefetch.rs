@@ -0,0 +1,154 @@
+use alloy_providers::provider::{HttpProvider, TempProvider};
+use alloy_rpc_types::{BlockId, BlockNumberOrTag, BlockTransactions};
+use alloy_transport_http::Http;
+use anyhow::Result;
+use reth_primitives::revm_primitives::{AccountInfo, Bytecode};
+use reth_primitives::{Address, U256};
+use revm::db::InMemoryDB;
+use std::collections::HashSet;
+use std::time::Duration;
+use url::Url;
+
+/// The interval at which the mempool is polled while waiting for the target block.
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// A speculative prefetcher that pre-warms the state touched by pending transactions.
+///
+/// While waiting for a block to be produced, the prefetcher repeatedly inspects the pending block
+/// and fetches the accounts and storage slots its transactions are likely to touch at the parent
+/// block. Once the block lands, the collected state can be used to prime the
+/// [crate::db::RemoteDb], so that building the witness mostly hits local state.
+pub struct Prefetcher {
+    /// The provider to fetch data from.
+    provider: HttpProvider,
+
+    /// The parent block number of the block that is going to be proven.
+    block_number: u64,
+
+    /// The accounts and storage slots fetched so far.
+    db: InMemoryDB,
+
+    /// The accounts that have already been fetched.
+    accounts: HashSet<Address>,
+
+    /// The storage slots that have already been fetched.
+    slots: HashSet<(Address, U256)>,
+}
+
+impl Prefetcher {
+    /// Creates a new prefetcher for the state at the given parent block number.
+    pub fn new(rpc_url: &str, block_number: u64) -> Self {
+        let http = Http::new(Url::parse(rpc_url).expect("invalid rpc url"));
+        Prefetcher {
+            provider: HttpProvider::new(http),
+            block_number,
+            db: InMemoryDB::default(),
+            accounts: HashSet::new(),
+            slots: HashSet::new(),
+        }
+    }
+
+    /// Polls the pending block until the block following `block_number` has been produced,
+    /// prefetching the state touched by pending transactions in the meantime.
+    pub async fn run_until_produced(&mut self) -> Result<()> {
+        loop {
+            let target = self
+                .provider
+                .get_block_by_number((self.block_number + 1).into(), false)
+                .await?;
+            if target.is_some() {
+                return Ok(());
+            }
+
+            // Only prefetch once the parent state is available on the provider.
+            let parent = self
+                .provider
+                .get_block_by_number(self.block_number.into(), false)
+                .await?;
+            if parent.is_some() {
+                let fetched = self.prefetch_pending().await?;
+                if fetched > 0 {
+                    println!("prefetched {} new accounts and slots", fetched);
+                }
+            }
+
+            tokio::time::sleep(POLL_INTERVAL).await;
+        }
+    }
+
+    /// Fetches the accounts and storage slots referenced by the transactions of the pending block
+    /// and returns the number of newly fetched entries.
+    pub async fn prefetch_pending(&mut self) -> Result<usize> {
+        let Some(pending) = self
+            .provider
+            .get_block_by_number(BlockNumberOrTag::Pending, true)
+            .await?
+        else {
+            return Ok(0);
+        };
+        let BlockTransactions::Full(txs) = pending.transactions else {
+            return Ok(0);
+        };
+
+        let mut fetched = 0;
+        for tx in txs {
+            fetched += self.prefetch_account(tx.from).await? as usize;
+            if let Some(to) = tx.to {
+                fetched += self.prefetch_account(to).await? as usize;
+            }
+            for item in tx.access_list.unwrap_or_default() {
+                fetched += self.prefetch_account(item.address).await? as usize;
+                for key in item.storage_keys {
+                    let index = U256::from_be_bytes(key.0);
+                    fetched += self.prefetch_slot(item.address, index).await? as usize;
+                }
+            }
+        }
+        Ok(fetched)
+    }
+
+    /// Consumes the prefetcher and returns the collected state.
+    pub fn into_db(self) -> InMemoryDB {
+        self.db
+    }
+
+    /// Fetches the nonce, balance and code of an account, returning whether it was newly fetched.
+    async fn prefetch_account(&mut self, address: Address) -> Result<bool> {
+        if !self.accounts.insert(address) {
+            return Ok(false);
+        }
+        let block_id = Some(BlockId::from(self.block_number));
+        let nonce = self
+            .provider
+            .get_transaction_count(address, block_id)
+            .await?;
+        let balance = self.provider.get_balance(address, block_id).await?;
+        let code = self.provider.get_code_at(address, block_id).await?;
+
+        let account_info = AccountInfo::new(
+            balance,
+            nonce.try_into().unwrap(),
+            Bytecode::new_raw(code.clone()).hash_slow(),
+            Bytecode::new_raw(code),
+        );
+        self.db.insert_account_info(address, account_info);
+        Ok(true)
+    }
+
+    /// Fetches a storage slot of an account, returning whether it was newly fetched.
+    async fn prefetch_slot(&mut self, address: Address, index: U256) -> Result<bool> {
+        if !self.slots.insert((address, index)) {
+            return Ok(false);
+        }
+        let value = self
+            .provider
+            .get_storage_at(
+                address.into_array().into(),
+                index,
+                Some(BlockId::from(self.block_number)),
+            )
+            .await?;
+        self.db.insert_account_storage(address, index, value)?;
+        Ok(true)
+    }
+}
@@ -0,0 +1,56 @@
+use anyhow::Result;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::fmt::Display;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::sync::Mutex;
+
+/// The placeholder written instead of the RPC url.
+const REDACTED: &str = "<redacted rpc url>";
+
+/// A recorder of the RPC requests made while building a witness, together with their responses.
+///
+/// Every request is written as one JSON object per line, so that failing runs against exotic
+/// providers can be replayed offline. The RPC url commonly embeds an API key, so it is redacted
+/// from every recorded error message.
+pub struct RpcRecorder {
+    /// The RPC url to redact from the recorded payloads.
+    rpc_url: String,
+
+    /// The file the requests are written to.
+    out: Mutex<BufWriter<File>>,
+}
+
+impl RpcRecorder {
+    /// Creates a recorder writing to the file at the given path.
+    pub fn create(path: impl AsRef<Path>, rpc_url: &str) -> Result<Self> {
+        Ok(RpcRecorder {
+            rpc_url: rpc_url.to_string(),
+            out: Mutex::new(BufWriter::new(File::create(path)?)),
+        })
+    }
+
+    /// Records a request and its response.
+    pub fn record<P, R, E>(&self, method: &str, params: &P, response: &Result<R, E>)
+    where
+        P: Serialize,
+        R: Serialize,
+        E: Display,
+    {
+        let outcome = match response {
+            Ok(result) => json!({ "result": result }),
+            Err(err) => json!({ "error": err.to_string().replace(&self.rpc_url, REDACTED) }),
+        };
+        let mut entry = json!({ "method": method, "params": params });
+        if let (Value::Object(entry), Value::Object(outcome)) = (&mut entry, outcome) {
+            entry.extend(outcome);
+        }
+
+        let mut out = self.out.lock().unwrap();
+        if let Err(err) = writeln!(out, "{}", entry).and_then(|_| out.flush()) {
+            println!("failed to record rpc request {}: {}", method, err);
+        }
+    }
+}
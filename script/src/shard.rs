@@ -0,0 +1,51 @@
+use sp1_core::runtime::{Program, Runtime};
+use sp1_core::SP1Stdin;
+
+/// The smallest shard size picked for a block, in cycles.
+const MIN_SHARD_SIZE: u64 = 1 << 18;
+
+/// The largest shard size picked for a block, in cycles.
+const MAX_SHARD_SIZE: u64 = 1 << 22;
+
+/// The number of shards a block is split into, unless the shard size bounds are hit.
+const TARGET_SHARD_COUNT: u64 = 32;
+
+/// Executes the program natively, without proving, and returns the number of cycles it takes.
+pub fn measure_cycles(elf: &[u8], stdin: &SP1Stdin) -> u64 {
+    let mut runtime = Runtime::new(Program::from(elf));
+    runtime.write_vecs(&stdin.buffer);
+    runtime.run();
+    runtime.state.global_clk as u64
+}
+
+/// Returns the shard size for a block of the given number of cycles: a power of two splitting it
+/// into about [TARGET_SHARD_COUNT] shards. Small blocks get small shards to limit padding, while
+/// large blocks get large shards to limit the number of shard proofs.
+pub fn shard_size(cycles: u64) -> u64 {
+    (cycles / TARGET_SHARD_COUNT)
+        .next_power_of_two()
+        .clamp(MIN_SHARD_SIZE, MAX_SHARD_SIZE)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shard_size() {
+        // A block splitting evenly into the target count gets shards of exactly its share.
+        assert_eq!(shard_size(TARGET_SHARD_COUNT << 20), 1 << 20);
+
+        // Otherwise the share is rounded up to the next power of two.
+        assert_eq!(shard_size((TARGET_SHARD_COUNT + 1) << 20), 1 << 21);
+
+        // Small and large blocks hit the bounds, without overflowing for any cycle count.
+        assert_eq!(shard_size(0), MIN_SHARD_SIZE);
+        assert_eq!(shard_size(1_000_000), MIN_SHARD_SIZE);
+        assert_eq!(
+            shard_size(TARGET_SHARD_COUNT * MAX_SHARD_SIZE * 4),
+            MAX_SHARD_SIZE
+        );
+        assert_eq!(shard_size(u64::MAX), MAX_SHARD_SIZE);
+    }
+}
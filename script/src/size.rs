@@ -0,0 +1,102 @@
+use anyhow::{bail, Result};
+use bincode::serialized_size;
+use reth_primitives::Address;
+use serde::{Serialize, Serializer};
+use sp1_reth_primitives::mpt::{MptNode, StorageEntry};
+use sp1_reth_primitives::{store, SP1RethInput};
+use std::cmp::Reverse;
+use std::collections::HashMap;
+
+/// The number of largest parts of the input reported when it exceeds the size limit.
+const REPORTED_PARTS: usize = 10;
+
+/// The state trie of the input, serialized through its node store as in the input.
+struct StateTrie<'a>(&'a MptNode);
+
+impl Serialize for StateTrie<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        store::trie::serialize(self.0, serializer)
+    }
+}
+
+/// The storage tries of the input, serialized through their shared node store as in the input.
+struct Storage<'a>(&'a HashMap<Address, StorageEntry>);
+
+impl Serialize for Storage<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        store::storage::serialize(self.0, serializer)
+    }
+}
+
+/// Checks that the serialized input does not exceed the given number of bytes, so that proving
+/// does not start for a witness that would exhaust the memory of the machine hours later.
+///
+/// The error lists the largest parts of the input and how to prove the block with a smaller one.
+pub fn check_input_size(input: &SP1RethInput, max_bytes: u64) -> Result<()> {
+    let size = serialized_size(input)?;
+    if size <= max_bytes {
+        return Ok(());
+    }
+
+    let mut parts = vec![
+        (
+            "state trie".to_string(),
+            serialized_size(&StateTrie(&input.parent_state_trie))?,
+        ),
+        (
+            "storage tries".to_string(),
+            serialized_size(&Storage(&input.parent_storage))?,
+        ),
+        ("contracts".to_string(), serialized_size(&input.contracts)?),
+        (
+            "ancestor headers".to_string(),
+            serialized_size(&input.ancestor_headers)?,
+        ),
+        (
+            "transactions".to_string(),
+            serialized_size(&input.transactions)?,
+        ),
+    ];
+    parts.extend(largest_storage(&input.parent_storage)?);
+    parts.sort_by_key(|(_, size)| Reverse(*size));
+
+    let breakdown = parts
+        .iter()
+        .take(REPORTED_PARTS)
+        .map(|(name, size)| format!("  {}: {} bytes", name, size))
+        .collect::<Vec<_>>()
+        .join("\n");
+    bail!(
+        "input of {} bytes exceeds the limit of {} bytes. Largest parts:\n{}\n\
+         Prove a prefix of the block with --tx-limit, or exclude the transactions touching the \
+         largest accounts with --blocklist, to reduce the witness",
+        size,
+        max_bytes,
+        breakdown
+    )
+}
+
+/// Returns the accounts whose storage takes the most space in the store shared by the storage
+/// tries, with the number of bytes the store shrinks by without them.
+///
+/// Nodes shared with the storage of other accounts are written to the store once, so the size of
+/// the storage of an account alone overstates what leaving it out saves. The saving is measured
+/// for the accounts with the largest storage alone, which bounds it from above.
+fn largest_storage(storage: &HashMap<Address, StorageEntry>) -> Result<Vec<(String, u64)>> {
+    let mut standalone = Vec::with_capacity(storage.len());
+    for (address, entry) in storage {
+        let alone = HashMap::from([(*address, entry.clone())]);
+        standalone.push((*address, serialized_size(&Storage(&alone))?));
+    }
+    standalone.sort_by_key(|(_, size)| Reverse(*size));
+
+    let total = serialized_size(&Storage(storage))?;
+    let mut parts = Vec::new();
+    for (address, _) in standalone.into_iter().take(REPORTED_PARTS) {
+        let mut others = storage.clone();
+        others.remove(&address);
+        let saved = total.saturating_sub(serialized_size(&Storage(&others))?);
+        parts.push((format!("storage of {} not shared", address), saved));
+    }
+    Ok(parts)
+}
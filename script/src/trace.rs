@@ -0,0 +1,216 @@
+use anyhow::Result;
+use reth_primitives::{Address, Bytes, B256, U256};
+use revm::interpreter::{
+    CallInputs, CallOutcome, CallScheme, CreateInputs, CreateOutcome, InterpreterResult,
+};
+use revm::primitives::CreateScheme;
+use revm::{Database, EvmContext, Inspector};
+use serde::Serialize;
+use sp1_reth_primitives::chain::SYSTEM_ADDRESS;
+use sp1_reth_primitives::filter::SkippedTransaction;
+use sp1_reth_primitives::SP1RethInput;
+use std::fs::File;
+use std::path::Path;
+
+/// The kind of a call frame.
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum CallKind {
+    Call,
+    CallCode,
+    DelegateCall,
+    StaticCall,
+    Create,
+    Create2,
+}
+
+/// A call frame of a transaction, together with the frames of its subcalls.
+#[derive(Debug, Clone, Serialize)]
+pub struct CallFrame {
+    /// The kind of the frame.
+    pub kind: CallKind,
+
+    /// The caller of the frame.
+    pub from: Address,
+
+    /// The called account, or the created contract if the creation succeeded.
+    pub to: Option<Address>,
+
+    /// The value transferred by the frame.
+    pub value: U256,
+
+    /// The gas limit of the frame.
+    pub gas: u64,
+
+    /// The gas spent by the frame, including its subcalls.
+    pub gas_used: u64,
+
+    /// The call data, or the init code of a creation.
+    pub input: Bytes,
+
+    /// The returned data, or the deployed code of a creation.
+    pub output: Bytes,
+
+    /// The result of the frame if it did not succeed.
+    pub error: Option<String>,
+
+    /// The frames of the subcalls, in the order they were made.
+    pub calls: Vec<CallFrame>,
+}
+
+/// The call frames of an executed transaction.
+#[derive(Debug, Clone, Serialize)]
+pub struct TransactionTrace {
+    /// The index of the transaction in the block.
+    pub transaction_index: u32,
+
+    /// The hash of the transaction.
+    pub transaction_hash: B256,
+
+    /// The top-level frame of the transaction.
+    pub frame: CallFrame,
+}
+
+/// An inspector recording the call frames of every transaction executed by the EVM.
+#[derive(Debug, Default)]
+pub struct CallTracer {
+    /// The frames entered but not exited yet, innermost last.
+    stack: Vec<CallFrame>,
+
+    /// The exited top-level frames, in the order they were executed.
+    frames: Vec<CallFrame>,
+}
+
+impl CallTracer {
+    /// Pairs the recorded top-level frames with the transactions of the input that were executed,
+    /// leaving out the system calls of the block.
+    pub fn into_traces(
+        self,
+        input: &SP1RethInput,
+        skipped_transactions: &[SkippedTransaction],
+    ) -> Vec<TransactionTrace> {
+        let executed = input
+            .transactions
+            .iter()
+            .enumerate()
+            .skip(input.transaction_start.unwrap_or_default() as usize)
+            .filter(|(index, _)| {
+                !skipped_transactions
+                    .iter()
+                    .any(|skipped| skipped.index as usize == *index)
+            });
+        self.frames
+            .into_iter()
+            .filter(|frame| frame.from != SYSTEM_ADDRESS)
+            .zip(executed)
+            .map(|(frame, (index, tx))| TransactionTrace {
+                transaction_index: index.try_into().unwrap(),
+                transaction_hash: tx.hash(),
+                frame,
+            })
+            .collect()
+    }
+
+    /// Opens a frame nested in the innermost open frame.
+    fn enter(&mut self, frame: CallFrame) {
+        self.stack.push(frame);
+    }
+
+    /// Closes the innermost open frame with the result of its execution.
+    fn exit(&mut self, result: &InterpreterResult, created: Option<Address>) {
+        let Some(mut frame) = self.stack.pop() else {
+            return;
+        };
+        frame.gas_used = result.gas.spend();
+        frame.output = result.output.clone();
+        if !result.result.is_ok() {
+            frame.error = Some(format!("{:?}", result.result));
+        }
+        if created.is_some() {
+            frame.to = created;
+        }
+        match self.stack.last_mut() {
+            Some(parent) => parent.calls.push(frame),
+            None => self.frames.push(frame),
+        }
+    }
+}
+
+impl<DB: Database> Inspector<DB> for CallTracer {
+    fn call(
+        &mut self,
+        _context: &mut EvmContext<DB>,
+        inputs: &mut CallInputs,
+    ) -> Option<CallOutcome> {
+        let kind = match inputs.context.scheme {
+            CallScheme::Call => CallKind::Call,
+            CallScheme::CallCode => CallKind::CallCode,
+            CallScheme::DelegateCall => CallKind::DelegateCall,
+            CallScheme::StaticCall => CallKind::StaticCall,
+        };
+        self.enter(CallFrame {
+            kind,
+            from: inputs.context.caller,
+            to: Some(inputs.contract),
+            value: inputs.transfer.value,
+            gas: inputs.gas_limit,
+            gas_used: 0,
+            input: inputs.input.clone(),
+            output: Bytes::new(),
+            error: None,
+            calls: Vec::new(),
+        });
+        None
+    }
+
+    fn call_end(
+        &mut self,
+        _context: &mut EvmContext<DB>,
+        _inputs: &CallInputs,
+        outcome: CallOutcome,
+    ) -> CallOutcome {
+        self.exit(&outcome.result, None);
+        outcome
+    }
+
+    fn create(
+        &mut self,
+        _context: &mut EvmContext<DB>,
+        inputs: &mut CreateInputs,
+    ) -> Option<CreateOutcome> {
+        let kind = match inputs.scheme {
+            CreateScheme::Create => CallKind::Create,
+            CreateScheme::Create2 { .. } => CallKind::Create2,
+        };
+        self.enter(CallFrame {
+            kind,
+            from: inputs.caller,
+            to: None,
+            value: inputs.value,
+            gas: inputs.gas_limit,
+            gas_used: 0,
+            input: inputs.init_code.clone(),
+            output: Bytes::new(),
+            error: None,
+            calls: Vec::new(),
+        });
+        None
+    }
+
+    fn create_end(
+        &mut self,
+        _context: &mut EvmContext<DB>,
+        _inputs: &CreateInputs,
+        outcome: CreateOutcome,
+    ) -> CreateOutcome {
+        self.exit(&outcome.result, outcome.address);
+        outcome
+    }
+}
+
+/// Saves the traces as JSON to the given path.
+pub fn save(path: impl AsRef<Path>, traces: &[TransactionTrace]) -> Result<()> {
+    let file = File::create(path)?;
+    serde_json::to_writer_pretty(file, traces)?;
+    Ok(())
+}
@@ -0,0 +1,123 @@
+//! Generation of test vectors, so that verifiers written outside of this crate can be tested
+//! against inputs, public values and proofs produced by it.
+
+use crate::cache;
+use crate::init::SP1RethInputInitializer;
+use crate::{check_elf, SP1RethArgs, SP1_RETH_ELF};
+use anyhow::Result;
+use clap::Args;
+use serde::Serialize;
+use sp1_core::{utils::BabyBearPoseidon2, SP1Prover, SP1Stdin, SP1Verifier};
+use sp1_reth_primitives::SP1RethInput;
+use std::fs::File;
+use std::path::PathBuf;
+
+/// The CLI arguments for generating test vectors from a block.
+#[derive(Args, Debug, Clone)]
+pub struct VectorsArgs {
+    #[arg(short, long)]
+    rpc_url: String,
+
+    /// The name of the chain profile the block belongs to.
+    #[arg(long, default_value = "mainnet")]
+    chain: String,
+
+    #[arg(short, long)]
+    block_number: u64,
+
+    /// The directory the vectors are written to.
+    #[arg(long, default_value = "vectors")]
+    out_dir: PathBuf,
+}
+
+/// The description of a test vector, written to the manifest of the output directory.
+#[derive(Debug, Serialize)]
+struct Vector {
+    /// The name of the vector, which is also the name of its directory.
+    name: &'static str,
+
+    /// The name of the chain profile of the block.
+    chain: String,
+
+    /// The number of the block.
+    block_number: u64,
+
+    /// Whether the compact receipts of the block are committed.
+    commit_receipts: bool,
+
+    /// Whether the block is committed in the ABI of block-hash oracles.
+    commit_block_oracle: bool,
+
+    /// The number of transactions executed, if only a prefix of the block is executed.
+    transaction_limit: Option<u32>,
+}
+
+/// Generates a test vector for each set of outputs the program can commit, proving the given
+/// block once per vector.
+///
+/// Every vector is written to its own directory, as the cached input and the proof with its
+/// public values, and a `manifest.json` lists the vectors.
+pub async fn generate(args: &VectorsArgs) -> Result<()> {
+    check_elf()?;
+    let block_args = SP1RethArgs::new(args.rpc_url.clone(), args.chain.clone(), args.block_number);
+    let block_input = SP1RethInput::initialize(&block_args).await?;
+    let tx_count = u32::try_from(block_input.transactions.len())?;
+
+    let mut vectors = vec![
+        ("block", block_input.clone()),
+        (
+            "receipts",
+            SP1RethInput {
+                commit_receipts: true,
+                ..block_input.clone()
+            },
+        ),
+        (
+            "oracle",
+            SP1RethInput {
+                commit_block_oracle: true,
+                ..block_input
+            },
+        ),
+    ];
+    // The witness of a prefix differs from the one of the block, so it is fetched again.
+    if tx_count >= 2 {
+        let prefix_args = SP1RethArgs {
+            tx_limit: Some(tx_count / 2),
+            ..block_args
+        };
+        vectors.push(("prefix", SP1RethInput::initialize(&prefix_args).await?));
+    }
+
+    let mut manifest = Vec::new();
+    for (name, input) in vectors {
+        println!("generating vector {}", name);
+        let dir = args.out_dir.join(name);
+        std::fs::create_dir_all(&dir)?;
+        cache::save_input(dir.join("input.bin"), &input, None)?;
+
+        let mut stdin = SP1Stdin::new();
+        stdin.write(&input);
+        let config = BabyBearPoseidon2::new();
+        let proof =
+            SP1Prover::prove_with_config(SP1_RETH_ELF, stdin, config).expect("proving failed");
+        let config = BabyBearPoseidon2::new();
+        SP1Verifier::verify_with_config(SP1_RETH_ELF, &proof, config).expect("verification failed");
+        proof
+            .save(dir.join("proof-with-io.json").to_str().unwrap())
+            .expect("saving proof failed");
+
+        manifest.push(Vector {
+            name,
+            chain: args.chain.clone(),
+            block_number: args.block_number,
+            commit_receipts: input.commit_receipts,
+            commit_block_oracle: input.commit_block_oracle,
+            transaction_limit: input.transaction_limit,
+        });
+    }
+
+    let file = File::create(args.out_dir.join("manifest.json"))?;
+    serde_json::to_writer_pretty(file, &manifest)?;
+    Ok(())
+}